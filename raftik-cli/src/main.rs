@@ -1,6 +1,13 @@
 use std::env;
 
-use raftik_core::{ast::ModuleParsed, validation::validate_module};
+use raftik_core::{
+    ast::{
+        CustomSection, ModuleParsed, Section,
+        custom::{ProducersSection, TargetFeaturesSection},
+    },
+    text::print_module,
+    validation::validate_module,
+};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
@@ -12,8 +19,49 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let wasm_file = &args[1];
     let data = std::fs::read(wasm_file)?;
     let module = ModuleParsed::from_slice(&data)?;
-    println!("{:#?}", module);
+    print!("{}", print_module(&module));
+    for section in &module.sections {
+        if let Section::Custom(custom) = section {
+            print_tool_metadata(custom);
+        }
+    }
     validate_module(&module)?;
     println!("validation succeeded");
     Ok(())
 }
+
+/// Shows the `producers` and `target_features` sections as `;;` comments, so that the output
+/// stays valid text; other custom sections are skipped.
+fn print_tool_metadata(custom: &CustomSection) {
+    match custom.producers() {
+        Some(Ok(producers)) => print_producers(&producers),
+        Some(Err(e)) => eprintln!("malformed producers section: {}", e),
+        None => (),
+    }
+    match custom.target_features() {
+        Some(Ok(features)) => print_target_features(&features),
+        Some(Err(e)) => eprintln!("malformed target_features section: {}", e),
+        None => (),
+    }
+}
+
+fn print_producers(producers: &ProducersSection) {
+    println!(";; producers:");
+    for field in &producers.fields {
+        let values: Vec<String> = field
+            .values
+            .iter()
+            .map(|v| format!("{} {}", v.name, v.version).trim_end().to_string())
+            .collect();
+        println!(";;   {}: {}", field.name, values.join(", "));
+    }
+}
+
+fn print_target_features(features: &TargetFeaturesSection) {
+    let features: Vec<String> = features
+        .features
+        .iter()
+        .map(|f| format!("{}{}", f.prefix.byte() as char, f.name))
+        .collect();
+    println!(";; target features: {}", features.join(" "));
+}
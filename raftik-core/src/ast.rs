@@ -1,19 +1,56 @@
+pub mod custom;
 pub mod instructions;
 pub mod section;
 pub mod types;
 
 pub use section::{
     CodeSection, CustomSection, DataCountSection, DataSection, ElementSection, ExportSection,
-    FunctionSection, GlobalSection, ImportSection, MemorySection, Section, StartSection,
-    TableSection, TypeSection,
+    FunctionSection, GlobalSection, ImportSection, LazyCodeSection, MemorySection, Section,
+    StartSection, TableSection, TypeSection,
 };
 
-#[derive(Debug, PartialEq, Eq, Default)]
+/// The version and layer fields which follow the magic number.
+/// Core modules are layer 0; component-model binaries are layer 1.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BinaryVersion {
+    pub version: u16,
+    pub layer: u16,
+}
+
+impl BinaryVersion {
+    pub const CORE_MODULE: BinaryVersion = BinaryVersion {
+        version: 1,
+        layer: 0,
+    };
+}
+
+impl Default for BinaryVersion {
+    fn default() -> Self {
+        Self::CORE_MODULE
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
 pub struct ModuleParsed<'a> {
+    /// Always [`BinaryVersion::CORE_MODULE`] for parsed modules. Component-model binaries fail
+    /// with [`ComponentBinary`](crate::binary::error::ParseErrorKind::ComponentBinary)
+    /// and other versions with
+    /// [`UnsupportedVersion`](crate::binary::error::ParseErrorKind::UnsupportedVersion).
+    pub version: BinaryVersion,
     pub sections: Vec<Section<'a>>,
 }
 
+/// A module which owns all of its bytes, independent of the input buffer.
+pub type ModuleOwned = ModuleParsed<'static>;
+
 impl<'a> ModuleParsed<'a> {
+    pub fn into_owned(self) -> ModuleOwned {
+        ModuleParsed {
+            version: self.version,
+            sections: self.sections.into_iter().map(Section::into_owned).collect(),
+        }
+    }
+
     pub fn sec_by_id(&self, id: section::SectionID) -> Option<&Section<'a>> {
         self.sections.iter().find(|s| s.id() == id)
     }
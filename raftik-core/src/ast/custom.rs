@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+/// Names keyed by the index of the item they belong to.
+pub type NameMap = BTreeMap<u32, String>;
+
+/// Name maps keyed by the index of a function, e.g. local names of each function.
+pub type IndirectNameMap = BTreeMap<u32, NameMap>;
+
+/// Debug names decoded from the `name` custom section, including the subsections
+/// of the extended name section proposal.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct NameSection {
+    pub module: Option<String>,
+    pub functions: NameMap,
+    pub locals: IndirectNameMap,
+    pub labels: IndirectNameMap,
+    pub types: NameMap,
+    pub tables: NameMap,
+    pub memories: NameMap,
+    pub globals: NameMap,
+    pub elements: NameMap,
+    pub data: NameMap,
+}
+
+impl NameSection {
+    pub fn function(&self, index: u32) -> Option<&str> {
+        self.functions.get(&index).map(String::as_str)
+    }
+
+    pub fn local(&self, function_index: u32, local_index: u32) -> Option<&str> {
+        self.locals
+            .get(&function_index)?
+            .get(&local_index)
+            .map(String::as_str)
+    }
+
+    /// `$name` if the function has a name, otherwise `function #index`.
+    pub fn describe_function(&self, index: u32) -> String {
+        match self.function(index) {
+            Some(name) => format!("${}", name),
+            None => format!("function #{}", index),
+        }
+    }
+}
+
+/// The `producers` custom section, listing the tools which produced the module.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct ProducersSection {
+    pub fields: Vec<ProducersField>,
+}
+
+impl ProducersSection {
+    /// The field with the given name, such as `language`, `processed-by` or `sdk`.
+    pub fn field(&self, name: &str) -> Option<&ProducersField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ProducersField {
+    pub name: String,
+    pub values: Vec<ProducerVersion>,
+}
+
+/// A language or tool name with its version, e.g. `rustc` and `1.80.0`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ProducerVersion {
+    pub name: String,
+    pub version: String,
+}
+
+/// The `target_features` custom section, recording the features used when compiling.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct TargetFeaturesSection {
+    pub features: Vec<TargetFeature>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TargetFeature {
+    pub prefix: FeaturePrefix,
+    pub name: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FeaturePrefix {
+    /// `+`: the feature is used by the module.
+    Used,
+    /// `-`: the feature must not be used by modules linked with this one.
+    Disallowed,
+    /// `=`: the feature is required by modules linked with this one.
+    Required,
+}
+
+impl FeaturePrefix {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            b'+' => Some(FeaturePrefix::Used),
+            b'-' => Some(FeaturePrefix::Disallowed),
+            b'=' => Some(FeaturePrefix::Required),
+            _ => None,
+        }
+    }
+
+    pub fn byte(&self) -> u8 {
+        match self {
+            FeaturePrefix::Used => b'+',
+            FeaturePrefix::Disallowed => b'-',
+            FeaturePrefix::Required => b'=',
+        }
+    }
+}
@@ -1,12 +1,58 @@
-use super::types::ReferenceType;
+mod vector;
 
-#[derive(Debug, PartialEq, Eq)]
+use std::borrow::Cow;
+
+pub use vector::{VectorLaneOp, VectorMemoryLaneOp, VectorMemoryOp, VectorOp};
+
+use super::types::{NumberType, ReferenceType, ValueType};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct RawExpression<'a> {
-    pub instructions: &'a [u8],
+    pub instructions: Cow<'a, [u8]>,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+impl RawExpression<'_> {
+    pub fn into_owned(self) -> RawExpression<'static> {
+        RawExpression {
+            instructions: Cow::Owned(self.instructions.into_owned()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BlockType {
+    Empty,
+    Value(ValueType),
+    TypeIndex(u32),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MemArg {
+    /// alignment exponent: the access is aligned to `2^align` bytes
+    pub align: u32,
+    pub offset: u32,
+    pub memory_index: u32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum Opcode {
+    Unreachable,
+    Nop,
+    Block(BlockType),
+    Loop(BlockType),
+    If(BlockType),
+    Else,
+    End,
+    Br(u32),
+    BrIf(u32),
+    BrTable(Vec<u32>, u32),
+    Return,
+    Call(u32),
+    // type index, table index
+    CallIndirect(u32, u32),
+    Drop,
+    Select,
+    SelectTyped(Vec<ValueType>),
     LocalGet(u32),
     LocalSet(u32),
     LocalTee(u32),
@@ -19,15 +65,45 @@ pub enum Opcode {
     RefNull(ReferenceType),
     RefIsNull,
     RefFunc(u32),
-    I32Add,
+    TableGet(u32),
+    TableSet(u32),
+    // element index, table index
+    TableInit(u32, u32),
+    ElemDrop(u32),
+    // destination table index, source table index
+    TableCopy(u32, u32),
+    TableGrow(u32),
+    TableSize(u32),
+    TableFill(u32),
+    Memory(MemoryOp, MemArg),
+    MemorySize(u32),
+    MemoryGrow(u32),
+    // data index, memory index
+    MemoryInit(u32, u32),
+    DataDrop(u32),
+    // destination memory index, source memory index
+    MemoryCopy(u32, u32),
+    MemoryFill(u32),
+    Numeric(NumericOp),
+    V128Const([u8; 16]),
+    I8x16Shuffle([u8; 16]),
+    VectorLane(VectorLaneOp, u8),
+    VectorMemory(VectorMemoryOp, MemArg),
+    VectorMemoryLane(VectorMemoryLaneOp, MemArg, u8),
+    Vector(VectorOp),
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum OpcodeCategory {
+    Control,
+    Parametric,
     Variable,
     Reference,
+    Table,
+    Memory,
     NumericConst,
     Numeric,
+    Vector,
 }
 
 impl Opcode {
@@ -39,6 +115,7 @@ impl Opcode {
                 | Opcode::I64Const(..)
                 | Opcode::F32Const(..)
                 | Opcode::F64Const(..)
+                | Opcode::V128Const(..)
                 | Opcode::RefNull(..)
                 | Opcode::RefFunc(..)
         )
@@ -46,6 +123,20 @@ impl Opcode {
 
     pub fn category(&self) -> OpcodeCategory {
         match self {
+            Opcode::Unreachable
+            | Opcode::Nop
+            | Opcode::Block(_)
+            | Opcode::Loop(_)
+            | Opcode::If(_)
+            | Opcode::Else
+            | Opcode::End
+            | Opcode::Br(_)
+            | Opcode::BrIf(_)
+            | Opcode::BrTable(..)
+            | Opcode::Return
+            | Opcode::Call(_)
+            | Opcode::CallIndirect(..) => OpcodeCategory::Control,
+            Opcode::Drop | Opcode::Select | Opcode::SelectTyped(_) => OpcodeCategory::Parametric,
             Opcode::LocalGet(_)
             | Opcode::LocalSet(_)
             | Opcode::LocalTee(_)
@@ -58,7 +149,327 @@ impl Opcode {
             Opcode::RefFunc(_) | Opcode::RefNull(_) | Opcode::RefIsNull => {
                 OpcodeCategory::Reference
             }
-            Opcode::I32Add => OpcodeCategory::Numeric,
+            Opcode::TableGet(_)
+            | Opcode::TableSet(_)
+            | Opcode::TableInit(..)
+            | Opcode::ElemDrop(_)
+            | Opcode::TableCopy(..)
+            | Opcode::TableGrow(_)
+            | Opcode::TableSize(_)
+            | Opcode::TableFill(_) => OpcodeCategory::Table,
+            Opcode::Memory(..)
+            | Opcode::MemorySize(_)
+            | Opcode::MemoryGrow(_)
+            | Opcode::MemoryInit(..)
+            | Opcode::DataDrop(_)
+            | Opcode::MemoryCopy(..)
+            | Opcode::MemoryFill(_) => OpcodeCategory::Memory,
+            Opcode::Numeric(_) => OpcodeCategory::Numeric,
+            Opcode::V128Const(_)
+            | Opcode::I8x16Shuffle(_)
+            | Opcode::VectorLane(..)
+            | Opcode::VectorMemory(..)
+            | Opcode::VectorMemoryLane(..)
+            | Opcode::Vector(_) => OpcodeCategory::Vector,
         }
     }
 }
+
+/// Binary encoding of an opcode: a single byte, or a prefix byte followed by a u32 sub-opcode
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OpcodeEncoding {
+    Single(u8),
+    Prefixed(u8, u32),
+}
+
+/// Declares numeric instructions with their encoding, text name and stack signature,
+/// so that parsing and validation are derived from the same table.
+/// Single-byte opcodes come first; 0xFC-prefixed ones follow after `;`.
+macro_rules! numeric_instructions {
+    (
+        $($byte:literal => $op:ident $name:literal [$($param:ident)*] -> [$($result:ident)*],)+
+        ;
+        $(0xFC $sub:literal => $fc_op:ident $fc_name:literal [$($fc_param:ident)*] -> [$($fc_result:ident)*],)*
+    ) => {
+        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+        pub enum NumericOp {
+            $($op,)+
+            $($fc_op,)*
+        }
+
+        impl NumericOp {
+            pub fn from_byte(byte: u8) -> Option<Self> {
+                match byte {
+                    $($byte => Some(NumericOp::$op),)+
+                    _ => None,
+                }
+            }
+
+            pub fn from_fc_subopcode(subopcode: u32) -> Option<Self> {
+                match subopcode {
+                    $($sub => Some(NumericOp::$fc_op),)*
+                    _ => None,
+                }
+            }
+
+            pub fn encoding(&self) -> OpcodeEncoding {
+                match self {
+                    $(NumericOp::$op => OpcodeEncoding::Single($byte),)+
+                    $(NumericOp::$fc_op => OpcodeEncoding::Prefixed(0xFC, $sub),)*
+                }
+            }
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(NumericOp::$op => $name,)+
+                    $(NumericOp::$fc_op => $fc_name,)*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some(NumericOp::$op),)+
+                    $($fc_name => Some(NumericOp::$fc_op),)*
+                    _ => None,
+                }
+            }
+
+            pub fn params(&self) -> &'static [NumberType] {
+                match self {
+                    $(NumericOp::$op => &[$(NumberType::$param),*],)+
+                    $(NumericOp::$fc_op => &[$(NumberType::$fc_param),*],)*
+                }
+            }
+
+            pub fn results(&self) -> &'static [NumberType] {
+                match self {
+                    $(NumericOp::$op => &[$(NumberType::$result),*],)+
+                    $(NumericOp::$fc_op => &[$(NumberType::$fc_result),*],)*
+                }
+            }
+        }
+    };
+}
+
+numeric_instructions! {
+    0x45 => I32Eqz "i32.eqz" [I32] -> [I32],
+    0x46 => I32Eq "i32.eq" [I32 I32] -> [I32],
+    0x47 => I32Ne "i32.ne" [I32 I32] -> [I32],
+    0x48 => I32LtS "i32.lt_s" [I32 I32] -> [I32],
+    0x49 => I32LtU "i32.lt_u" [I32 I32] -> [I32],
+    0x4A => I32GtS "i32.gt_s" [I32 I32] -> [I32],
+    0x4B => I32GtU "i32.gt_u" [I32 I32] -> [I32],
+    0x4C => I32LeS "i32.le_s" [I32 I32] -> [I32],
+    0x4D => I32LeU "i32.le_u" [I32 I32] -> [I32],
+    0x4E => I32GeS "i32.ge_s" [I32 I32] -> [I32],
+    0x4F => I32GeU "i32.ge_u" [I32 I32] -> [I32],
+    0x50 => I64Eqz "i64.eqz" [I64] -> [I32],
+    0x51 => I64Eq "i64.eq" [I64 I64] -> [I32],
+    0x52 => I64Ne "i64.ne" [I64 I64] -> [I32],
+    0x53 => I64LtS "i64.lt_s" [I64 I64] -> [I32],
+    0x54 => I64LtU "i64.lt_u" [I64 I64] -> [I32],
+    0x55 => I64GtS "i64.gt_s" [I64 I64] -> [I32],
+    0x56 => I64GtU "i64.gt_u" [I64 I64] -> [I32],
+    0x57 => I64LeS "i64.le_s" [I64 I64] -> [I32],
+    0x58 => I64LeU "i64.le_u" [I64 I64] -> [I32],
+    0x59 => I64GeS "i64.ge_s" [I64 I64] -> [I32],
+    0x5A => I64GeU "i64.ge_u" [I64 I64] -> [I32],
+    0x5B => F32Eq "f32.eq" [F32 F32] -> [I32],
+    0x5C => F32Ne "f32.ne" [F32 F32] -> [I32],
+    0x5D => F32Lt "f32.lt" [F32 F32] -> [I32],
+    0x5E => F32Gt "f32.gt" [F32 F32] -> [I32],
+    0x5F => F32Le "f32.le" [F32 F32] -> [I32],
+    0x60 => F32Ge "f32.ge" [F32 F32] -> [I32],
+    0x61 => F64Eq "f64.eq" [F64 F64] -> [I32],
+    0x62 => F64Ne "f64.ne" [F64 F64] -> [I32],
+    0x63 => F64Lt "f64.lt" [F64 F64] -> [I32],
+    0x64 => F64Gt "f64.gt" [F64 F64] -> [I32],
+    0x65 => F64Le "f64.le" [F64 F64] -> [I32],
+    0x66 => F64Ge "f64.ge" [F64 F64] -> [I32],
+    0x67 => I32Clz "i32.clz" [I32] -> [I32],
+    0x68 => I32Ctz "i32.ctz" [I32] -> [I32],
+    0x69 => I32Popcnt "i32.popcnt" [I32] -> [I32],
+    0x6A => I32Add "i32.add" [I32 I32] -> [I32],
+    0x6B => I32Sub "i32.sub" [I32 I32] -> [I32],
+    0x6C => I32Mul "i32.mul" [I32 I32] -> [I32],
+    0x6D => I32DivS "i32.div_s" [I32 I32] -> [I32],
+    0x6E => I32DivU "i32.div_u" [I32 I32] -> [I32],
+    0x6F => I32RemS "i32.rem_s" [I32 I32] -> [I32],
+    0x70 => I32RemU "i32.rem_u" [I32 I32] -> [I32],
+    0x71 => I32And "i32.and" [I32 I32] -> [I32],
+    0x72 => I32Or "i32.or" [I32 I32] -> [I32],
+    0x73 => I32Xor "i32.xor" [I32 I32] -> [I32],
+    0x74 => I32Shl "i32.shl" [I32 I32] -> [I32],
+    0x75 => I32ShrS "i32.shr_s" [I32 I32] -> [I32],
+    0x76 => I32ShrU "i32.shr_u" [I32 I32] -> [I32],
+    0x77 => I32Rotl "i32.rotl" [I32 I32] -> [I32],
+    0x78 => I32Rotr "i32.rotr" [I32 I32] -> [I32],
+    0x79 => I64Clz "i64.clz" [I64] -> [I64],
+    0x7A => I64Ctz "i64.ctz" [I64] -> [I64],
+    0x7B => I64Popcnt "i64.popcnt" [I64] -> [I64],
+    0x7C => I64Add "i64.add" [I64 I64] -> [I64],
+    0x7D => I64Sub "i64.sub" [I64 I64] -> [I64],
+    0x7E => I64Mul "i64.mul" [I64 I64] -> [I64],
+    0x7F => I64DivS "i64.div_s" [I64 I64] -> [I64],
+    0x80 => I64DivU "i64.div_u" [I64 I64] -> [I64],
+    0x81 => I64RemS "i64.rem_s" [I64 I64] -> [I64],
+    0x82 => I64RemU "i64.rem_u" [I64 I64] -> [I64],
+    0x83 => I64And "i64.and" [I64 I64] -> [I64],
+    0x84 => I64Or "i64.or" [I64 I64] -> [I64],
+    0x85 => I64Xor "i64.xor" [I64 I64] -> [I64],
+    0x86 => I64Shl "i64.shl" [I64 I64] -> [I64],
+    0x87 => I64ShrS "i64.shr_s" [I64 I64] -> [I64],
+    0x88 => I64ShrU "i64.shr_u" [I64 I64] -> [I64],
+    0x89 => I64Rotl "i64.rotl" [I64 I64] -> [I64],
+    0x8A => I64Rotr "i64.rotr" [I64 I64] -> [I64],
+    0x8B => F32Abs "f32.abs" [F32] -> [F32],
+    0x8C => F32Neg "f32.neg" [F32] -> [F32],
+    0x8D => F32Ceil "f32.ceil" [F32] -> [F32],
+    0x8E => F32Floor "f32.floor" [F32] -> [F32],
+    0x8F => F32Trunc "f32.trunc" [F32] -> [F32],
+    0x90 => F32Nearest "f32.nearest" [F32] -> [F32],
+    0x91 => F32Sqrt "f32.sqrt" [F32] -> [F32],
+    0x92 => F32Add "f32.add" [F32 F32] -> [F32],
+    0x93 => F32Sub "f32.sub" [F32 F32] -> [F32],
+    0x94 => F32Mul "f32.mul" [F32 F32] -> [F32],
+    0x95 => F32Div "f32.div" [F32 F32] -> [F32],
+    0x96 => F32Min "f32.min" [F32 F32] -> [F32],
+    0x97 => F32Max "f32.max" [F32 F32] -> [F32],
+    0x98 => F32Copysign "f32.copysign" [F32 F32] -> [F32],
+    0x99 => F64Abs "f64.abs" [F64] -> [F64],
+    0x9A => F64Neg "f64.neg" [F64] -> [F64],
+    0x9B => F64Ceil "f64.ceil" [F64] -> [F64],
+    0x9C => F64Floor "f64.floor" [F64] -> [F64],
+    0x9D => F64Trunc "f64.trunc" [F64] -> [F64],
+    0x9E => F64Nearest "f64.nearest" [F64] -> [F64],
+    0x9F => F64Sqrt "f64.sqrt" [F64] -> [F64],
+    0xA0 => F64Add "f64.add" [F64 F64] -> [F64],
+    0xA1 => F64Sub "f64.sub" [F64 F64] -> [F64],
+    0xA2 => F64Mul "f64.mul" [F64 F64] -> [F64],
+    0xA3 => F64Div "f64.div" [F64 F64] -> [F64],
+    0xA4 => F64Min "f64.min" [F64 F64] -> [F64],
+    0xA5 => F64Max "f64.max" [F64 F64] -> [F64],
+    0xA6 => F64Copysign "f64.copysign" [F64 F64] -> [F64],
+    0xA7 => I32WrapI64 "i32.wrap_i64" [I64] -> [I32],
+    0xA8 => I32TruncF32S "i32.trunc_f32_s" [F32] -> [I32],
+    0xA9 => I32TruncF32U "i32.trunc_f32_u" [F32] -> [I32],
+    0xAA => I32TruncF64S "i32.trunc_f64_s" [F64] -> [I32],
+    0xAB => I32TruncF64U "i32.trunc_f64_u" [F64] -> [I32],
+    0xAC => I64ExtendI32S "i64.extend_i32_s" [I32] -> [I64],
+    0xAD => I64ExtendI32U "i64.extend_i32_u" [I32] -> [I64],
+    0xAE => I64TruncF32S "i64.trunc_f32_s" [F32] -> [I64],
+    0xAF => I64TruncF32U "i64.trunc_f32_u" [F32] -> [I64],
+    0xB0 => I64TruncF64S "i64.trunc_f64_s" [F64] -> [I64],
+    0xB1 => I64TruncF64U "i64.trunc_f64_u" [F64] -> [I64],
+    0xB2 => F32ConvertI32S "f32.convert_i32_s" [I32] -> [F32],
+    0xB3 => F32ConvertI32U "f32.convert_i32_u" [I32] -> [F32],
+    0xB4 => F32ConvertI64S "f32.convert_i64_s" [I64] -> [F32],
+    0xB5 => F32ConvertI64U "f32.convert_i64_u" [I64] -> [F32],
+    0xB6 => F32DemoteF64 "f32.demote_f64" [F64] -> [F32],
+    0xB7 => F64ConvertI32S "f64.convert_i32_s" [I32] -> [F64],
+    0xB8 => F64ConvertI32U "f64.convert_i32_u" [I32] -> [F64],
+    0xB9 => F64ConvertI64S "f64.convert_i64_s" [I64] -> [F64],
+    0xBA => F64ConvertI64U "f64.convert_i64_u" [I64] -> [F64],
+    0xBB => F64PromoteF32 "f64.promote_f32" [F32] -> [F64],
+    0xBC => I32ReinterpretF32 "i32.reinterpret_f32" [F32] -> [I32],
+    0xBD => I64ReinterpretF64 "i64.reinterpret_f64" [F64] -> [I64],
+    0xBE => F32ReinterpretI32 "f32.reinterpret_i32" [I32] -> [F32],
+    0xBF => F64ReinterpretI64 "f64.reinterpret_i64" [I64] -> [F64],
+    0xC0 => I32Extend8S "i32.extend8_s" [I32] -> [I32],
+    0xC1 => I32Extend16S "i32.extend16_s" [I32] -> [I32],
+    0xC2 => I64Extend8S "i64.extend8_s" [I64] -> [I64],
+    0xC3 => I64Extend16S "i64.extend16_s" [I64] -> [I64],
+    0xC4 => I64Extend32S "i64.extend32_s" [I64] -> [I64],
+    ;
+    0xFC 0 => I32TruncSatF32S "i32.trunc_sat_f32_s" [F32] -> [I32],
+    0xFC 1 => I32TruncSatF32U "i32.trunc_sat_f32_u" [F32] -> [I32],
+    0xFC 2 => I32TruncSatF64S "i32.trunc_sat_f64_s" [F64] -> [I32],
+    0xFC 3 => I32TruncSatF64U "i32.trunc_sat_f64_u" [F64] -> [I32],
+    0xFC 4 => I64TruncSatF32S "i64.trunc_sat_f32_s" [F32] -> [I64],
+    0xFC 5 => I64TruncSatF32U "i64.trunc_sat_f32_u" [F32] -> [I64],
+    0xFC 6 => I64TruncSatF64S "i64.trunc_sat_f64_s" [F64] -> [I64],
+    0xFC 7 => I64TruncSatF64U "i64.trunc_sat_f64_u" [F64] -> [I64],
+}
+
+/// Declares load and store instructions with their encoding, text name, natural alignment
+/// (as an exponent of 2) and stack signature.
+macro_rules! memory_instructions {
+    ($($byte:literal => $op:ident $name:literal align $align:literal [$($param:ident)*] -> [$($result:ident)*]),+ $(,)?) => {
+        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+        pub enum MemoryOp {
+            $($op,)+
+        }
+
+        impl MemoryOp {
+            pub fn from_byte(byte: u8) -> Option<Self> {
+                match byte {
+                    $($byte => Some(MemoryOp::$op),)+
+                    _ => None,
+                }
+            }
+
+            pub fn byte(&self) -> u8 {
+                match self {
+                    $(MemoryOp::$op => $byte,)+
+                }
+            }
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(MemoryOp::$op => $name,)+
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some(MemoryOp::$op),)+
+                    _ => None,
+                }
+            }
+
+            pub fn natural_alignment(&self) -> u32 {
+                match self {
+                    $(MemoryOp::$op => $align,)+
+                }
+            }
+
+            pub fn params(&self) -> &'static [NumberType] {
+                match self {
+                    $(MemoryOp::$op => &[$(NumberType::$param),*],)+
+                }
+            }
+
+            pub fn results(&self) -> &'static [NumberType] {
+                match self {
+                    $(MemoryOp::$op => &[$(NumberType::$result),*],)+
+                }
+            }
+        }
+    };
+}
+
+memory_instructions! {
+    0x28 => I32Load "i32.load" align 2 [I32] -> [I32],
+    0x29 => I64Load "i64.load" align 3 [I32] -> [I64],
+    0x2A => F32Load "f32.load" align 2 [I32] -> [F32],
+    0x2B => F64Load "f64.load" align 3 [I32] -> [F64],
+    0x2C => I32Load8S "i32.load8_s" align 0 [I32] -> [I32],
+    0x2D => I32Load8U "i32.load8_u" align 0 [I32] -> [I32],
+    0x2E => I32Load16S "i32.load16_s" align 1 [I32] -> [I32],
+    0x2F => I32Load16U "i32.load16_u" align 1 [I32] -> [I32],
+    0x30 => I64Load8S "i64.load8_s" align 0 [I32] -> [I64],
+    0x31 => I64Load8U "i64.load8_u" align 0 [I32] -> [I64],
+    0x32 => I64Load16S "i64.load16_s" align 1 [I32] -> [I64],
+    0x33 => I64Load16U "i64.load16_u" align 1 [I32] -> [I64],
+    0x34 => I64Load32S "i64.load32_s" align 2 [I32] -> [I64],
+    0x35 => I64Load32U "i64.load32_u" align 2 [I32] -> [I64],
+    0x36 => I32Store "i32.store" align 2 [I32 I32] -> [],
+    0x37 => I64Store "i64.store" align 3 [I32 I64] -> [],
+    0x38 => F32Store "f32.store" align 2 [I32 F32] -> [],
+    0x39 => F64Store "f64.store" align 3 [I32 F64] -> [],
+    0x3A => I32Store8 "i32.store8" align 0 [I32 I32] -> [],
+    0x3B => I32Store16 "i32.store16" align 1 [I32 I32] -> [],
+    0x3C => I64Store8 "i64.store8" align 0 [I32 I64] -> [],
+    0x3D => I64Store16 "i64.store16" align 1 [I32 I64] -> [],
+    0x3E => I64Store32 "i64.store32" align 2 [I32 I64] -> [],
+}
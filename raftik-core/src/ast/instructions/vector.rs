@@ -0,0 +1,337 @@
+use super::OpcodeEncoding;
+use crate::ast::types::{NumberType, ValueType, VectorType};
+
+macro_rules! value_type {
+    (V128) => {
+        ValueType::Vector(VectorType::V128)
+    };
+    ($t:ident) => {
+        ValueType::Number(NumberType::$t)
+    };
+}
+
+/// Declares 0xFD-prefixed instructions with their sub-opcode, text name and stack signature.
+/// An optional extra column (e.g. natural alignment or lane count) becomes a method.
+macro_rules! vector_instructions {
+    (@common $ty:ident $($sub:literal => $op:ident $name:literal [$($param:ident)*] -> [$($result:ident)*],)+) => {
+        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+        pub enum $ty {
+            $($op,)+
+        }
+
+        impl $ty {
+            pub fn from_subopcode(subopcode: u32) -> Option<Self> {
+                match subopcode {
+                    $($sub => Some($ty::$op),)+
+                    _ => None,
+                }
+            }
+
+            pub fn encoding(&self) -> OpcodeEncoding {
+                match self {
+                    $($ty::$op => OpcodeEncoding::Prefixed(0xFD, $sub),)+
+                }
+            }
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($ty::$op => $name,)+
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some($ty::$op),)+
+                    _ => None,
+                }
+            }
+
+            pub fn params(&self) -> &'static [ValueType] {
+                match self {
+                    $($ty::$op => &[$(value_type!($param)),*],)+
+                }
+            }
+
+            pub fn results(&self) -> &'static [ValueType] {
+                match self {
+                    $($ty::$op => &[$(value_type!($result)),*],)+
+                }
+            }
+        }
+    };
+    ($ty:ident { $($sub:literal => $op:ident $name:literal [$($param:ident)*] -> [$($result:ident)*],)+ }) => {
+        vector_instructions!(@common $ty $($sub => $op $name [$($param)*] -> [$($result)*],)+);
+    };
+    ($ty:ident, $extra:ident { $($sub:literal => $op:ident $name:literal $value:literal [$($param:ident)*] -> [$($result:ident)*],)+ }) => {
+        vector_instructions!(@common $ty $($sub => $op $name [$($param)*] -> [$($result)*],)+);
+
+        impl $ty {
+            pub fn $extra(&self) -> u32 {
+                match self {
+                    $($ty::$op => $value,)+
+                }
+            }
+        }
+    };
+}
+
+vector_instructions! {
+    VectorOp {
+        0x0E => I8x16Swizzle "i8x16.swizzle" [V128 V128] -> [V128],
+        0x0F => I8x16Splat "i8x16.splat" [I32] -> [V128],
+        0x10 => I16x8Splat "i16x8.splat" [I32] -> [V128],
+        0x11 => I32x4Splat "i32x4.splat" [I32] -> [V128],
+        0x12 => I64x2Splat "i64x2.splat" [I64] -> [V128],
+        0x13 => F32x4Splat "f32x4.splat" [F32] -> [V128],
+        0x14 => F64x2Splat "f64x2.splat" [F64] -> [V128],
+        0x23 => I8x16Eq "i8x16.eq" [V128 V128] -> [V128],
+        0x24 => I8x16Ne "i8x16.ne" [V128 V128] -> [V128],
+        0x25 => I8x16LtS "i8x16.lt_s" [V128 V128] -> [V128],
+        0x26 => I8x16LtU "i8x16.lt_u" [V128 V128] -> [V128],
+        0x27 => I8x16GtS "i8x16.gt_s" [V128 V128] -> [V128],
+        0x28 => I8x16GtU "i8x16.gt_u" [V128 V128] -> [V128],
+        0x29 => I8x16LeS "i8x16.le_s" [V128 V128] -> [V128],
+        0x2A => I8x16LeU "i8x16.le_u" [V128 V128] -> [V128],
+        0x2B => I8x16GeS "i8x16.ge_s" [V128 V128] -> [V128],
+        0x2C => I8x16GeU "i8x16.ge_u" [V128 V128] -> [V128],
+        0x2D => I16x8Eq "i16x8.eq" [V128 V128] -> [V128],
+        0x2E => I16x8Ne "i16x8.ne" [V128 V128] -> [V128],
+        0x2F => I16x8LtS "i16x8.lt_s" [V128 V128] -> [V128],
+        0x30 => I16x8LtU "i16x8.lt_u" [V128 V128] -> [V128],
+        0x31 => I16x8GtS "i16x8.gt_s" [V128 V128] -> [V128],
+        0x32 => I16x8GtU "i16x8.gt_u" [V128 V128] -> [V128],
+        0x33 => I16x8LeS "i16x8.le_s" [V128 V128] -> [V128],
+        0x34 => I16x8LeU "i16x8.le_u" [V128 V128] -> [V128],
+        0x35 => I16x8GeS "i16x8.ge_s" [V128 V128] -> [V128],
+        0x36 => I16x8GeU "i16x8.ge_u" [V128 V128] -> [V128],
+        0x37 => I32x4Eq "i32x4.eq" [V128 V128] -> [V128],
+        0x38 => I32x4Ne "i32x4.ne" [V128 V128] -> [V128],
+        0x39 => I32x4LtS "i32x4.lt_s" [V128 V128] -> [V128],
+        0x3A => I32x4LtU "i32x4.lt_u" [V128 V128] -> [V128],
+        0x3B => I32x4GtS "i32x4.gt_s" [V128 V128] -> [V128],
+        0x3C => I32x4GtU "i32x4.gt_u" [V128 V128] -> [V128],
+        0x3D => I32x4LeS "i32x4.le_s" [V128 V128] -> [V128],
+        0x3E => I32x4LeU "i32x4.le_u" [V128 V128] -> [V128],
+        0x3F => I32x4GeS "i32x4.ge_s" [V128 V128] -> [V128],
+        0x40 => I32x4GeU "i32x4.ge_u" [V128 V128] -> [V128],
+        0x41 => F32x4Eq "f32x4.eq" [V128 V128] -> [V128],
+        0x42 => F32x4Ne "f32x4.ne" [V128 V128] -> [V128],
+        0x43 => F32x4Lt "f32x4.lt" [V128 V128] -> [V128],
+        0x44 => F32x4Gt "f32x4.gt" [V128 V128] -> [V128],
+        0x45 => F32x4Le "f32x4.le" [V128 V128] -> [V128],
+        0x46 => F32x4Ge "f32x4.ge" [V128 V128] -> [V128],
+        0x47 => F64x2Eq "f64x2.eq" [V128 V128] -> [V128],
+        0x48 => F64x2Ne "f64x2.ne" [V128 V128] -> [V128],
+        0x49 => F64x2Lt "f64x2.lt" [V128 V128] -> [V128],
+        0x4A => F64x2Gt "f64x2.gt" [V128 V128] -> [V128],
+        0x4B => F64x2Le "f64x2.le" [V128 V128] -> [V128],
+        0x4C => F64x2Ge "f64x2.ge" [V128 V128] -> [V128],
+        0x4D => V128Not "v128.not" [V128] -> [V128],
+        0x4E => V128And "v128.and" [V128 V128] -> [V128],
+        0x4F => V128Andnot "v128.andnot" [V128 V128] -> [V128],
+        0x50 => V128Or "v128.or" [V128 V128] -> [V128],
+        0x51 => V128Xor "v128.xor" [V128 V128] -> [V128],
+        0x52 => V128Bitselect "v128.bitselect" [V128 V128 V128] -> [V128],
+        0x53 => V128AnyTrue "v128.any_true" [V128] -> [I32],
+        0x5E => F32x4DemoteF64x2Zero "f32x4.demote_f64x2_zero" [V128] -> [V128],
+        0x5F => F64x2PromoteLowF32x4 "f64x2.promote_low_f32x4" [V128] -> [V128],
+        0x60 => I8x16Abs "i8x16.abs" [V128] -> [V128],
+        0x61 => I8x16Neg "i8x16.neg" [V128] -> [V128],
+        0x62 => I8x16Popcnt "i8x16.popcnt" [V128] -> [V128],
+        0x63 => I8x16AllTrue "i8x16.all_true" [V128] -> [I32],
+        0x64 => I8x16Bitmask "i8x16.bitmask" [V128] -> [I32],
+        0x65 => I8x16NarrowI16x8S "i8x16.narrow_i16x8_s" [V128 V128] -> [V128],
+        0x66 => I8x16NarrowI16x8U "i8x16.narrow_i16x8_u" [V128 V128] -> [V128],
+        0x67 => F32x4Ceil "f32x4.ceil" [V128] -> [V128],
+        0x68 => F32x4Floor "f32x4.floor" [V128] -> [V128],
+        0x69 => F32x4Trunc "f32x4.trunc" [V128] -> [V128],
+        0x6A => F32x4Nearest "f32x4.nearest" [V128] -> [V128],
+        0x6B => I8x16Shl "i8x16.shl" [V128 I32] -> [V128],
+        0x6C => I8x16ShrS "i8x16.shr_s" [V128 I32] -> [V128],
+        0x6D => I8x16ShrU "i8x16.shr_u" [V128 I32] -> [V128],
+        0x6E => I8x16Add "i8x16.add" [V128 V128] -> [V128],
+        0x6F => I8x16AddSatS "i8x16.add_sat_s" [V128 V128] -> [V128],
+        0x70 => I8x16AddSatU "i8x16.add_sat_u" [V128 V128] -> [V128],
+        0x71 => I8x16Sub "i8x16.sub" [V128 V128] -> [V128],
+        0x72 => I8x16SubSatS "i8x16.sub_sat_s" [V128 V128] -> [V128],
+        0x73 => I8x16SubSatU "i8x16.sub_sat_u" [V128 V128] -> [V128],
+        0x74 => F64x2Ceil "f64x2.ceil" [V128] -> [V128],
+        0x75 => F64x2Floor "f64x2.floor" [V128] -> [V128],
+        0x76 => I8x16MinS "i8x16.min_s" [V128 V128] -> [V128],
+        0x77 => I8x16MinU "i8x16.min_u" [V128 V128] -> [V128],
+        0x78 => I8x16MaxS "i8x16.max_s" [V128 V128] -> [V128],
+        0x79 => I8x16MaxU "i8x16.max_u" [V128 V128] -> [V128],
+        0x7A => F64x2Trunc "f64x2.trunc" [V128] -> [V128],
+        0x7B => I8x16AvgrU "i8x16.avgr_u" [V128 V128] -> [V128],
+        0x7C => I16x8ExtaddPairwiseI8x16S "i16x8.extadd_pairwise_i8x16_s" [V128] -> [V128],
+        0x7D => I16x8ExtaddPairwiseI8x16U "i16x8.extadd_pairwise_i8x16_u" [V128] -> [V128],
+        0x7E => I32x4ExtaddPairwiseI16x8S "i32x4.extadd_pairwise_i16x8_s" [V128] -> [V128],
+        0x7F => I32x4ExtaddPairwiseI16x8U "i32x4.extadd_pairwise_i16x8_u" [V128] -> [V128],
+        0x80 => I16x8Abs "i16x8.abs" [V128] -> [V128],
+        0x81 => I16x8Neg "i16x8.neg" [V128] -> [V128],
+        0x82 => I16x8Q15mulrSatS "i16x8.q15mulr_sat_s" [V128 V128] -> [V128],
+        0x83 => I16x8AllTrue "i16x8.all_true" [V128] -> [I32],
+        0x84 => I16x8Bitmask "i16x8.bitmask" [V128] -> [I32],
+        0x85 => I16x8NarrowI32x4S "i16x8.narrow_i32x4_s" [V128 V128] -> [V128],
+        0x86 => I16x8NarrowI32x4U "i16x8.narrow_i32x4_u" [V128 V128] -> [V128],
+        0x87 => I16x8ExtendLowI8x16S "i16x8.extend_low_i8x16_s" [V128] -> [V128],
+        0x88 => I16x8ExtendHighI8x16S "i16x8.extend_high_i8x16_s" [V128] -> [V128],
+        0x89 => I16x8ExtendLowI8x16U "i16x8.extend_low_i8x16_u" [V128] -> [V128],
+        0x8A => I16x8ExtendHighI8x16U "i16x8.extend_high_i8x16_u" [V128] -> [V128],
+        0x8B => I16x8Shl "i16x8.shl" [V128 I32] -> [V128],
+        0x8C => I16x8ShrS "i16x8.shr_s" [V128 I32] -> [V128],
+        0x8D => I16x8ShrU "i16x8.shr_u" [V128 I32] -> [V128],
+        0x8E => I16x8Add "i16x8.add" [V128 V128] -> [V128],
+        0x8F => I16x8AddSatS "i16x8.add_sat_s" [V128 V128] -> [V128],
+        0x90 => I16x8AddSatU "i16x8.add_sat_u" [V128 V128] -> [V128],
+        0x91 => I16x8Sub "i16x8.sub" [V128 V128] -> [V128],
+        0x92 => I16x8SubSatS "i16x8.sub_sat_s" [V128 V128] -> [V128],
+        0x93 => I16x8SubSatU "i16x8.sub_sat_u" [V128 V128] -> [V128],
+        0x94 => F64x2Nearest "f64x2.nearest" [V128] -> [V128],
+        0x95 => I16x8Mul "i16x8.mul" [V128 V128] -> [V128],
+        0x96 => I16x8MinS "i16x8.min_s" [V128 V128] -> [V128],
+        0x97 => I16x8MinU "i16x8.min_u" [V128 V128] -> [V128],
+        0x98 => I16x8MaxS "i16x8.max_s" [V128 V128] -> [V128],
+        0x99 => I16x8MaxU "i16x8.max_u" [V128 V128] -> [V128],
+        0x9B => I16x8AvgrU "i16x8.avgr_u" [V128 V128] -> [V128],
+        0x9C => I16x8ExtmulLowI8x16S "i16x8.extmul_low_i8x16_s" [V128 V128] -> [V128],
+        0x9D => I16x8ExtmulHighI8x16S "i16x8.extmul_high_i8x16_s" [V128 V128] -> [V128],
+        0x9E => I16x8ExtmulLowI8x16U "i16x8.extmul_low_i8x16_u" [V128 V128] -> [V128],
+        0x9F => I16x8ExtmulHighI8x16U "i16x8.extmul_high_i8x16_u" [V128 V128] -> [V128],
+        0xA0 => I32x4Abs "i32x4.abs" [V128] -> [V128],
+        0xA1 => I32x4Neg "i32x4.neg" [V128] -> [V128],
+        0xA3 => I32x4AllTrue "i32x4.all_true" [V128] -> [I32],
+        0xA4 => I32x4Bitmask "i32x4.bitmask" [V128] -> [I32],
+        0xA7 => I32x4ExtendLowI16x8S "i32x4.extend_low_i16x8_s" [V128] -> [V128],
+        0xA8 => I32x4ExtendHighI16x8S "i32x4.extend_high_i16x8_s" [V128] -> [V128],
+        0xA9 => I32x4ExtendLowI16x8U "i32x4.extend_low_i16x8_u" [V128] -> [V128],
+        0xAA => I32x4ExtendHighI16x8U "i32x4.extend_high_i16x8_u" [V128] -> [V128],
+        0xAB => I32x4Shl "i32x4.shl" [V128 I32] -> [V128],
+        0xAC => I32x4ShrS "i32x4.shr_s" [V128 I32] -> [V128],
+        0xAD => I32x4ShrU "i32x4.shr_u" [V128 I32] -> [V128],
+        0xAE => I32x4Add "i32x4.add" [V128 V128] -> [V128],
+        0xB1 => I32x4Sub "i32x4.sub" [V128 V128] -> [V128],
+        0xB5 => I32x4Mul "i32x4.mul" [V128 V128] -> [V128],
+        0xB6 => I32x4MinS "i32x4.min_s" [V128 V128] -> [V128],
+        0xB7 => I32x4MinU "i32x4.min_u" [V128 V128] -> [V128],
+        0xB8 => I32x4MaxS "i32x4.max_s" [V128 V128] -> [V128],
+        0xB9 => I32x4MaxU "i32x4.max_u" [V128 V128] -> [V128],
+        0xBA => I32x4DotI16x8S "i32x4.dot_i16x8_s" [V128 V128] -> [V128],
+        0xBC => I32x4ExtmulLowI16x8S "i32x4.extmul_low_i16x8_s" [V128 V128] -> [V128],
+        0xBD => I32x4ExtmulHighI16x8S "i32x4.extmul_high_i16x8_s" [V128 V128] -> [V128],
+        0xBE => I32x4ExtmulLowI16x8U "i32x4.extmul_low_i16x8_u" [V128 V128] -> [V128],
+        0xBF => I32x4ExtmulHighI16x8U "i32x4.extmul_high_i16x8_u" [V128 V128] -> [V128],
+        0xC0 => I64x2Abs "i64x2.abs" [V128] -> [V128],
+        0xC1 => I64x2Neg "i64x2.neg" [V128] -> [V128],
+        0xC3 => I64x2AllTrue "i64x2.all_true" [V128] -> [I32],
+        0xC4 => I64x2Bitmask "i64x2.bitmask" [V128] -> [I32],
+        0xC7 => I64x2ExtendLowI32x4S "i64x2.extend_low_i32x4_s" [V128] -> [V128],
+        0xC8 => I64x2ExtendHighI32x4S "i64x2.extend_high_i32x4_s" [V128] -> [V128],
+        0xC9 => I64x2ExtendLowI32x4U "i64x2.extend_low_i32x4_u" [V128] -> [V128],
+        0xCA => I64x2ExtendHighI32x4U "i64x2.extend_high_i32x4_u" [V128] -> [V128],
+        0xCB => I64x2Shl "i64x2.shl" [V128 I32] -> [V128],
+        0xCC => I64x2ShrS "i64x2.shr_s" [V128 I32] -> [V128],
+        0xCD => I64x2ShrU "i64x2.shr_u" [V128 I32] -> [V128],
+        0xCE => I64x2Add "i64x2.add" [V128 V128] -> [V128],
+        0xD1 => I64x2Sub "i64x2.sub" [V128 V128] -> [V128],
+        0xD5 => I64x2Mul "i64x2.mul" [V128 V128] -> [V128],
+        0xD6 => I64x2Eq "i64x2.eq" [V128 V128] -> [V128],
+        0xD7 => I64x2Ne "i64x2.ne" [V128 V128] -> [V128],
+        0xD8 => I64x2LtS "i64x2.lt_s" [V128 V128] -> [V128],
+        0xD9 => I64x2GtS "i64x2.gt_s" [V128 V128] -> [V128],
+        0xDA => I64x2LeS "i64x2.le_s" [V128 V128] -> [V128],
+        0xDB => I64x2GeS "i64x2.ge_s" [V128 V128] -> [V128],
+        0xDC => I64x2ExtmulLowI32x4S "i64x2.extmul_low_i32x4_s" [V128 V128] -> [V128],
+        0xDD => I64x2ExtmulHighI32x4S "i64x2.extmul_high_i32x4_s" [V128 V128] -> [V128],
+        0xDE => I64x2ExtmulLowI32x4U "i64x2.extmul_low_i32x4_u" [V128 V128] -> [V128],
+        0xDF => I64x2ExtmulHighI32x4U "i64x2.extmul_high_i32x4_u" [V128 V128] -> [V128],
+        0xE0 => F32x4Abs "f32x4.abs" [V128] -> [V128],
+        0xE1 => F32x4Neg "f32x4.neg" [V128] -> [V128],
+        0xE3 => F32x4Sqrt "f32x4.sqrt" [V128] -> [V128],
+        0xE4 => F32x4Add "f32x4.add" [V128 V128] -> [V128],
+        0xE5 => F32x4Sub "f32x4.sub" [V128 V128] -> [V128],
+        0xE6 => F32x4Mul "f32x4.mul" [V128 V128] -> [V128],
+        0xE7 => F32x4Div "f32x4.div" [V128 V128] -> [V128],
+        0xE8 => F32x4Min "f32x4.min" [V128 V128] -> [V128],
+        0xE9 => F32x4Max "f32x4.max" [V128 V128] -> [V128],
+        0xEA => F32x4Pmin "f32x4.pmin" [V128 V128] -> [V128],
+        0xEB => F32x4Pmax "f32x4.pmax" [V128 V128] -> [V128],
+        0xEC => F64x2Abs "f64x2.abs" [V128] -> [V128],
+        0xED => F64x2Neg "f64x2.neg" [V128] -> [V128],
+        0xEF => F64x2Sqrt "f64x2.sqrt" [V128] -> [V128],
+        0xF0 => F64x2Add "f64x2.add" [V128 V128] -> [V128],
+        0xF1 => F64x2Sub "f64x2.sub" [V128 V128] -> [V128],
+        0xF2 => F64x2Mul "f64x2.mul" [V128 V128] -> [V128],
+        0xF3 => F64x2Div "f64x2.div" [V128 V128] -> [V128],
+        0xF4 => F64x2Min "f64x2.min" [V128 V128] -> [V128],
+        0xF5 => F64x2Max "f64x2.max" [V128 V128] -> [V128],
+        0xF6 => F64x2Pmin "f64x2.pmin" [V128 V128] -> [V128],
+        0xF7 => F64x2Pmax "f64x2.pmax" [V128 V128] -> [V128],
+        0xF8 => I32x4TruncSatF32x4S "i32x4.trunc_sat_f32x4_s" [V128] -> [V128],
+        0xF9 => I32x4TruncSatF32x4U "i32x4.trunc_sat_f32x4_u" [V128] -> [V128],
+        0xFA => F32x4ConvertI32x4S "f32x4.convert_i32x4_s" [V128] -> [V128],
+        0xFB => F32x4ConvertI32x4U "f32x4.convert_i32x4_u" [V128] -> [V128],
+        0xFC => I32x4TruncSatF64x2SZero "i32x4.trunc_sat_f64x2_s_zero" [V128] -> [V128],
+        0xFD => I32x4TruncSatF64x2UZero "i32x4.trunc_sat_f64x2_u_zero" [V128] -> [V128],
+        0xFE => F64x2ConvertLowI32x4S "f64x2.convert_low_i32x4_s" [V128] -> [V128],
+        0xFF => F64x2ConvertLowI32x4U "f64x2.convert_low_i32x4_u" [V128] -> [V128],
+    }
+}
+
+vector_instructions! {
+    VectorMemoryOp, natural_alignment {
+        0x00 => V128Load "v128.load" 4 [I32] -> [V128],
+        0x01 => V128Load8x8S "v128.load8x8_s" 3 [I32] -> [V128],
+        0x02 => V128Load8x8U "v128.load8x8_u" 3 [I32] -> [V128],
+        0x03 => V128Load16x4S "v128.load16x4_s" 3 [I32] -> [V128],
+        0x04 => V128Load16x4U "v128.load16x4_u" 3 [I32] -> [V128],
+        0x05 => V128Load32x2S "v128.load32x2_s" 3 [I32] -> [V128],
+        0x06 => V128Load32x2U "v128.load32x2_u" 3 [I32] -> [V128],
+        0x07 => V128Load8Splat "v128.load8_splat" 0 [I32] -> [V128],
+        0x08 => V128Load16Splat "v128.load16_splat" 1 [I32] -> [V128],
+        0x09 => V128Load32Splat "v128.load32_splat" 2 [I32] -> [V128],
+        0x0A => V128Load64Splat "v128.load64_splat" 3 [I32] -> [V128],
+        0x0B => V128Store "v128.store" 4 [I32 V128] -> [],
+        0x5C => V128Load32Zero "v128.load32_zero" 2 [I32] -> [V128],
+        0x5D => V128Load64Zero "v128.load64_zero" 3 [I32] -> [V128],
+    }
+}
+
+vector_instructions! {
+    VectorMemoryLaneOp, natural_alignment {
+        0x54 => V128Load8Lane "v128.load8_lane" 0 [I32 V128] -> [V128],
+        0x55 => V128Load16Lane "v128.load16_lane" 1 [I32 V128] -> [V128],
+        0x56 => V128Load32Lane "v128.load32_lane" 2 [I32 V128] -> [V128],
+        0x57 => V128Load64Lane "v128.load64_lane" 3 [I32 V128] -> [V128],
+        0x58 => V128Store8Lane "v128.store8_lane" 0 [I32 V128] -> [],
+        0x59 => V128Store16Lane "v128.store16_lane" 1 [I32 V128] -> [],
+        0x5A => V128Store32Lane "v128.store32_lane" 2 [I32 V128] -> [],
+        0x5B => V128Store64Lane "v128.store64_lane" 3 [I32 V128] -> [],
+    }
+}
+
+vector_instructions! {
+    VectorLaneOp, lanes {
+        0x15 => I8x16ExtractLaneS "i8x16.extract_lane_s" 16 [V128] -> [I32],
+        0x16 => I8x16ExtractLaneU "i8x16.extract_lane_u" 16 [V128] -> [I32],
+        0x17 => I8x16ReplaceLane "i8x16.replace_lane" 16 [V128 I32] -> [V128],
+        0x18 => I16x8ExtractLaneS "i16x8.extract_lane_s" 8 [V128] -> [I32],
+        0x19 => I16x8ExtractLaneU "i16x8.extract_lane_u" 8 [V128] -> [I32],
+        0x1A => I16x8ReplaceLane "i16x8.replace_lane" 8 [V128 I32] -> [V128],
+        0x1B => I32x4ExtractLane "i32x4.extract_lane" 4 [V128] -> [I32],
+        0x1C => I32x4ReplaceLane "i32x4.replace_lane" 4 [V128 I32] -> [V128],
+        0x1D => I64x2ExtractLane "i64x2.extract_lane" 2 [V128] -> [I64],
+        0x1E => I64x2ReplaceLane "i64x2.replace_lane" 2 [V128 I64] -> [V128],
+        0x1F => F32x4ExtractLane "f32x4.extract_lane" 4 [V128] -> [F32],
+        0x20 => F32x4ReplaceLane "f32x4.replace_lane" 4 [V128 F32] -> [V128],
+        0x21 => F64x2ExtractLane "f64x2.extract_lane" 2 [V128] -> [F64],
+        0x22 => F64x2ReplaceLane "f64x2.replace_lane" 2 [V128 F64] -> [V128],
+    }
+}
+
+impl VectorMemoryLaneOp {
+    /// Number of lanes, derived from the lane width which equals the natural alignment.
+    pub fn lanes(&self) -> u32 {
+        16 >> self.natural_alignment()
+    }
+}
@@ -1,10 +1,15 @@
+use std::{borrow::Cow, cell::OnceCell, ops::Range};
+
 use super::types::ValueType;
-use crate::ast::{
-    instructions::RawExpression,
-    types::{FunctionType, GlobalType, MemoryType, ReferenceType, TableType},
+use crate::{
+    ast::{
+        instructions::RawExpression,
+        types::{FunctionType, GlobalType, MemoryType, ReferenceType, TableType},
+    },
+    binary::error::ParseError,
 };
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Section<'a> {
     Type(TypeSection),
     Import(ImportSection),
@@ -16,29 +21,31 @@ pub enum Section<'a> {
     Start(StartSection),
     Element(ElementSection<'a>),
     Code(CodeSection<'a>),
+    /// A code section whose bodies have not been decoded yet.
+    LazyCode(LazyCodeSection<'a>),
     Data(DataSection<'a>),
     DataCount(DataCountSection),
     Custom(CustomSection<'a>),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TypeSection {
     pub types: Vec<FunctionType>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ImportSection {
     pub imports: Vec<Import>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Import {
     pub module: String,
     pub name: String,
     pub desc: ImportDesc,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ImportDesc {
     TypeIndex(u32),
     Table(TableType),
@@ -46,69 +53,71 @@ pub enum ImportDesc {
     Global(GlobalType),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct FunctionSection {
     pub type_indices: Vec<u32>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TableSection {
     pub tables: Vec<TableType>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct MemorySection {
     pub memories: Vec<MemoryType>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct GlobalSection<'a> {
     pub globals: Vec<Global<'a>>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Global<'a> {
     pub global_type: GlobalType,
     pub expression: RawExpression<'a>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ExportSection {
     pub exports: Vec<Export>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Export {
     pub name: String,
     pub desc: ExportDesc,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ExportDesc {
     FunctionIndex(u32),
     TableIndex(u32),
     MemoryIndex(u32),
     GlobalIndex(u32),
 }
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct StartSection {
     pub start_function_index: u32,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ElementSection<'a> {
     pub elements: Vec<Element<'a>>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Element<'a> {
     pub kind: ElementKind<'a>,
     pub items: ElementItems<'a>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ElementKind<'a> {
     Active {
+        /// `None` if the segment uses the short form for table 0, which the binary format
+        /// only has for funcref elements.
         table_index: Option<u32>,
         offset_expression: RawExpression<'a>,
     },
@@ -116,41 +125,73 @@ pub enum ElementKind<'a> {
     Passive,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ElementItems<'a> {
     Functions(Vec<u32>),
     Expressions(ReferenceType, Vec<RawExpression<'a>>),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct CodeSection<'a> {
     pub code: Vec<FunctionBody<'a>>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct FunctionBody<'a> {
     // do not hold function size here.
     pub locals: Vec<Locals>,
     pub expression: RawExpression<'a>,
 }
-#[derive(Debug, PartialEq, Eq)]
+
+/// A code section which only records where each body is;
+/// bodies are decoded on first access and then cached.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LazyCodeSection<'a> {
+    pub bodies: Vec<LazyFunctionBody<'a>>,
+}
+
+/// The bytes of a function body after its size prefix.
+#[derive(Debug, Clone)]
+pub struct LazyFunctionBody<'a> {
+    /// Offset of `bytes` in the module binary, so that decoding errors point into the module.
+    pub offset: usize,
+    pub bytes: Cow<'a, [u8]>,
+    pub(crate) decoded: OnceCell<Result<DecodedBody, ParseError>>,
+}
+
+/// What decoding a [`LazyFunctionBody`] found; the expression is kept as a range of its bytes.
+#[derive(Debug, Clone)]
+pub(crate) struct DecodedBody {
+    pub locals: Vec<Locals>,
+    pub expression: Range<usize>,
+}
+
+impl PartialEq for LazyFunctionBody<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset && self.bytes == other.bytes
+    }
+}
+
+impl Eq for LazyFunctionBody<'_> {}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Locals {
     pub count: u32,
     pub value_type: ValueType,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct DataSection<'a> {
     pub segments: Vec<DataSegment<'a>>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct DataSegment<'a> {
     pub mode: DataMode<'a>,
-    pub data: &'a [u8],
+    pub data: Cow<'a, [u8]>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum DataMode<'a> {
     Active {
         memory_index: Option<u32>,
@@ -159,15 +200,15 @@ pub enum DataMode<'a> {
     Passive,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct DataCountSection {
     pub count: u32,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct CustomSection<'a> {
     pub name: String,
-    pub payload: &'a [u8],
+    pub payload: Cow<'a, [u8]>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -217,6 +258,47 @@ impl TryFrom<u8> for SectionID {
 }
 
 impl Section<'_> {
+    /// Copies any borrowed bytes so the section no longer refers to the input.
+    pub fn into_owned(self) -> Section<'static> {
+        match self {
+            Section::Type(s) => Section::Type(s),
+            Section::Import(s) => Section::Import(s),
+            Section::Function(s) => Section::Function(s),
+            Section::Table(s) => Section::Table(s),
+            Section::Memory(s) => Section::Memory(s),
+            Section::Global(s) => Section::Global(GlobalSection {
+                globals: s.globals.into_iter().map(Global::into_owned).collect(),
+            }),
+            Section::Export(s) => Section::Export(s),
+            Section::Start(s) => Section::Start(s),
+            Section::Element(s) => Section::Element(ElementSection {
+                elements: s.elements.into_iter().map(Element::into_owned).collect(),
+            }),
+            Section::Code(s) => Section::Code(CodeSection {
+                code: s.code.into_iter().map(FunctionBody::into_owned).collect(),
+            }),
+            Section::LazyCode(s) => Section::LazyCode(LazyCodeSection {
+                bodies: s
+                    .bodies
+                    .into_iter()
+                    .map(LazyFunctionBody::into_owned)
+                    .collect(),
+            }),
+            Section::Data(s) => Section::Data(DataSection {
+                segments: s
+                    .segments
+                    .into_iter()
+                    .map(DataSegment::into_owned)
+                    .collect(),
+            }),
+            Section::DataCount(s) => Section::DataCount(s),
+            Section::Custom(s) => Section::Custom(CustomSection {
+                name: s.name,
+                payload: Cow::Owned(s.payload.into_owned()),
+            }),
+        }
+    }
+
     pub fn id(&self) -> SectionID {
         match self {
             Section::Type(_) => SectionID::Type,
@@ -228,10 +310,92 @@ impl Section<'_> {
             Section::Export(_) => SectionID::Export,
             Section::Start(_) => SectionID::Start,
             Section::Element(_) => SectionID::Element,
-            Section::Code(_) => SectionID::Code,
+            Section::Code(_) | Section::LazyCode(_) => SectionID::Code,
             Section::Data(_) => SectionID::Data,
             Section::DataCount(_) => SectionID::DataCount,
             Section::Custom(_) => SectionID::Custom,
         }
     }
 }
+
+impl Global<'_> {
+    pub fn into_owned(self) -> Global<'static> {
+        Global {
+            global_type: self.global_type,
+            expression: self.expression.into_owned(),
+        }
+    }
+}
+
+impl Element<'_> {
+    pub fn into_owned(self) -> Element<'static> {
+        let kind = match self.kind {
+            ElementKind::Active {
+                table_index,
+                offset_expression,
+            } => ElementKind::Active {
+                table_index,
+                offset_expression: offset_expression.into_owned(),
+            },
+            ElementKind::Declarative => ElementKind::Declarative,
+            ElementKind::Passive => ElementKind::Passive,
+        };
+        let items = match self.items {
+            ElementItems::Functions(indices) => ElementItems::Functions(indices),
+            ElementItems::Expressions(ref_type, expressions) => ElementItems::Expressions(
+                ref_type,
+                expressions
+                    .into_iter()
+                    .map(RawExpression::into_owned)
+                    .collect(),
+            ),
+        };
+        Element { kind, items }
+    }
+}
+
+impl FunctionBody<'_> {
+    pub fn into_owned(self) -> FunctionBody<'static> {
+        FunctionBody {
+            locals: self.locals,
+            expression: self.expression.into_owned(),
+        }
+    }
+}
+
+impl<'a> LazyFunctionBody<'a> {
+    pub fn new(offset: usize, bytes: Cow<'a, [u8]>) -> Self {
+        Self {
+            offset,
+            bytes,
+            decoded: OnceCell::new(),
+        }
+    }
+
+    pub fn into_owned(self) -> LazyFunctionBody<'static> {
+        LazyFunctionBody {
+            offset: self.offset,
+            bytes: Cow::Owned(self.bytes.into_owned()),
+            decoded: self.decoded,
+        }
+    }
+}
+
+impl DataSegment<'_> {
+    pub fn into_owned(self) -> DataSegment<'static> {
+        let mode = match self.mode {
+            DataMode::Active {
+                memory_index,
+                offset_expression,
+            } => DataMode::Active {
+                memory_index,
+                offset_expression: offset_expression.into_owned(),
+            },
+            DataMode::Passive => DataMode::Passive,
+        };
+        DataSegment {
+            mode,
+            data: Cow::Owned(self.data.into_owned()),
+        }
+    }
+}
@@ -41,6 +41,16 @@ impl TryFrom<u8> for ValueType {
     }
 }
 
+impl From<ValueType> for u8 {
+    fn from(value: ValueType) -> Self {
+        match value {
+            ValueType::Number(t) => t as u8,
+            ValueType::Vector(t) => t as u8,
+            ValueType::Reference(t) => t as u8,
+        }
+    }
+}
+
 impl From<NumberType> for ValueType {
     fn from(value: NumberType) -> Self {
         ValueType::Number(value)
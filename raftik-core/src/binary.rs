@@ -1 +1,6 @@
-pub mod parser;
+pub mod encoder;
+pub mod error;
+pub(crate) mod parser;
+
+pub use parser::reader::{SectionEntry, SectionReader};
+pub use parser::stream::{StreamEvent, StreamParser};
@@ -0,0 +1,99 @@
+mod instructions;
+mod leb128;
+mod module;
+mod types;
+
+use leb128::{encode_sleb128_i64, encode_uleb128_u64};
+
+use crate::ast::ModuleParsed;
+
+/// Serializes an AST node in the binary format, appending to `sink`.
+pub trait Encode {
+    fn encode(&self, sink: &mut Vec<u8>);
+}
+
+impl Encode for u32 {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        encode_uleb128_u64(u64::from(*self), sink);
+    }
+}
+
+impl Encode for i32 {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        encode_sleb128_i64(i64::from(*self), sink);
+    }
+}
+
+impl Encode for i64 {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        encode_sleb128_i64(*self, sink);
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        encode_bytes(self.as_bytes(), sink);
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        self.as_str().encode(sink);
+    }
+}
+
+/// Vectors are encoded with their length first.
+impl<T: Encode> Encode for [T] {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        encode_len(self.len(), sink);
+        for item in self {
+            item.encode(sink);
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        self.as_slice().encode(sink);
+    }
+}
+
+fn encode_len(len: usize, sink: &mut Vec<u8>) {
+    let len = u32::try_from(len).expect("vector length should fit in u32");
+    len.encode(sink);
+}
+
+/// Encodes a byte vector, with its length first.
+fn encode_bytes(bytes: &[u8], sink: &mut Vec<u8>) {
+    encode_len(bytes.len(), sink);
+    sink.extend_from_slice(bytes);
+}
+
+/// Encodes `module` to a binary which `ModuleParsed::from_slice` decodes back to an equal module.
+pub fn encode(module: &ModuleParsed) -> Vec<u8> {
+    let mut sink = Vec::new();
+    module.encode(&mut sink);
+    sink
+}
+
+impl ModuleParsed<'_> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::MODULES;
+
+    #[test]
+    fn test_module_round_trip() {
+        for wat in MODULES {
+            let wasm = wat::parse_str(wat).unwrap();
+            let module = ModuleParsed::from_slice(&wasm).unwrap();
+            let bytes = module.to_bytes();
+            assert_eq!(ModuleParsed::from_slice(&bytes).unwrap(), module, "{}", wat);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use std::borrow::Cow;
+
+use super::Encode;
+use crate::{
+    ast::instructions::{BlockType, MemArg, Opcode, OpcodeEncoding, RawExpression},
+    binary::parser::instructions::MEMARG_HAS_MEMORY_INDEX,
+};
+
+impl Encode for OpcodeEncoding {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        match self {
+            OpcodeEncoding::Single(byte) => sink.push(*byte),
+            OpcodeEncoding::Prefixed(prefix, sub) => {
+                sink.push(*prefix);
+                sub.encode(sink);
+            }
+        }
+    }
+}
+
+impl Encode for BlockType {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        match self {
+            BlockType::Empty => sink.push(0x40),
+            BlockType::Value(t) => t.encode(sink),
+            // type indices are encoded as a positive s33
+            BlockType::TypeIndex(i) => i64::from(*i).encode(sink),
+        }
+    }
+}
+
+impl Encode for MemArg {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        if self.memory_index == 0 {
+            self.align.encode(sink);
+        } else {
+            (self.align | MEMARG_HAS_MEMORY_INDEX).encode(sink);
+            self.memory_index.encode(sink);
+        }
+        self.offset.encode(sink);
+    }
+}
+
+impl Encode for Opcode {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        let prefixed =
+            |sub: u32, sink: &mut Vec<u8>| OpcodeEncoding::Prefixed(0xFC, sub).encode(sink);
+        match self {
+            Opcode::Unreachable => sink.push(0x00),
+            Opcode::Nop => sink.push(0x01),
+            Opcode::Block(t) => {
+                sink.push(0x02);
+                t.encode(sink);
+            }
+            Opcode::Loop(t) => {
+                sink.push(0x03);
+                t.encode(sink);
+            }
+            Opcode::If(t) => {
+                sink.push(0x04);
+                t.encode(sink);
+            }
+            Opcode::Else => sink.push(0x05),
+            Opcode::End => sink.push(0x0B),
+            Opcode::Br(l) => {
+                sink.push(0x0C);
+                l.encode(sink);
+            }
+            Opcode::BrIf(l) => {
+                sink.push(0x0D);
+                l.encode(sink);
+            }
+            Opcode::BrTable(labels, default) => {
+                sink.push(0x0E);
+                labels.encode(sink);
+                default.encode(sink);
+            }
+            Opcode::Return => sink.push(0x0F),
+            Opcode::Call(i) => {
+                sink.push(0x10);
+                i.encode(sink);
+            }
+            Opcode::CallIndirect(type_index, table_index) => {
+                sink.push(0x11);
+                type_index.encode(sink);
+                table_index.encode(sink);
+            }
+            Opcode::Drop => sink.push(0x1A),
+            Opcode::Select => sink.push(0x1B),
+            Opcode::SelectTyped(types) => {
+                sink.push(0x1C);
+                types.encode(sink);
+            }
+            Opcode::LocalGet(i) => {
+                sink.push(0x20);
+                i.encode(sink);
+            }
+            Opcode::LocalSet(i) => {
+                sink.push(0x21);
+                i.encode(sink);
+            }
+            Opcode::LocalTee(i) => {
+                sink.push(0x22);
+                i.encode(sink);
+            }
+            Opcode::GlobalGet(i) => {
+                sink.push(0x23);
+                i.encode(sink);
+            }
+            Opcode::GlobalSet(i) => {
+                sink.push(0x24);
+                i.encode(sink);
+            }
+            Opcode::TableGet(i) => {
+                sink.push(0x25);
+                i.encode(sink);
+            }
+            Opcode::TableSet(i) => {
+                sink.push(0x26);
+                i.encode(sink);
+            }
+            Opcode::Memory(op, memarg) => {
+                sink.push(op.byte());
+                memarg.encode(sink);
+            }
+            Opcode::MemorySize(i) => {
+                sink.push(0x3F);
+                i.encode(sink);
+            }
+            Opcode::MemoryGrow(i) => {
+                sink.push(0x40);
+                i.encode(sink);
+            }
+            Opcode::I32Const(v) => {
+                sink.push(0x41);
+                v.encode(sink);
+            }
+            Opcode::I64Const(v) => {
+                sink.push(0x42);
+                v.encode(sink);
+            }
+            Opcode::F32Const(v) => {
+                sink.push(0x43);
+                sink.extend_from_slice(&v.to_le_bytes());
+            }
+            Opcode::F64Const(v) => {
+                sink.push(0x44);
+                sink.extend_from_slice(&v.to_le_bytes());
+            }
+            Opcode::Numeric(op) => op.encoding().encode(sink),
+            Opcode::RefNull(t) => {
+                sink.push(0xD0);
+                t.encode(sink);
+            }
+            Opcode::RefIsNull => sink.push(0xD1),
+            Opcode::RefFunc(i) => {
+                sink.push(0xD2);
+                i.encode(sink);
+            }
+            Opcode::MemoryInit(data, memory) => {
+                prefixed(8, sink);
+                data.encode(sink);
+                memory.encode(sink);
+            }
+            Opcode::DataDrop(i) => {
+                prefixed(9, sink);
+                i.encode(sink);
+            }
+            Opcode::MemoryCopy(dst, src) => {
+                prefixed(10, sink);
+                dst.encode(sink);
+                src.encode(sink);
+            }
+            Opcode::MemoryFill(i) => {
+                prefixed(11, sink);
+                i.encode(sink);
+            }
+            Opcode::TableInit(elem, table) => {
+                prefixed(12, sink);
+                elem.encode(sink);
+                table.encode(sink);
+            }
+            Opcode::ElemDrop(i) => {
+                prefixed(13, sink);
+                i.encode(sink);
+            }
+            Opcode::TableCopy(dst, src) => {
+                prefixed(14, sink);
+                dst.encode(sink);
+                src.encode(sink);
+            }
+            Opcode::TableGrow(i) => {
+                prefixed(15, sink);
+                i.encode(sink);
+            }
+            Opcode::TableSize(i) => {
+                prefixed(16, sink);
+                i.encode(sink);
+            }
+            Opcode::TableFill(i) => {
+                prefixed(17, sink);
+                i.encode(sink);
+            }
+            Opcode::V128Const(bytes) => {
+                OpcodeEncoding::Prefixed(0xFD, 0x0C).encode(sink);
+                sink.extend_from_slice(bytes);
+            }
+            Opcode::I8x16Shuffle(lanes) => {
+                OpcodeEncoding::Prefixed(0xFD, 0x0D).encode(sink);
+                sink.extend_from_slice(lanes);
+            }
+            Opcode::VectorLane(op, lane) => {
+                op.encoding().encode(sink);
+                sink.push(*lane);
+            }
+            Opcode::VectorMemory(op, memarg) => {
+                op.encoding().encode(sink);
+                memarg.encode(sink);
+            }
+            Opcode::VectorMemoryLane(op, memarg, lane) => {
+                op.encoding().encode(sink);
+                memarg.encode(sink);
+                sink.push(*lane);
+            }
+            Opcode::Vector(op) => op.encoding().encode(sink),
+        }
+    }
+}
+
+impl RawExpression<'static> {
+    /// Encodes `opcodes` as an expression; the closing `end` is implied.
+    pub fn from_opcodes(opcodes: &[Opcode]) -> Self {
+        let mut instructions = Vec::new();
+        for opcode in opcodes {
+            opcode.encode(&mut instructions);
+        }
+        RawExpression {
+            instructions: Cow::Owned(instructions),
+        }
+    }
+}
+
+/// The closing `end` is not part of `RawExpression`, so it is written here.
+impl Encode for RawExpression<'_> {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        sink.extend_from_slice(&self.instructions);
+        sink.push(0x0B);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{
+            instructions::{MemoryOp, NumericOp, VectorLaneOp, VectorMemoryLaneOp, VectorOp},
+            types::{NumberType, ReferenceType},
+        },
+        binary::parser::instructions::parse_instruction,
+    };
+
+    #[test]
+    fn test_instruction_round_trip() {
+        let memarg = MemArg {
+            align: 2,
+            offset: 300,
+            memory_index: 0,
+        };
+        let opcodes = vec![
+            Opcode::Block(BlockType::Empty),
+            Opcode::Loop(BlockType::Value(NumberType::I64.into())),
+            Opcode::If(BlockType::TypeIndex(70)),
+            Opcode::BrTable(vec![0, 1, 200], 3),
+            Opcode::CallIndirect(4, 1),
+            Opcode::SelectTyped(vec![ReferenceType::ExternRef.into()]),
+            Opcode::I32Const(-1),
+            Opcode::I64Const(i64::MIN),
+            Opcode::F32Const(1.5),
+            Opcode::F64Const(-0.25),
+            Opcode::RefNull(ReferenceType::FuncRef),
+            Opcode::Memory(MemoryOp::I32Load, memarg),
+            Opcode::Memory(
+                MemoryOp::I64Store8,
+                MemArg {
+                    memory_index: 2,
+                    ..memarg
+                },
+            ),
+            Opcode::MemoryInit(1, 0),
+            Opcode::TableCopy(2, 3),
+            Opcode::Numeric(NumericOp::I32Add),
+            Opcode::Numeric(NumericOp::I64TruncSatF64U),
+            Opcode::V128Const([7; 16]),
+            Opcode::VectorLane(VectorLaneOp::I16x8ReplaceLane, 7),
+            Opcode::VectorMemoryLane(VectorMemoryLaneOp::V128Store64Lane, memarg, 1),
+            Opcode::Vector(VectorOp::F64x2ConvertLowI32x4U),
+        ];
+        for opcode in opcodes {
+            let mut sink = vec![];
+            opcode.encode(&mut sink);
+            assert_eq!(parse_instruction(&sink), Ok((&[][..], opcode)));
+        }
+    }
+}
@@ -0,0 +1,61 @@
+#[inline]
+pub fn encode_uleb128_u64(mut value: u64, sink: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            sink.push(byte);
+            return;
+        }
+        sink.push(byte | 0x80);
+    }
+}
+
+#[inline]
+pub fn encode_sleb128_i64(mut value: i64, sink: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        // arithmetic shift keeps the sign
+        value >>= 7;
+        let sign_bit_clear = byte & 0x40 == 0;
+        if (value == 0 && sign_bit_clear) || (value == -1 && !sign_bit_clear) {
+            sink.push(byte);
+            return;
+        }
+        sink.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::parser::leb128::{decode_sleb128_i64, decode_uleb128_u64};
+
+    #[test]
+    fn test_encode_uleb128_u64() {
+        let mut sink = vec![];
+        encode_uleb128_u64(624485, &mut sink);
+        assert_eq!(sink, [0xE5, 0x8E, 0x26]);
+    }
+
+    #[test]
+    fn test_encode_sleb128_i64() {
+        let mut sink = vec![];
+        encode_sleb128_i64(-123456, &mut sink);
+        assert_eq!(sink, [0xC0, 0xBB, 0x78]);
+    }
+
+    #[test]
+    fn test_leb128_round_trip() {
+        for value in [0, 1, 63, 64, 127, 128, u32::MAX as u64, u64::MAX] {
+            let mut sink = vec![];
+            encode_uleb128_u64(value, &mut sink);
+            assert_eq!(decode_uleb128_u64(&sink, 10, 64), Ok((value, sink.len())));
+        }
+        for value in [0, -1, 63, 64, -64, -65, i32::MIN as i64, i64::MIN, i64::MAX] {
+            let mut sink = vec![];
+            encode_sleb128_i64(value, &mut sink);
+            assert_eq!(decode_sleb128_i64(&sink, 10, 64), Ok((value, sink.len())));
+        }
+    }
+}
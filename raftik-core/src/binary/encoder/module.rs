@@ -0,0 +1,198 @@
+use super::{Encode, encode_bytes};
+use crate::ast::{
+    ModuleParsed,
+    section::{
+        DataMode, DataSegment, Element, ElementItems, ElementKind, Export, ExportDesc,
+        FunctionBody, Global, Import, ImportDesc, Locals, Section,
+    },
+    types::ReferenceType,
+};
+
+const MAGIC: &[u8; 4] = b"\0asm";
+
+impl Encode for ModuleParsed<'_> {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        sink.extend_from_slice(MAGIC);
+        sink.extend_from_slice(&self.version.version.to_le_bytes());
+        sink.extend_from_slice(&self.version.layer.to_le_bytes());
+        for section in &self.sections {
+            section.encode(sink);
+        }
+    }
+}
+
+impl Encode for Section<'_> {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        match self {
+            Section::Type(s) => s.types.encode(&mut payload),
+            Section::Import(s) => s.imports.encode(&mut payload),
+            Section::Function(s) => s.type_indices.encode(&mut payload),
+            Section::Table(s) => s.tables.encode(&mut payload),
+            Section::Memory(s) => s.memories.encode(&mut payload),
+            Section::Global(s) => s.globals.encode(&mut payload),
+            Section::Export(s) => s.exports.encode(&mut payload),
+            Section::Start(s) => s.start_function_index.encode(&mut payload),
+            Section::Element(s) => s.elements.encode(&mut payload),
+            Section::Code(s) => s.code.encode(&mut payload),
+            // bodies are copied as they are, whether or not they have been decoded
+            Section::LazyCode(s) => {
+                (s.bodies.len() as u32).encode(&mut payload);
+                for body in &s.bodies {
+                    encode_bytes(&body.bytes, &mut payload);
+                }
+            }
+            Section::Data(s) => s.segments.encode(&mut payload),
+            Section::DataCount(s) => s.count.encode(&mut payload),
+            Section::Custom(s) => {
+                s.name.encode(&mut payload);
+                payload.extend_from_slice(&s.payload);
+            }
+        }
+        sink.push(self.id().into());
+        encode_bytes(&payload, sink);
+    }
+}
+
+impl Encode for Import {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        self.module.encode(sink);
+        self.name.encode(sink);
+        match &self.desc {
+            ImportDesc::TypeIndex(i) => {
+                sink.push(0x00);
+                i.encode(sink);
+            }
+            ImportDesc::Table(t) => {
+                sink.push(0x01);
+                t.encode(sink);
+            }
+            ImportDesc::Memory(m) => {
+                sink.push(0x02);
+                m.encode(sink);
+            }
+            ImportDesc::Global(g) => {
+                sink.push(0x03);
+                g.encode(sink);
+            }
+        }
+    }
+}
+
+impl Encode for Global<'_> {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        self.global_type.encode(sink);
+        self.expression.encode(sink);
+    }
+}
+
+impl Encode for Export {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        self.name.encode(sink);
+        let (kind, index) = match self.desc {
+            ExportDesc::FunctionIndex(i) => (0x00, i),
+            ExportDesc::TableIndex(i) => (0x01, i),
+            ExportDesc::MemoryIndex(i) => (0x02, i),
+            ExportDesc::GlobalIndex(i) => (0x03, i),
+        };
+        sink.push(kind);
+        index.encode(sink);
+    }
+}
+
+/// Picks the smallest of the eight element segment encodings which
+/// decodes back to the same `Element`.
+impl Encode for Element<'_> {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        let expressions = matches!(self.items, ElementItems::Expressions(..));
+        let items_flag: u32 = if expressions { 0b100 } else { 0b000 };
+        let (flag, table_index, offset_expression) = match &self.kind {
+            ElementKind::Active {
+                table_index,
+                offset_expression,
+            } => {
+                // the short forms imply table 0 and funcref
+                let implicit = match (&self.items, table_index) {
+                    (_, Some(_)) => false,
+                    (ElementItems::Functions(_), None) => true,
+                    (ElementItems::Expressions(t, _), None) => *t == ReferenceType::FuncRef,
+                };
+                if implicit {
+                    (items_flag, None, Some(offset_expression))
+                } else {
+                    let table_index = table_index.unwrap_or(0);
+                    (
+                        items_flag | 0b010,
+                        Some(table_index),
+                        Some(offset_expression),
+                    )
+                }
+            }
+            ElementKind::Passive => (items_flag | 0b001, None, None),
+            ElementKind::Declarative => (items_flag | 0b011, None, None),
+        };
+        flag.encode(sink);
+        if let Some(table_index) = table_index {
+            table_index.encode(sink);
+        }
+        if let Some(offset_expression) = offset_expression {
+            offset_expression.encode(sink);
+        }
+        let has_kind = flag & 0b011 != 0;
+        match &self.items {
+            ElementItems::Functions(indices) => {
+                if has_kind {
+                    // elemkind 0x00: funcref
+                    sink.push(0x00);
+                }
+                indices.encode(sink);
+            }
+            ElementItems::Expressions(ref_type, expressions) => {
+                if has_kind {
+                    ref_type.encode(sink);
+                }
+                expressions.encode(sink);
+            }
+        }
+    }
+}
+
+impl Encode for FunctionBody<'_> {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        let mut body = Vec::new();
+        self.locals.encode(&mut body);
+        self.expression.encode(&mut body);
+        encode_bytes(&body, sink);
+    }
+}
+
+impl Encode for Locals {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        self.count.encode(sink);
+        self.value_type.encode(sink);
+    }
+}
+
+impl Encode for DataSegment<'_> {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        match &self.mode {
+            DataMode::Active {
+                memory_index: None,
+                offset_expression,
+            } => {
+                0u32.encode(sink);
+                offset_expression.encode(sink);
+            }
+            DataMode::Passive => 1u32.encode(sink),
+            DataMode::Active {
+                memory_index: Some(memory_index),
+                offset_expression,
+            } => {
+                2u32.encode(sink);
+                memory_index.encode(sink);
+                offset_expression.encode(sink);
+            }
+        }
+        encode_bytes(&self.data, sink);
+    }
+}
@@ -0,0 +1,63 @@
+use super::Encode;
+use crate::ast::types::{
+    FunctionType, GlobalType, Limits, MemoryType, Mutability, ReferenceType, TableType, ValueType,
+};
+
+impl Encode for ValueType {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        sink.push((*self).into());
+    }
+}
+
+impl Encode for ReferenceType {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        sink.push(*self as u8);
+    }
+}
+
+impl Encode for FunctionType {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        sink.push(0x60);
+        self.params.encode(sink);
+        self.results.encode(sink);
+    }
+}
+
+impl Encode for Limits {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        match self.max {
+            None => {
+                sink.push(0x00);
+                self.min.encode(sink);
+            }
+            Some(max) => {
+                sink.push(0x01);
+                self.min.encode(sink);
+                max.encode(sink);
+            }
+        }
+    }
+}
+
+impl Encode for MemoryType {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        self.limits.encode(sink);
+    }
+}
+
+impl Encode for TableType {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        self.ref_type.encode(sink);
+        self.limits.encode(sink);
+    }
+}
+
+impl Encode for GlobalType {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        self.val_type.encode(sink);
+        sink.push(match self.mutability {
+            Mutability::Const => 0x00,
+            Mutability::Var => 0x01,
+        });
+    }
+}
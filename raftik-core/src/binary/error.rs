@@ -0,0 +1,206 @@
+use std::fmt;
+
+use nom::error::{ErrorKind, FromExternalError};
+use thiserror::Error;
+
+pub use super::parser::leb128::Leb128Err;
+use crate::ast::{BinaryVersion, section::SectionID};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    #[error("bad magic number")]
+    BadMagic,
+
+    #[error("unsupported version {} (layer {})", .0.version, .0.layer)]
+    UnsupportedVersion(BinaryVersion),
+
+    #[error("component-model binary (version {0:#x}) is not a core module")]
+    ComponentBinary(u16),
+
+    #[error("unknown section id {0}")]
+    UnknownSectionId(u8),
+
+    #[error("section size {size} overruns the {remaining} bytes remaining")]
+    SectionSizeOverrun { size: u32, remaining: usize },
+
+    #[error("section size mismatch: {0} bytes left unconsumed")]
+    SectionSizeMismatch(usize),
+
+    #[error("function body size mismatch: {0} bytes left unconsumed")]
+    BodySizeMismatch(usize),
+
+    #[error("{0:?} section out of order")]
+    SectionOutOfOrder(SectionID),
+
+    #[error("malformed LEB128 integer: {0}")]
+    Leb128(Leb128Err),
+
+    #[error("invalid UTF-8 in name")]
+    InvalidUtf8,
+
+    #[error("unknown opcode {0:#04x}")]
+    UnknownOpcode(u8),
+
+    #[error("unknown opcode {0:#04x} {1}")]
+    UnknownPrefixedOpcode(u8, u32),
+
+    #[error("invalid {what} {byte:#04x}")]
+    InvalidByte { what: &'static str, byte: u8 },
+
+    #[error("invalid {0} flag {1}")]
+    InvalidFlag(&'static str, u32),
+
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+
+    #[error("malformed input")]
+    Malformed,
+}
+
+/// Error returned by [`crate::ast::ModuleParsed::from_slice`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Absolute offset in the binary where decoding failed.
+    pub offset: usize,
+    /// The section being decoded, if any.
+    pub section: Option<SectionID>,
+    /// Index of the section entry being decoded, if any.
+    pub item: Option<u32>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error at {:#x}", self.offset)?;
+        if let Some(section) = self.section {
+            write!(f, " in {} section", section_name(section))?;
+            if let Some(item) = self.item {
+                write!(f, ", {} #{}", item_name(section), item)?;
+            }
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn section_name(id: SectionID) -> &'static str {
+    match id {
+        SectionID::Custom => "custom",
+        SectionID::Type => "type",
+        SectionID::Import => "import",
+        SectionID::Function => "function",
+        SectionID::Table => "table",
+        SectionID::Memory => "memory",
+        SectionID::Global => "global",
+        SectionID::Export => "export",
+        SectionID::Start => "start",
+        SectionID::Element => "element",
+        SectionID::Code => "code",
+        SectionID::Data => "data",
+        SectionID::DataCount => "data count",
+    }
+}
+
+fn item_name(id: SectionID) -> &'static str {
+    match id {
+        SectionID::Type => "type",
+        SectionID::Import => "import",
+        SectionID::Function | SectionID::Code => "function",
+        SectionID::Table => "table",
+        SectionID::Memory => "memory",
+        SectionID::Global => "global",
+        SectionID::Export => "export",
+        SectionID::Element | SectionID::Data => "segment",
+        SectionID::Custom | SectionID::Start | SectionID::DataCount => "entry",
+    }
+}
+
+/// The nom error type used throughout the binary parser.
+/// `input` is the remaining input at the point of failure, which is turned
+/// into an absolute offset by [`DecodeError::into_parse_error`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DecodeError<'a> {
+    pub input: &'a [u8],
+    pub kind: ParseErrorKind,
+    pub section: Option<SectionID>,
+    pub item: Option<u32>,
+}
+
+pub(crate) type PResult<'a, O> = nom::IResult<&'a [u8], O, DecodeError<'a>>;
+
+impl<'a> DecodeError<'a> {
+    pub fn new(input: &'a [u8], kind: ParseErrorKind) -> Self {
+        Self {
+            input,
+            kind,
+            section: None,
+            item: None,
+        }
+    }
+
+    pub fn in_section(mut self, section: SectionID) -> Self {
+        self.section.get_or_insert(section);
+        self
+    }
+
+    pub fn in_item(mut self, item: u32) -> Self {
+        self.item.get_or_insert(item);
+        self
+    }
+
+    pub fn into_parse_error(self, base: &[u8]) -> ParseError {
+        let offset = (self.input.as_ptr() as usize)
+            .saturating_sub(base.as_ptr() as usize)
+            .min(base.len());
+        ParseError {
+            kind: self.kind,
+            offset,
+            section: self.section,
+            item: self.item,
+        }
+    }
+}
+
+/// Shorthand for a recoverable parse failure at `input`.
+pub(crate) fn fail<'a, O>(input: &'a [u8], kind: ParseErrorKind) -> PResult<'a, O> {
+    Err(nom::Err::Error(DecodeError::new(input, kind)))
+}
+
+impl<'a> nom::error::ParseError<&'a [u8]> for DecodeError<'a> {
+    fn from_error_kind(input: &'a [u8], kind: ErrorKind) -> Self {
+        // leftover input is reported by the callers, so `Eof` only comes from truncation
+        let kind = match kind {
+            ErrorKind::Eof | ErrorKind::Complete => ParseErrorKind::UnexpectedEof,
+            _ => ParseErrorKind::Malformed,
+        };
+        Self::new(input, kind)
+    }
+
+    fn append(_: &'a [u8], _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    /// Keeps the error which got furthest into the input, so that `alt`
+    /// reports the branch that actually matched rather than the last one tried.
+    fn or(self, other: Self) -> Self {
+        match self.input.len().cmp(&other.input.len()) {
+            std::cmp::Ordering::Less => self,
+            std::cmp::Ordering::Greater => other,
+            std::cmp::Ordering::Equal if other.kind == ParseErrorKind::Malformed => self,
+            std::cmp::Ordering::Equal => other,
+        }
+    }
+}
+
+impl<'a> FromExternalError<&'a [u8], Leb128Err> for DecodeError<'a> {
+    fn from_external_error(input: &'a [u8], _: ErrorKind, e: Leb128Err) -> Self {
+        Self::new(input, ParseErrorKind::Leb128(e))
+    }
+}
+
+impl<'a> FromExternalError<&'a [u8], std::str::Utf8Error> for DecodeError<'a> {
+    fn from_external_error(input: &'a [u8], _: ErrorKind, _: std::str::Utf8Error) -> Self {
+        Self::new(input, ParseErrorKind::InvalidUtf8)
+    }
+}
@@ -1,48 +1,68 @@
+mod custom;
 pub mod instructions;
-mod integer;
-mod leb128;
+pub(crate) mod integer;
+pub(super) mod leb128;
 mod module;
-mod name;
+pub(crate) mod name;
+pub mod reader;
 mod section_parser_trait;
+pub mod stream;
 mod types;
 
 use module::parse_module;
 
-use crate::ast::{ModuleParsed, section::SectionID};
+use super::error::{DecodeError, ParseError, ParseErrorKind};
+use crate::ast::{ModuleParsed, Section};
 
 impl<'a> ModuleParsed<'a> {
-    pub fn from_slice(input: &'a [u8]) -> Result<Self, String> {
-        let (_, module) =
-            parse_module(input).map_err(|e| format!("Failed to parse module: {:?}", e))?;
-        // check section order
-        let mut last_id = 0u8;
-        for s in &module.sections {
-            let id: u8 = s.id().into();
-            if id == 0 {
-                continue;
-            }
-            if id <= last_id
-                && !(last_id == SectionID::DataCount.into()
-                    && (s.id() == SectionID::Code || s.id() == SectionID::Data))
-            {
-                return Err(format!(
-                    "Sections are not in the correct order: {:?} < {:?}",
-                    s.id(),
-                    last_id
-                ));
+    pub fn from_slice(input: &'a [u8]) -> Result<Self, ParseError> {
+        Self::parse(input, false)
+    }
+
+    /// Like [`ModuleParsed::from_slice`], but the code section becomes a
+    /// [`Section::LazyCode`] whose bodies are decoded on first access.
+    /// A malformed body is only reported when it is accessed.
+    pub fn from_slice_lazy(input: &'a [u8]) -> Result<Self, ParseError> {
+        let mut module = Self::parse(input, true)?;
+        for section in &mut module.sections {
+            if let Section::LazyCode(code) = section {
+                for body in &mut code.bodies {
+                    body.offset = body.bytes.as_ptr() as usize - input.as_ptr() as usize;
+                }
             }
-            last_id = id;
         }
         Ok(module)
     }
+
+    fn parse(input: &'a [u8], lazy_code: bool) -> Result<Self, ParseError> {
+        parse_module(input, lazy_code)
+            .map(|(_, module)| module)
+            .map_err(|e| to_parse_error(e, input))
+    }
+}
+
+/// Converts a parser error with offsets relative to `base`.
+fn to_parse_error(e: nom::Err<DecodeError>, base: &[u8]) -> ParseError {
+    match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e.into_parse_error(base),
+        nom::Err::Incomplete(_) => ParseError {
+            kind: ParseErrorKind::UnexpectedEof,
+            offset: base.len(),
+            section: None,
+            item: None,
+        },
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
     use crate::ast::{
-        CodeSection, DataCountSection, DataSection, ElementSection, ExportSection, FunctionSection,
-        GlobalSection, ImportSection, MemorySection, ModuleParsed, Section, StartSection,
-        TableSection, TypeSection,
+        BinaryVersion, CodeSection, DataCountSection, DataSection, ElementSection, ExportSection,
+        FunctionSection, GlobalSection, ImportSection, MemorySection, ModuleOwned, ModuleParsed,
+        Section, StartSection, TableSection, TypeSection,
         instructions::*,
         section::{
             DataMode, DataSegment, Element, ElementItems, ElementKind, Export, ExportDesc,
@@ -50,6 +70,7 @@ mod tests {
         },
         types::*,
     };
+    use crate::binary::error::Leb128Err;
 
     fn with_wat(wat: impl AsRef<str>, test: impl Fn(ModuleParsed)) {
         let wasm = wat::parse_str(wat).unwrap();
@@ -72,6 +93,7 @@ mod tests {
                 assert_eq!(
                     module,
                     ModuleParsed {
+                        version: BinaryVersion::CORE_MODULE,
                         sections: vec![Section::Type(TypeSection {
                             types: vec![FunctionType {
                                 params: vec![NumberType::I32.into(), NumberType::I32.into(),],
@@ -251,7 +273,27 @@ mod tests {
                             mutability: Mutability::Const,
                         },
                         expression: RawExpression {
-                            instructions: &[0x41, 0x20][..]
+                            instructions: Cow::Borrowed(&[0x41, 0x20][..])
+                        }
+                    }]
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn test_wasm_with_end_byte_in_global_expression() {
+        with_wat("(module (global i32 (i32.const 11)))", |module| {
+            assert_eq!(
+                module.sections[0],
+                Section::Global(GlobalSection {
+                    globals: vec![Global {
+                        global_type: GlobalType {
+                            val_type: NumberType::I32.into(),
+                            mutability: Mutability::Const,
+                        },
+                        expression: RawExpression {
+                            instructions: Cow::Borrowed(&[0x41, 0x0b][..])
                         }
                     }]
                 })
@@ -307,7 +349,7 @@ mod tests {
                             kind: ElementKind::Active {
                                 table_index: None,
                                 offset_expression: RawExpression {
-                                    instructions: &[0x41, 0x00][..]
+                                    instructions: Cow::Borrowed(&[0x41, 0x00][..])
                                 }
                             },
                             items: ElementItems::Functions(vec![0])
@@ -347,7 +389,7 @@ mod tests {
                             kind: ElementKind::Active {
                                 table_index: Some(1),
                                 offset_expression: RawExpression {
-                                    instructions: &[0x41, 0x00][..]
+                                    instructions: Cow::Borrowed(&[0x41, 0x00][..])
                                 }
                             },
                             items: ElementItems::Functions(vec![0])
@@ -387,13 +429,13 @@ mod tests {
                             kind: ElementKind::Active {
                                 table_index: None,
                                 offset_expression: RawExpression {
-                                    instructions: &[0x41, 0x00][..]
+                                    instructions: Cow::Borrowed(&[0x41, 0x00][..])
                                 }
                             },
                             items: ElementItems::Expressions(
                                 ReferenceType::FuncRef,
                                 vec![RawExpression {
-                                    instructions: &[0xd2, 0][..]
+                                    instructions: Cow::Borrowed(&[0xd2, 0][..])
                                 }]
                             ),
                         }],
@@ -417,7 +459,7 @@ mod tests {
                             items: ElementItems::Expressions(
                                 ReferenceType::FuncRef,
                                 vec![RawExpression {
-                                    instructions: &[0xd2, 0][..]
+                                    instructions: Cow::Borrowed(&[0xd2, 0][..])
                                 }]
                             ),
                         }],
@@ -440,13 +482,13 @@ mod tests {
                             kind: ElementKind::Active {
                                 table_index: Some(1),
                                 offset_expression: RawExpression {
-                                    instructions: &[0x41, 0x00][..]
+                                    instructions: Cow::Borrowed(&[0x41, 0x00][..])
                                 }
                             },
                             items: ElementItems::Expressions(
                                 ReferenceType::FuncRef,
                                 vec![RawExpression {
-                                    instructions: &[0xd2, 0][..]
+                                    instructions: Cow::Borrowed(&[0xd2, 0][..])
                                 }]
                             ),
                         }],
@@ -470,7 +512,7 @@ mod tests {
                             items: ElementItems::Expressions(
                                 ReferenceType::FuncRef,
                                 vec![RawExpression {
-                                    instructions: &[0xd2, 0][..]
+                                    instructions: Cow::Borrowed(&[0xd2, 0][..])
                                 }]
                             ),
                         }],
@@ -495,7 +537,30 @@ mod tests {
                                 value_type: NumberType::F64.into(),
                             }],
                             expression: RawExpression {
-                                instructions: &[0x20, 0, 0x20, 1, 0x6a][..]
+                                instructions: Cow::Borrowed(&[0x20, 0, 0x20, 1, 0x6a][..])
+                            }
+                        }]
+                    })
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_wasm_with_nested_blocks_in_code_section() {
+        with_wat(
+            "(module (func (result i32) (block (result i32) (loop (result i32) (i32.const 11)))))",
+            |module| {
+                let section = module.sec_by_id(SectionID::Code).unwrap();
+                assert_eq!(
+                    *section,
+                    Section::Code(CodeSection {
+                        code: vec![FunctionBody {
+                            locals: vec![],
+                            expression: RawExpression {
+                                instructions: Cow::Borrowed(
+                                    &[0x02, 0x7f, 0x03, 0x7f, 0x41, 0x0b, 0x0b, 0x0b][..]
+                                )
                             }
                         }]
                     })
@@ -509,7 +574,10 @@ mod tests {
         let wat = "(module (memory 1 10) (data (i32.const 0) \"0\") (data \"1\") (data 1 (i32.const 0) \"2\"))";
         let mut wasm = wat::parse_str(wat).unwrap();
         // wat does not generate DataCountSection, so adding one manually
-        wasm.extend(vec![0x0c, 0x01, 0x03]); // section id 12, section size 1, 3: u32
+        // before the data section, which follows the header and the memory section
+        let data_offset = HEADER.len() + 6;
+        assert_eq!(wasm[data_offset], SectionID::Data.into());
+        wasm.splice(data_offset..data_offset, [0x0c, 0x01, 0x03]); // section id 12, section size 1, 3: u32
         let module = ModuleParsed::from_slice(&wasm).unwrap();
         let data_section = module.sec_by_id(SectionID::Data).unwrap();
         assert_eq!(
@@ -520,23 +588,23 @@ mod tests {
                         mode: DataMode::Active {
                             memory_index: None,
                             offset_expression: RawExpression {
-                                instructions: &[0x41, 0][..]
+                                instructions: Cow::Borrowed(&[0x41, 0][..])
                             }
                         },
-                        data: &[48][..]
+                        data: Cow::Borrowed(&[48][..])
                     },
                     DataSegment {
                         mode: DataMode::Passive,
-                        data: &[49][..]
+                        data: Cow::Borrowed(&[49][..])
                     },
                     DataSegment {
                         mode: DataMode::Active {
                             memory_index: Some(1),
                             offset_expression: RawExpression {
-                                instructions: &[0x41, 0][..]
+                                instructions: Cow::Borrowed(&[0x41, 0][..])
                             }
                         },
-                        data: &[50][..]
+                        data: Cow::Borrowed(&[50][..])
                     },
                 ]
             })
@@ -547,4 +615,265 @@ mod tests {
             Section::DataCount(DataCountSection { count: 3 }),
         );
     }
+
+    fn parse_error(wasm: &[u8]) -> ParseError {
+        ModuleParsed::from_slice(wasm).unwrap_err()
+    }
+
+    const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+    #[test]
+    fn test_bad_magic() {
+        let e = parse_error(b"\0asn\x01\0\0\0");
+        assert_eq!(e.kind, ParseErrorKind::BadMagic);
+        assert_eq!(e.offset, 0);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let e = parse_error(&[0x00, 0x61, 0x73, 0x6d, 0x02, 0x00, 0x00, 0x00]);
+        assert_eq!(
+            e.kind,
+            ParseErrorKind::UnsupportedVersion(BinaryVersion {
+                version: 2,
+                layer: 0
+            })
+        );
+        assert_eq!(e.offset, 4);
+
+        let e = parse_error(&[0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x02, 0x00]);
+        assert_eq!(
+            e.kind,
+            ParseErrorKind::UnsupportedVersion(BinaryVersion {
+                version: 1,
+                layer: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_component_binary() {
+        let wasm = wat::parse_str("(component)").unwrap();
+        let e = parse_error(&wasm);
+        assert_eq!(e.kind, ParseErrorKind::ComponentBinary(0x0d));
+        assert_eq!(e.offset, 4);
+    }
+
+    #[test]
+    fn test_unknown_opcode_error() {
+        let mut wasm = HEADER.to_vec();
+        wasm.extend([0x01, 0x04, 0x01, 0x60, 0x00, 0x00]); // type section: [] -> []
+        wasm.extend([0x03, 0x03, 0x02, 0x00, 0x00]); // function section: 2 functions
+        // code section: two bodies, the second contains 0xff
+        wasm.extend([0x0a, 0x07, 0x02, 0x02, 0x00, 0x0b, 0x02, 0x00, 0xff]);
+        let e = parse_error(&wasm);
+        assert_eq!(
+            e,
+            ParseError {
+                kind: ParseErrorKind::UnknownOpcode(0xff),
+                offset: wasm.len() - 1,
+                section: Some(SectionID::Code),
+                item: Some(1),
+            }
+        );
+        assert_eq!(
+            e.to_string(),
+            "error at 0x1b in code section, function #1: unknown opcode 0xff"
+        );
+    }
+
+    #[test]
+    fn test_lazy_code_section() {
+        let wasm = wat::parse_str(
+            "(module (func (param i32) (local i64 i64) local.get 0 drop) (func (result i32) i32.const 7))",
+        )
+        .unwrap();
+        let eager = ModuleParsed::from_slice(&wasm).unwrap();
+        let lazy = ModuleParsed::from_slice_lazy(&wasm).unwrap();
+        let Some(Section::Code(code)) = eager.sec_by_id(SectionID::Code) else {
+            panic!("no code section");
+        };
+        let Some(Section::LazyCode(lazy_code)) = lazy.sec_by_id(SectionID::Code) else {
+            panic!("no lazy code section");
+        };
+        assert_eq!(lazy_code.len(), 2);
+        assert_eq!(lazy_code.get(1).unwrap().unwrap(), code.code[1]);
+        // a second access reuses the decoded body
+        assert_eq!(lazy_code.get(1).unwrap().unwrap(), code.code[1]);
+        assert_eq!(lazy_code.to_code_section().unwrap(), *code);
+        assert_eq!(lazy.to_bytes(), wasm);
+        assert!(crate::validation::validate_module(&lazy).is_ok());
+    }
+
+    #[test]
+    fn test_lazy_code_section_with_malformed_body() {
+        let mut wasm = HEADER.to_vec();
+        wasm.extend([0x01, 0x04, 0x01, 0x60, 0x00, 0x00]); // type section: [] -> []
+        wasm.extend([0x03, 0x04, 0x03, 0x00, 0x00, 0x00]); // function section: 3 functions
+        // code section: three bodies, the second contains 0xff
+        wasm.extend([
+            0x0a, 0x0a, 0x03, 0x02, 0x00, 0x0b, 0x02, 0x00, 0xff, 0x02, 0x00, 0x0b,
+        ]);
+        assert!(ModuleParsed::from_slice(&wasm).is_err());
+
+        let module = ModuleParsed::from_slice_lazy(&wasm).unwrap();
+        let Some(Section::LazyCode(code)) = module.sec_by_id(SectionID::Code) else {
+            panic!("no lazy code section");
+        };
+        let results: Vec<_> = code.iter().collect();
+        assert!(results[0].is_ok());
+        assert_eq!(
+            results[1],
+            Err(ParseError {
+                kind: ParseErrorKind::UnknownOpcode(0xff),
+                offset: wasm.len() - 4,
+                section: Some(SectionID::Code),
+                item: Some(1),
+            })
+        );
+        assert!(results[2].is_ok());
+        assert_eq!(code.bodies[1].offset, wasm.len() - 5);
+    }
+
+    #[test]
+    fn test_unknown_prefixed_opcode_error() {
+        let mut wasm = wat::parse_str("(module (func i32.const 0 drop))").unwrap();
+        // replace `i32.const 0 drop` with an unassigned 0xfc sub-opcode and a nop
+        let len = wasm.len();
+        wasm[len - 4..len - 1].copy_from_slice(&[0xfc, 0x20, 0x01]);
+        let e = parse_error(&wasm);
+        assert_eq!(e.kind, ParseErrorKind::UnknownPrefixedOpcode(0xfc, 0x20));
+        assert_eq!(e.section, Some(SectionID::Code));
+        assert_eq!(e.offset, len - 2);
+    }
+
+    #[test]
+    fn test_unknown_section_id() {
+        let mut wasm = HEADER.to_vec();
+        wasm.extend([0x0d, 0x00]);
+        let e = parse_error(&wasm);
+        assert_eq!(e.kind, ParseErrorKind::UnknownSectionId(13));
+        assert_eq!(e.offset, 8);
+    }
+
+    #[test]
+    fn test_section_out_of_order() {
+        let mut wasm = HEADER.to_vec();
+        wasm.extend([0x03, 0x01, 0x00]); // empty function section
+        wasm.extend([0x01, 0x01, 0x00]); // empty type section
+        let e = parse_error(&wasm);
+        assert_eq!(e.kind, ParseErrorKind::SectionOutOfOrder(SectionID::Type));
+        assert_eq!(e.offset, 11);
+
+        // the data count section belongs between the element and code sections
+        let mut wasm = HEADER.to_vec();
+        wasm.extend([0x0a, 0x01, 0x00]); // empty code section
+        wasm.extend([0x0c, 0x01, 0x00]); // data count 0
+        let e = parse_error(&wasm);
+        assert_eq!(
+            e.kind,
+            ParseErrorKind::SectionOutOfOrder(SectionID::DataCount)
+        );
+        assert_eq!(e.offset, 11);
+
+        let mut wasm = HEADER.to_vec();
+        wasm.extend([0x0c, 0x01, 0x00]); // data count 0
+        wasm.extend([0x09, 0x01, 0x00]); // empty element section
+        let e = parse_error(&wasm);
+        assert_eq!(
+            e.kind,
+            ParseErrorKind::SectionOutOfOrder(SectionID::Element)
+        );
+
+        let mut wasm = HEADER.to_vec();
+        wasm.extend([0x09, 0x01, 0x00]); // empty element section
+        wasm.extend([0x0c, 0x01, 0x00]); // data count 0
+        wasm.extend([0x0a, 0x01, 0x00]); // empty code section
+        wasm.extend([0x0b, 0x01, 0x00]); // empty data section
+        assert!(ModuleParsed::from_slice(&wasm).is_ok());
+    }
+
+    #[test]
+    fn test_section_size_errors() {
+        let mut wasm = HEADER.to_vec();
+        wasm.extend([0x01, 0x05, 0x00]);
+        let e = parse_error(&wasm);
+        assert_eq!(
+            e.kind,
+            ParseErrorKind::SectionSizeOverrun {
+                size: 5,
+                remaining: 1
+            }
+        );
+        assert_eq!(e.section, Some(SectionID::Type));
+
+        let mut wasm = HEADER.to_vec();
+        wasm.extend([0x01, 0x02, 0x00, 0x00]);
+        let e = parse_error(&wasm);
+        assert_eq!(e.kind, ParseErrorKind::SectionSizeMismatch(1));
+        assert_eq!(e.offset, 11);
+    }
+
+    #[test]
+    fn test_function_body_size_mismatch() {
+        let mut wasm = wat::parse_str("(module (func))").unwrap();
+        // code section with a body of `end` followed by one extra byte
+        let len = wasm.len();
+        wasm.truncate(len - 5);
+        wasm.extend([0x05, 0x01, 0x03, 0x00, 0x0b, 0x01]);
+        let e = parse_error(&wasm);
+        assert_eq!(e.kind, ParseErrorKind::BodySizeMismatch(1));
+        assert_eq!(e.section, Some(SectionID::Code));
+        assert_eq!(e.item, Some(0));
+        assert_eq!(e.offset, wasm.len() - 1);
+    }
+
+    #[test]
+    fn test_leb128_overflow() {
+        let mut wasm = HEADER.to_vec();
+        // function section whose count does not fit in u32
+        wasm.extend([0x03, 0x05, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+        let e = parse_error(&wasm);
+        assert_eq!(e.kind, ParseErrorKind::Leb128(Leb128Err::Overflow));
+        assert_eq!(e.offset, 10);
+        assert_eq!(e.section, Some(SectionID::Function));
+    }
+
+    #[test]
+    fn test_invalid_utf8_name() {
+        let mut wasm = HEADER.to_vec();
+        // export section with a single export named "\xff"
+        wasm.extend([0x07, 0x05, 0x01, 0x01, 0xff, 0x00, 0x00]);
+        let e = parse_error(&wasm);
+        assert_eq!(e.kind, ParseErrorKind::InvalidUtf8);
+        assert_eq!(e.section, Some(SectionID::Export));
+        assert_eq!(e.item, Some(0));
+    }
+
+    #[test]
+    fn test_into_owned() {
+        let mut owned: ModuleOwned = {
+            let wasm = wat::parse_str(
+                r#"(module (memory 1) (func (result i32) i32.const 7) (data (i32.const 0) "ab"))"#,
+            )
+            .unwrap();
+            ModuleParsed::from_slice(&wasm).unwrap().into_owned()
+        };
+        if let Some(Section::Data(data)) = owned.sec_by_id_mut(SectionID::Data) {
+            data.segments[0].data.to_mut().push(b'c');
+        }
+        let encoded = std::thread::spawn({
+            let owned = owned.clone();
+            move || owned.to_bytes()
+        })
+        .join()
+        .unwrap();
+        let module = ModuleParsed::from_slice(&encoded).unwrap();
+        assert_eq!(module, owned);
+        if let Some(Section::Data(data)) = module.sec_by_id(SectionID::Data) {
+            assert_eq!(&data.segments[0].data[..], b"abc");
+        } else {
+            unreachable!()
+        }
+    }
 }
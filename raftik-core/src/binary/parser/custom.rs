@@ -0,0 +1,276 @@
+use nom::{
+    Parser,
+    bytes::complete::take,
+    combinator::{flat_map, map},
+    multi::length_count,
+    number::complete::u8,
+};
+
+use super::{
+    integer::parse_varuint32, name::parse_name, section_parser_trait::ParseSection, types::invalid,
+};
+use crate::{
+    ast::{
+        CustomSection, ModuleParsed, Section,
+        custom::{
+            FeaturePrefix, IndirectNameMap, NameMap, NameSection, ProducerVersion, ProducersField,
+            ProducersSection, TargetFeature, TargetFeaturesSection,
+        },
+        section::SectionID,
+    },
+    binary::error::{PResult, ParseError, ParseErrorKind, fail},
+};
+
+impl ModuleParsed<'_> {
+    /// Decodes the `name` custom section, if the module has one.
+    pub fn name_section(&self) -> Option<NameSection> {
+        self.sections.iter().find_map(|section| match section {
+            Section::Custom(custom) => custom.name_section(),
+            _ => None,
+        })
+    }
+}
+
+impl CustomSection<'_> {
+    /// Decodes the payload if this is the `name` section.
+    /// Malformed subsections are skipped, since debug names must not make a module invalid.
+    pub fn name_section(&self) -> Option<NameSection> {
+        (self.name == "name").then(|| parse_name_section(&self.payload))
+    }
+
+    /// Decodes the payload if this is the `producers` section.
+    /// Error offsets are relative to the start of the payload.
+    pub fn producers(&self) -> Option<Result<ProducersSection, ParseError>> {
+        (self.name == "producers").then(|| decode_payload(&self.payload))
+    }
+
+    /// Decodes the payload if this is the `target_features` section.
+    /// Error offsets are relative to the start of the payload.
+    pub fn target_features(&self) -> Option<Result<TargetFeaturesSection, ParseError>> {
+        (self.name == "target_features").then(|| decode_payload(&self.payload))
+    }
+}
+
+fn decode_payload<'a, T: ParseSection<'a>>(payload: &'a [u8]) -> Result<T, ParseError> {
+    T::parse_all(payload).map_err(|e| match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => {
+            e.in_section(SectionID::Custom).into_parse_error(payload)
+        }
+        nom::Err::Incomplete(_) => ParseError {
+            kind: ParseErrorKind::UnexpectedEof,
+            offset: payload.len(),
+            section: Some(SectionID::Custom),
+            item: None,
+        },
+    })
+}
+
+impl ParseSection<'_> for ProducersSection {
+    fn parse_from_payload(payload: &[u8]) -> PResult<'_, Self> {
+        let version = map((parse_name, parse_name), |(name, version)| {
+            ProducerVersion { name, version }
+        });
+        let field = map(
+            (parse_name, length_count(parse_varuint32, version)),
+            |(name, values)| ProducersField { name, values },
+        );
+        map(length_count(parse_varuint32, field), |fields| {
+            ProducersSection { fields }
+        })
+        .parse(payload)
+    }
+}
+
+impl ParseSection<'_> for TargetFeaturesSection {
+    fn parse_from_payload(payload: &[u8]) -> PResult<'_, Self> {
+        let feature = map((parse_feature_prefix, parse_name), |(prefix, name)| {
+            TargetFeature { prefix, name }
+        });
+        map(length_count(parse_varuint32, feature), |features| {
+            TargetFeaturesSection { features }
+        })
+        .parse(payload)
+    }
+}
+
+fn parse_feature_prefix(input: &[u8]) -> PResult<'_, FeaturePrefix> {
+    let (rest, byte) = u8(input)?;
+    match FeaturePrefix::from_byte(byte) {
+        Some(prefix) => Ok((rest, prefix)),
+        None => fail(input, invalid("feature prefix", byte)),
+    }
+}
+
+fn parse_name_section(mut payload: &[u8]) -> NameSection {
+    let mut names = NameSection::default();
+    while let Ok((rest, (id, content))) = parse_subsection(payload) {
+        let name_map = || decode(content, parse_name_map);
+        let indirect_name_map = || decode(content, parse_indirect_name_map);
+        match id {
+            0 => names.module = parse_name(content).ok().map(|(_, name)| name),
+            1 => names.functions = name_map(),
+            2 => names.locals = indirect_name_map(),
+            3 => names.labels = indirect_name_map(),
+            4 => names.types = name_map(),
+            5 => names.tables = name_map(),
+            6 => names.memories = name_map(),
+            7 => names.globals = name_map(),
+            8 => names.elements = name_map(),
+            9 => names.data = name_map(),
+            _ => (),
+        }
+        payload = rest;
+    }
+    names
+}
+
+fn decode<T: Default>(content: &[u8], parser: fn(&[u8]) -> PResult<'_, T>) -> T {
+    parser(content).map(|(_, value)| value).unwrap_or_default()
+}
+
+fn parse_subsection(input: &[u8]) -> PResult<'_, (u8, &[u8])> {
+    (u8, flat_map(parse_varuint32, take)).parse(input)
+}
+
+fn parse_name_map(input: &[u8]) -> PResult<'_, NameMap> {
+    map(
+        length_count(parse_varuint32, (parse_varuint32, parse_name)),
+        |entries| entries.into_iter().collect(),
+    )
+    .parse(input)
+}
+
+fn parse_indirect_name_map(input: &[u8]) -> PResult<'_, IndirectNameMap> {
+    map(
+        length_count(parse_varuint32, (parse_varuint32, parse_name_map)),
+        |entries| entries.into_iter().collect(),
+    )
+    .parse(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_section() {
+        let wasm = wat::parse_str(
+            r#"
+(module $m
+  (type $t (func))
+  (import "env" "f" (func $imported))
+  (table $tab 1 funcref)
+  (memory $mem 1)
+  (global $g i32 (i32.const 0))
+  (func $f (param $p i32) (local $l i64)
+    block $exit
+    end)
+  (elem $e func $f)
+  (data $d ""))
+"#,
+        )
+        .unwrap();
+        let module = ModuleParsed::from_slice(&wasm).unwrap();
+        let names = module.name_section().unwrap();
+        let map = |entries: &[(u32, &str)]| -> NameMap {
+            entries.iter().map(|(i, n)| (*i, n.to_string())).collect()
+        };
+        assert_eq!(names.module.as_deref(), Some("m"));
+        assert_eq!(names.functions, map(&[(0, "imported"), (1, "f")]));
+        assert_eq!(names.local(1, 0), Some("p"));
+        assert_eq!(names.local(1, 1), Some("l"));
+        assert_eq!(names.labels.get(&1), Some(&map(&[(0, "exit")])));
+        assert_eq!(names.types, map(&[(0, "t")]));
+        assert_eq!(names.tables, map(&[(0, "tab")]));
+        assert_eq!(names.memories, map(&[(0, "mem")]));
+        assert_eq!(names.globals, map(&[(0, "g")]));
+        assert_eq!(names.elements, map(&[(0, "e")]));
+        assert_eq!(names.data, map(&[(0, "d")]));
+        assert_eq!(names.describe_function(1), "$f");
+        assert_eq!(names.describe_function(2), "function #2");
+    }
+
+    #[test]
+    fn test_malformed_name_subsection() {
+        let custom = CustomSection {
+            name: "name".to_string(),
+            // a function name map with a truncated name, then a valid module name
+            payload: vec![1, 3, 1, 0, 5, 0, 2, 1, b'm'].into(),
+        };
+        let names = custom.name_section().unwrap();
+        assert_eq!(names.functions, NameMap::new());
+        assert_eq!(names.module.as_deref(), Some("m"));
+
+        let other = CustomSection {
+            name: "producers".to_string(),
+            payload: vec![].into(),
+        };
+        assert_eq!(other.name_section(), None);
+    }
+
+    #[test]
+    fn test_producers() {
+        let mut payload = vec![2];
+        payload.extend(b"\x08language\x01\x04Rust\x061.80.0");
+        payload.extend(b"\x0cprocessed-by\x02\x05rustc\x061.80.0\x05clang\x0217");
+        let custom = CustomSection {
+            name: "producers".to_string(),
+            payload: payload.into(),
+        };
+        let producers = custom.producers().unwrap().unwrap();
+        let version = |name: &str, version: &str| ProducerVersion {
+            name: name.to_string(),
+            version: version.to_string(),
+        };
+        assert_eq!(
+            producers.field("language").unwrap().values,
+            vec![version("Rust", "1.80.0")]
+        );
+        assert_eq!(
+            producers.field("processed-by").unwrap().values,
+            vec![version("rustc", "1.80.0"), version("clang", "17")]
+        );
+        assert_eq!(producers.field("sdk"), None);
+        assert_eq!(custom.target_features(), None);
+    }
+
+    #[test]
+    fn test_target_features() {
+        let custom = CustomSection {
+            name: "target_features".to_string(),
+            payload: b"\x03+\x08sign-ext-\x07atomics=\x0fmutable-globals"
+                .to_vec()
+                .into(),
+        };
+        let features = custom.target_features().unwrap().unwrap().features;
+        let prefixes: Vec<_> = features
+            .iter()
+            .map(|f| (f.prefix, f.name.as_str()))
+            .collect();
+        assert_eq!(
+            prefixes,
+            vec![
+                (FeaturePrefix::Used, "sign-ext"),
+                (FeaturePrefix::Disallowed, "atomics"),
+                (FeaturePrefix::Required, "mutable-globals"),
+            ]
+        );
+
+        let malformed = CustomSection {
+            name: "target_features".to_string(),
+            payload: b"\x01*\x04simd".to_vec().into(),
+        };
+        let error = malformed.target_features().unwrap().unwrap_err();
+        assert_eq!(
+            (error.kind, error.offset, error.section),
+            (
+                ParseErrorKind::InvalidByte {
+                    what: "feature prefix",
+                    byte: b'*'
+                },
+                1,
+                Some(SectionID::Custom)
+            )
+        );
+    }
+}
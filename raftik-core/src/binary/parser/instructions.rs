@@ -1,29 +1,116 @@
+use std::borrow::Cow;
+
 use nom::{
-    IResult, Parser,
+    Parser,
     branch::alt,
-    bytes::complete::{tag, take_until},
-    combinator::map,
-    number::{le_f32, le_f64},
-    sequence::terminated,
+    bytes::complete::{tag, take},
+    combinator::{map, map_opt},
+    multi::length_count,
+    number::{complete::u8, le_f32, le_f64},
 };
 
 use super::{
-    integer::{parse_varint32, parse_varint64, parse_varuint32},
-    types::parse_reference_type,
+    integer::{parse_varint32, parse_varint33, parse_varint64, parse_varuint32},
+    types::{parse_reference_type, parse_value_type},
+};
+use crate::{
+    ast::instructions::{
+        BlockType, MemArg, MemoryOp, NumericOp, Opcode, RawExpression, VectorLaneOp,
+        VectorMemoryLaneOp, VectorMemoryOp, VectorOp,
+    },
+    binary::error::{PResult, ParseErrorKind, fail},
 };
-use crate::ast::instructions::{Opcode, RawExpression};
 
-const END_OPCODE: u8 = 0x0b;
+/// Parses instructions up to the `end` which closes the expression.
+/// Nested blocks are tracked so that only the outermost `end` terminates it.
+/// The terminating `end` is consumed but not included in `RawExpression`.
+pub fn parse_expression(input: &[u8]) -> PResult<'_, RawExpression<'_>> {
+    let mut rest = input;
+    let mut depth = 0usize;
+    loop {
+        let (next, opcode) = parse_instruction(rest)?;
+        match opcode {
+            Opcode::Block(_) | Opcode::Loop(_) | Opcode::If(_) => depth += 1,
+            Opcode::End if depth == 0 => {
+                let instructions = &input[..input.len() - rest.len()];
+                return Ok((
+                    next,
+                    RawExpression {
+                        instructions: Cow::Borrowed(instructions),
+                    },
+                ));
+            }
+            Opcode::End => depth -= 1,
+            _ => (),
+        }
+        rest = next;
+    }
+}
+
+fn parse_block_type(input: &[u8]) -> PResult<'_, BlockType> {
+    alt((
+        map(tag(&[0x40][..]), |_| BlockType::Empty),
+        map(parse_value_type, BlockType::Value),
+        map_opt(parse_varint33, |i| {
+            u32::try_from(i).ok().map(BlockType::TypeIndex)
+        }),
+    ))
+    .parse(input)
+}
+
+fn parse_control_instruction(input: &[u8]) -> PResult<'_, Opcode> {
+    alt((
+        map(tag(&[0x00][..]), |_| Opcode::Unreachable),
+        map(tag(&[0x01][..]), |_| Opcode::Nop),
+        map((tag(&[0x02][..]), parse_block_type), |(_, t)| {
+            Opcode::Block(t)
+        }),
+        map((tag(&[0x03][..]), parse_block_type), |(_, t)| {
+            Opcode::Loop(t)
+        }),
+        map((tag(&[0x04][..]), parse_block_type), |(_, t)| Opcode::If(t)),
+        map(tag(&[0x05][..]), |_| Opcode::Else),
+        map(tag(&[0x0B][..]), |_| Opcode::End),
+        map((tag(&[0x0C][..]), parse_varuint32), |(_, l)| Opcode::Br(l)),
+        map((tag(&[0x0D][..]), parse_varuint32), |(_, l)| {
+            Opcode::BrIf(l)
+        }),
+        map(
+            (
+                tag(&[0x0E][..]),
+                length_count(parse_varuint32, parse_varuint32),
+                parse_varuint32,
+            ),
+            |(_, labels, default)| Opcode::BrTable(labels, default),
+        ),
+        map(tag(&[0x0F][..]), |_| Opcode::Return),
+        map((tag(&[0x10][..]), parse_varuint32), |(_, i)| {
+            Opcode::Call(i)
+        }),
+        map(
+            (tag(&[0x11][..]), parse_varuint32, parse_varuint32),
+            |(_, type_index, table_index)| Opcode::CallIndirect(type_index, table_index),
+        ),
+    ))
+    .parse(input)
+}
 
-pub fn parse_expression(input: &[u8]) -> IResult<&[u8], RawExpression<'_>> {
-    map(
-        terminated(take_until(&[END_OPCODE][..]), tag(&[END_OPCODE][..])),
-        |instructions| RawExpression { instructions },
-    )
+fn parse_parametric_instruction(input: &[u8]) -> PResult<'_, Opcode> {
+    alt((
+        map(tag(&[0x1A][..]), |_| Opcode::Drop),
+        map(tag(&[0x1B][..]), |_| Opcode::Select),
+        map(
+            (
+                tag(&[0x1C][..]),
+                length_count(parse_varuint32, parse_value_type),
+            ),
+            |(_, types)| Opcode::SelectTyped(types),
+        ),
+    ))
     .parse(input)
 }
 
-fn parse_numeric_const(input: &[u8]) -> IResult<&[u8], Opcode> {
+fn parse_numeric_const(input: &[u8]) -> PResult<'_, Opcode> {
     alt((
         map((tag(&[0x41][..]), parse_varint32), |(_, v)| {
             Opcode::I32Const(v)
@@ -37,6 +124,120 @@ fn parse_numeric_const(input: &[u8]) -> IResult<&[u8], Opcode> {
     .parse(input)
 }
 
+// bit 6 of the alignment field signals an explicit memory index (multi-memory)
+pub(crate) const MEMARG_HAS_MEMORY_INDEX: u32 = 0x40;
+
+fn parse_memarg(input: &[u8]) -> PResult<'_, MemArg> {
+    let (input, flags) = parse_varuint32(input)?;
+    let (input, memory_index) = if flags & MEMARG_HAS_MEMORY_INDEX != 0 {
+        parse_varuint32(input)?
+    } else {
+        (input, 0)
+    };
+    let (input, offset) = parse_varuint32(input)?;
+    Ok((
+        input,
+        MemArg {
+            align: flags & !MEMARG_HAS_MEMORY_INDEX,
+            offset,
+            memory_index,
+        },
+    ))
+}
+
+fn parse_memory_instruction(input: &[u8]) -> PResult<'_, Opcode> {
+    alt((
+        map(
+            (map_opt(u8, MemoryOp::from_byte), parse_memarg),
+            |(op, memarg)| Opcode::Memory(op, memarg),
+        ),
+        map((tag(&[0x3F][..]), parse_varuint32), |(_, i)| {
+            Opcode::MemorySize(i)
+        }),
+        map((tag(&[0x40][..]), parse_varuint32), |(_, i)| {
+            Opcode::MemoryGrow(i)
+        }),
+    ))
+    .parse(input)
+}
+
+fn parse_table_instruction(input: &[u8]) -> PResult<'_, Opcode> {
+    alt((
+        map((tag(&[0x25][..]), parse_varuint32), |(_, i)| {
+            Opcode::TableGet(i)
+        }),
+        map((tag(&[0x26][..]), parse_varuint32), |(_, i)| {
+            Opcode::TableSet(i)
+        }),
+    ))
+    .parse(input)
+}
+
+/// Instructions with the 0xFC prefix, followed by a u32 sub-opcode
+fn parse_fc_prefixed_instruction(input: &[u8]) -> PResult<'_, Opcode> {
+    let (input, (_, subopcode)) = (tag(&[0xFC][..]), parse_varuint32).parse(input)?;
+    let index = parse_varuint32;
+    let index_pair = (parse_varuint32, parse_varuint32);
+    match subopcode {
+        8 => map(index_pair, |(d, m)| Opcode::MemoryInit(d, m)).parse(input),
+        9 => map(index, Opcode::DataDrop).parse(input),
+        10 => map(index_pair, |(d, s)| Opcode::MemoryCopy(d, s)).parse(input),
+        11 => map(index, Opcode::MemoryFill).parse(input),
+        12 => map(index_pair, |(e, t)| Opcode::TableInit(e, t)).parse(input),
+        13 => map(index, Opcode::ElemDrop).parse(input),
+        14 => map(index_pair, |(d, s)| Opcode::TableCopy(d, s)).parse(input),
+        15 => map(index, Opcode::TableGrow).parse(input),
+        16 => map(index, Opcode::TableSize).parse(input),
+        17 => map(index, Opcode::TableFill).parse(input),
+        _ => match NumericOp::from_fc_subopcode(subopcode) {
+            Some(op) => Ok((input, Opcode::Numeric(op))),
+            None => fail(
+                input,
+                ParseErrorKind::UnknownPrefixedOpcode(0xFC, subopcode),
+            ),
+        },
+    }
+}
+
+fn parse_v128_bytes(input: &[u8]) -> PResult<'_, [u8; 16]> {
+    map(take(16usize), |b: &[u8]| {
+        b.try_into().expect("take should return exactly 16 bytes")
+    })
+    .parse(input)
+}
+
+/// Instructions with the 0xFD prefix (fixed-width SIMD), followed by a u32 sub-opcode
+fn parse_fd_prefixed_instruction(input: &[u8]) -> PResult<'_, Opcode> {
+    let (input, (_, subopcode)) = (tag(&[0xFD][..]), parse_varuint32).parse(input)?;
+    match subopcode {
+        0x0C => map(parse_v128_bytes, Opcode::V128Const).parse(input),
+        0x0D => map(parse_v128_bytes, Opcode::I8x16Shuffle).parse(input),
+        _ => {
+            if let Some(op) = VectorMemoryOp::from_subopcode(subopcode) {
+                map(parse_memarg, |memarg| Opcode::VectorMemory(op, memarg)).parse(input)
+            } else if let Some(op) = VectorMemoryLaneOp::from_subopcode(subopcode) {
+                map((parse_memarg, u8), |(memarg, lane)| {
+                    Opcode::VectorMemoryLane(op, memarg, lane)
+                })
+                .parse(input)
+            } else if let Some(op) = VectorLaneOp::from_subopcode(subopcode) {
+                map(u8, |lane| Opcode::VectorLane(op, lane)).parse(input)
+            } else if let Some(op) = VectorOp::from_subopcode(subopcode) {
+                Ok((input, Opcode::Vector(op)))
+            } else {
+                fail(
+                    input,
+                    ParseErrorKind::UnknownPrefixedOpcode(0xFD, subopcode),
+                )
+            }
+        }
+    }
+}
+
+fn parse_numeric_instruction(input: &[u8]) -> PResult<'_, Opcode> {
+    map_opt(u8, |b| NumericOp::from_byte(b).map(Opcode::Numeric)).parse(input)
+}
+
 macro_rules! variable_instruction {
     ($($b:literal => $v:ident),+ $(,)?) => {
         alt((
@@ -47,7 +248,7 @@ macro_rules! variable_instruction {
     };
 }
 
-fn parse_variable_instruction(input: &[u8]) -> IResult<&[u8], Opcode> {
+fn parse_variable_instruction(input: &[u8]) -> PResult<'_, Opcode> {
     variable_instruction! {
         0x20 => LocalGet,
         0x21 => LocalSet,
@@ -58,7 +259,7 @@ fn parse_variable_instruction(input: &[u8]) -> IResult<&[u8], Opcode> {
     .parse(input)
 }
 
-fn parse_reference_instruction(input: &[u8]) -> IResult<&[u8], Opcode> {
+fn parse_reference_instruction(input: &[u8]) -> PResult<'_, Opcode> {
     alt((
         map((tag(&[0xD0][..]), parse_reference_type), |(_, r)| {
             Opcode::RefNull(r)
@@ -71,12 +272,246 @@ fn parse_reference_instruction(input: &[u8]) -> IResult<&[u8], Opcode> {
     .parse(input)
 }
 
-pub fn parse_instruction(input: &[u8]) -> IResult<&[u8], Opcode> {
-    alt((
+pub fn parse_instruction(input: &[u8]) -> PResult<'_, Opcode> {
+    let result = alt((
+        parse_control_instruction,
+        parse_parametric_instruction,
         parse_reference_instruction,
         parse_numeric_const,
         parse_variable_instruction,
-        map(tag(&[0x6a][..]), |_| Opcode::I32Add),
+        parse_table_instruction,
+        parse_memory_instruction,
+        parse_numeric_instruction,
+        parse_fc_prefixed_instruction,
+        parse_fd_prefixed_instruction,
     ))
-    .parse(input)
+    .parse(input);
+    // when no group got past the opcode byte, the opcode itself is unknown
+    match (result, input.first()) {
+        (Err(nom::Err::Error(e)), Some(&byte)) if e.input.len() == input.len() => {
+            fail(input, ParseErrorKind::UnknownOpcode(byte))
+        }
+        (result, _) => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{
+        instructions::OpcodeEncoding,
+        types::{NumberType, ReferenceType},
+    };
+
+    #[test]
+    fn test_parse_expression_with_end_byte_in_immediate() {
+        // i32.const 11 (0x0b), end
+        let input = [0x41, 0x0b, 0x0b, 0xff];
+        let result = parse_expression(&input);
+        assert_eq!(
+            result,
+            Ok((
+                &[0xff][..],
+                RawExpression {
+                    instructions: Cow::Borrowed(&[0x41, 0x0b][..])
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_expression_with_nested_blocks() {
+        // block (result i32) loop i32.const 1 end end, end
+        let input = [0x02, 0x7f, 0x03, 0x40, 0x41, 0x01, 0x0b, 0x0b, 0x0b];
+        let result = parse_expression(&input);
+        assert_eq!(
+            result,
+            Ok((
+                &[][..],
+                RawExpression {
+                    instructions: Cow::Borrowed(&input[..8])
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_expression_unterminated() {
+        let input = [0x02, 0x40, 0x0b];
+        assert!(parse_expression(&input).is_err());
+    }
+
+    #[test]
+    fn test_parse_control_instruction() {
+        let test_cases = vec![
+            (&[0x00][..], Opcode::Unreachable),
+            (&[0x0c, 0x02][..], Opcode::Br(2)),
+            (
+                &[0x0e, 0x02, 0x00, 0x01, 0x02][..],
+                Opcode::BrTable(vec![0, 1], 2),
+            ),
+            (&[0x11, 0x03, 0x00][..], Opcode::CallIndirect(3, 0)),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(parse_instruction(input), Ok((&[][..], expected)));
+        }
+    }
+
+    #[test]
+    fn test_parse_numeric_instruction() {
+        let test_cases = vec![
+            (0x45, NumericOp::I32Eqz),
+            (0x6a, NumericOp::I32Add),
+            (0xa7, NumericOp::I32WrapI64),
+            (0xbf, NumericOp::F64ReinterpretI64),
+            (0xc4, NumericOp::I64Extend32S),
+        ];
+        for (byte, expected) in test_cases {
+            assert_eq!(expected.encoding(), OpcodeEncoding::Single(byte));
+            assert_eq!(
+                parse_instruction(&[byte]),
+                Ok((&[][..], Opcode::Numeric(expected)))
+            );
+        }
+        assert!(parse_numeric_instruction(&[0xc5]).is_err());
+
+        let input = [0xfc, 0x07];
+        assert_eq!(
+            parse_instruction(&input),
+            Ok((&[][..], Opcode::Numeric(NumericOp::I64TruncSatF64U)))
+        );
+        assert_eq!(
+            NumericOp::I64TruncSatF64U.encoding(),
+            OpcodeEncoding::Prefixed(0xfc, 7)
+        );
+    }
+
+    #[test]
+    fn test_parse_memory_instruction() {
+        let test_cases = vec![
+            (
+                &[0x28, 0x02, 0x10][..],
+                Opcode::Memory(
+                    MemoryOp::I32Load,
+                    MemArg {
+                        align: 2,
+                        offset: 16,
+                        memory_index: 0,
+                    },
+                ),
+            ),
+            (
+                &[0x3c, 0x40, 0x01, 0x00][..],
+                Opcode::Memory(
+                    MemoryOp::I64Store8,
+                    MemArg {
+                        align: 0,
+                        offset: 0,
+                        memory_index: 1,
+                    },
+                ),
+            ),
+            (&[0x3f, 0x00][..], Opcode::MemorySize(0)),
+            (&[0x40, 0x00][..], Opcode::MemoryGrow(0)),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(parse_instruction(input), Ok((&[][..], expected)));
+        }
+    }
+
+    #[test]
+    fn test_parse_parametric_instruction() {
+        let test_cases = vec![
+            (&[0x1a][..], Opcode::Drop),
+            (&[0x1b][..], Opcode::Select),
+            (
+                &[0x1c, 0x01, 0x6f][..],
+                Opcode::SelectTyped(vec![ReferenceType::ExternRef.into()]),
+            ),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(parse_instruction(input), Ok((&[][..], expected)));
+        }
+    }
+
+    #[test]
+    fn test_parse_fc_prefixed_instruction() {
+        let test_cases = vec![
+            (&[0xfc, 0x08, 0x01, 0x00][..], Opcode::MemoryInit(1, 0)),
+            (&[0xfc, 0x0b, 0x00][..], Opcode::MemoryFill(0)),
+            (&[0xfc, 0x0e, 0x01, 0x02][..], Opcode::TableCopy(1, 2)),
+            (&[0xfc, 0x91, 0x00, 0x03][..], Opcode::TableFill(3)), // non-minimal LEB128
+            (&[0x25, 0x00][..], Opcode::TableGet(0)),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(parse_instruction(input), Ok((&[][..], expected)));
+        }
+        assert!(parse_instruction(&[0xfc, 0x12, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_parse_unknown_opcode() {
+        let input = [0x41, 0x00, 0xff, 0x0b];
+        assert_eq!(
+            parse_expression(&input),
+            fail(&input[2..], ParseErrorKind::UnknownOpcode(0xff))
+        );
+        // a malformed immediate is reported rather than an unknown opcode
+        let input = [0x41, 0x80];
+        assert_eq!(
+            parse_instruction(&input),
+            fail(&input[1..], ParseErrorKind::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn test_parse_fd_prefixed_instruction() {
+        let mut v128_const = vec![0xfd, 0x0c];
+        v128_const.extend(1..=16);
+        let memarg = MemArg {
+            align: 0,
+            offset: 4,
+            memory_index: 0,
+        };
+        let test_cases = vec![
+            (
+                &v128_const[..],
+                Opcode::V128Const(core::array::from_fn(|i| i as u8 + 1)),
+            ),
+            (
+                &[0xfd, 0x15, 0x0f][..],
+                Opcode::VectorLane(VectorLaneOp::I8x16ExtractLaneS, 15),
+            ),
+            (
+                &[0xfd, 0x07, 0x00, 0x04][..],
+                Opcode::VectorMemory(VectorMemoryOp::V128Load8Splat, memarg),
+            ),
+            (
+                &[0xfd, 0x54, 0x00, 0x04, 0x03][..],
+                Opcode::VectorMemoryLane(VectorMemoryLaneOp::V128Load8Lane, memarg, 3),
+            ),
+            (&[0xfd, 0xae, 0x01][..], Opcode::Vector(VectorOp::I32x4Add)),
+            (
+                &[0xfd, 0xff, 0x01][..],
+                Opcode::Vector(VectorOp::F64x2ConvertLowI32x4U),
+            ),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(parse_instruction(input), Ok((&[][..], expected)));
+        }
+        // 0xa2 is an unassigned sub-opcode
+        assert!(parse_instruction(&[0xfd, 0xa2, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_parse_block_type() {
+        let test_cases = vec![
+            (&[0x40][..], BlockType::Empty),
+            (&[0x7f][..], BlockType::Value(NumberType::I32.into())),
+            (&[0x01][..], BlockType::TypeIndex(1)),
+        ];
+        for (input, expected) in test_cases {
+            assert_eq!(parse_block_type(input), Ok((&[][..], expected)));
+        }
+    }
 }
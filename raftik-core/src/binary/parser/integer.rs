@@ -1,43 +1,61 @@
 use nom::{
     Err as NomErr, IResult,
-    error::{ErrorKind, ParseError},
+    error::{ErrorKind, FromExternalError, ParseError},
 };
 
 use super::leb128::{Leb128Err, decode_sleb128_i64, decode_uleb128_u64};
 
+/// Errors which can describe a malformed LEB128 integer.
+pub trait LebError<'a>: ParseError<&'a [u8]> + FromExternalError<&'a [u8], Leb128Err> {}
+
+impl<'a, E> LebError<'a> for E where E: ParseError<&'a [u8]> + FromExternalError<&'a [u8], Leb128Err>
+{}
+
 const MAX_BYTES_32: usize = 5; // ceil(32/7)
+const MAX_BYTES_33: usize = 5; // ceil(33/7)
 const MAX_BYTES_64: usize = 10; // ceil(64/7)
 
-pub fn parse_varuint32<'a, E: ParseError<&'a [u8]>>(i: &'a [u8]) -> IResult<&'a [u8], u32, E> {
+/// Running out of input before the terminating byte is reported as end of input.
+fn leb_error<'a, E: LebError<'a>>(i: &'a [u8], max_bytes: usize, e: Leb128Err) -> NomErr<E> {
+    if e == Leb128Err::Unterminated && i.len() < max_bytes {
+        NomErr::Error(E::from_error_kind(i, ErrorKind::Eof))
+    } else {
+        NomErr::Error(E::from_external_error(i, ErrorKind::Fail, e))
+    }
+}
+
+pub fn parse_varuint32<'a, E: LebError<'a>>(i: &'a [u8]) -> IResult<&'a [u8], u32, E> {
     match decode_uleb128_u64(i, MAX_BYTES_32, 32) {
         Ok((v, used)) => Ok((&i[used..], v as u32)),
-        Err(Leb128Err::Unterminated) => Err(NomErr::Incomplete(nom::Needed::Unknown)),
-        Err(_) => Err(NomErr::Error(E::from_error_kind(i, ErrorKind::Fail))),
+        Err(e) => Err(leb_error(i, MAX_BYTES_32, e)),
     }
 }
 
 #[allow(dead_code)]
-pub fn parse_varuint64<'a, E: ParseError<&'a [u8]>>(i: &'a [u8]) -> IResult<&'a [u8], u64, E> {
+pub fn parse_varuint64<'a, E: LebError<'a>>(i: &'a [u8]) -> IResult<&'a [u8], u64, E> {
     match decode_uleb128_u64(i, MAX_BYTES_64, 64) {
         Ok((v, used)) => Ok((&i[used..], v)),
-        Err(Leb128Err::Unterminated) => Err(NomErr::Incomplete(nom::Needed::Unknown)),
-        Err(_) => Err(NomErr::Error(E::from_error_kind(i, ErrorKind::Fail))),
+        Err(e) => Err(leb_error(i, MAX_BYTES_64, e)),
     }
 }
 #[allow(dead_code)]
-pub fn parse_varint32<'a, E: ParseError<&'a [u8]>>(i: &'a [u8]) -> IResult<&'a [u8], i32, E> {
+pub fn parse_varint32<'a, E: LebError<'a>>(i: &'a [u8]) -> IResult<&'a [u8], i32, E> {
     match decode_sleb128_i64(i, MAX_BYTES_32, 32) {
         Ok((v, used)) => Ok((&i[used..], v as i32)),
-        Err(Leb128Err::Unterminated) => Err(NomErr::Incomplete(nom::Needed::Unknown)),
-        Err(_) => Err(NomErr::Error(E::from_error_kind(i, ErrorKind::Fail))),
+        Err(e) => Err(leb_error(i, MAX_BYTES_32, e)),
+    }
+}
+pub fn parse_varint33<'a, E: LebError<'a>>(i: &'a [u8]) -> IResult<&'a [u8], i64, E> {
+    match decode_sleb128_i64(i, MAX_BYTES_33, 33) {
+        Ok((v, used)) => Ok((&i[used..], v)),
+        Err(e) => Err(leb_error(i, MAX_BYTES_33, e)),
     }
 }
 #[allow(dead_code)]
-pub fn parse_varint64<'a, E: ParseError<&'a [u8]>>(i: &'a [u8]) -> IResult<&'a [u8], i64, E> {
+pub fn parse_varint64<'a, E: LebError<'a>>(i: &'a [u8]) -> IResult<&'a [u8], i64, E> {
     match decode_sleb128_i64(i, MAX_BYTES_64, 64) {
         Ok((v, used)) => Ok((&i[used..], v)),
-        Err(Leb128Err::Unterminated) => Err(NomErr::Incomplete(nom::Needed::Unknown)),
-        Err(_) => Err(NomErr::Error(E::from_error_kind(i, ErrorKind::Fail))),
+        Err(e) => Err(leb_error(i, MAX_BYTES_64, e)),
     }
 }
 
@@ -65,6 +83,13 @@ mod tests {
         assert_eq!(result, Ok((&[][..], -1)));
     }
 
+    #[test]
+    fn test_parse_varint33() {
+        let input = [0x80, 0x80, 0x80, 0x80, 0x08];
+        let result = parse_varint33::<nom::error::Error<&[u8]>>(&input);
+        assert_eq!(result, Ok((&[][..], 1 << 31)));
+    }
+
     #[test]
     fn test_parse_varint64() {
         let input = [0x7f];
@@ -1,6 +1,10 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Leb128Err {
+    #[error("unterminated")]
     Unterminated,
+    #[error("integer too large")]
     Overflow,
 }
 
@@ -54,7 +58,7 @@ pub fn decode_sleb128_i64(
         used += 1;
         shift += 7;
         if (b & 0x80) == 0 {
-            if (b & 0x40) != 0 {
+            if (b & 0x40) != 0 && shift < 64 {
                 // Sign extend if the sign bit is set
                 result |= -1i64 << shift;
             }
@@ -108,6 +112,13 @@ mod tests {
         assert_eq!(result, Ok((-123456, 3)));
     }
 
+    #[test]
+    fn test_decode_sleb128_i64_min() {
+        let input = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7f];
+        let result = decode_sleb128_i64(&input, 10, 64);
+        assert_eq!(result, Ok((i64::MIN, 10)));
+    }
+
     #[test]
     fn test_decode_sleb128_i32() {
         let input = [0xff, 0x7f];
@@ -1,10 +1,11 @@
+use std::{borrow::Cow, ops::Range};
+
 use nom::{
-    IResult, Parser,
-    branch::alt,
+    Parser,
     bytes::complete::{tag, take},
-    combinator::{all_consuming, flat_map, map, map_res},
-    multi::{length_count, many0},
-    number::complete::{le_u32, u8},
+    combinator::{consumed, flat_map, map},
+    multi::length_count,
+    number::complete::{le_u16, u8},
 };
 
 use super::{
@@ -13,49 +14,73 @@ use super::{
     name::parse_name,
     section_parser_trait::ParseSection,
     types::{
-        parse_function_type, parse_global_type, parse_memory_type, parse_reference_type,
+        invalid, parse_function_type, parse_global_type, parse_memory_type, parse_reference_type,
         parse_table_type, parse_value_type,
     },
 };
 use crate::ast::{
-    ModuleParsed,
+    BinaryVersion, ModuleParsed,
+    instructions::RawExpression,
     section::{
-        CodeSection, CustomSection, DataCountSection, DataMode, DataSection, DataSegment, Element,
-        ElementItems, ElementKind, ElementSection, Export, ExportDesc, ExportSection, FunctionBody,
-        FunctionSection, Global, GlobalSection, Import, ImportDesc, ImportSection, Locals,
-        MemorySection, Section, SectionID, StartSection, TableSection, TypeSection,
+        CodeSection, CustomSection, DataCountSection, DataMode, DataSection, DataSegment,
+        DecodedBody, Element, ElementItems, ElementKind, ElementSection, Export, ExportDesc,
+        ExportSection, FunctionBody, FunctionSection, Global, GlobalSection, Import, ImportDesc,
+        ImportSection, LazyCodeSection, LazyFunctionBody, Locals, MemorySection, Section,
+        SectionID, StartSection, TableSection, TypeSection,
     },
     types::ReferenceType,
 };
+use crate::binary::error::{DecodeError, PResult, ParseError, ParseErrorKind, fail};
 
-pub fn parse_module(input: &'_ [u8]) -> IResult<&[u8], ModuleParsed<'_>> {
+/// With `lazy_code`, the code section is read as a [`LazyCodeSection`].
+pub fn parse_module(input: &'_ [u8], lazy_code: bool) -> PResult<'_, ModuleParsed<'_>> {
     map(
-        all_consuming((parse_magic, parse_version, parse_sections)),
-        |(_, _, sections)| ModuleParsed { sections },
+        (parse_magic, parse_version, |input| {
+            parse_sections(input, lazy_code)
+        }),
+        |(_, version, sections)| ModuleParsed { version, sections },
     )
     .parse(input)
 }
 
+/// Parses a section's vector of entries, recording the index of the entry
+/// which failed to decode.
+fn section_entries<'a, O>(
+    mut entry: impl Parser<&'a [u8], Output = O, Error = DecodeError<'a>>,
+) -> impl Parser<&'a [u8], Output = Vec<O>, Error = DecodeError<'a>> {
+    move |input: &'a [u8]| {
+        let (mut input, count) = parse_varuint32(input)?;
+        let mut entries = Vec::new();
+        for index in 0..count {
+            let (rest, e) = entry
+                .parse(input)
+                .map_err(|err| err.map(|err| err.in_item(index)))?;
+            entries.push(e);
+            input = rest;
+        }
+        Ok((input, entries))
+    }
+}
+
 impl ParseSection<'_> for TypeSection {
-    fn parse_from_payload(payload: &[u8]) -> IResult<&[u8], Self> {
-        map(
-            length_count(parse_varuint32, parse_function_type),
-            |types| TypeSection { types },
-        )
+    fn parse_from_payload(payload: &[u8]) -> PResult<'_, Self> {
+        map(section_entries(parse_function_type), |types| TypeSection {
+            types,
+        })
         .parse(payload)
     }
 }
 
 impl ParseSection<'_> for ImportSection {
-    fn parse_from_payload(payload: &[u8]) -> IResult<&[u8], Self> {
-        map(length_count(parse_varuint32, parse_import), |imports| {
-            ImportSection { imports }
+    fn parse_from_payload(payload: &[u8]) -> PResult<'_, Self> {
+        map(section_entries(parse_import), |imports| ImportSection {
+            imports,
         })
         .parse(payload)
     }
 }
 
-fn parse_import(input: &[u8]) -> IResult<&[u8], Import> {
+fn parse_import(input: &[u8]) -> PResult<'_, Import> {
     map(
         (parse_name, parse_name, parse_import_desc),
         |(module, name, desc)| Import { module, name, desc },
@@ -63,63 +88,54 @@ fn parse_import(input: &[u8]) -> IResult<&[u8], Import> {
     .parse(input)
 }
 
-fn parse_import_desc(input: &[u8]) -> IResult<&[u8], ImportDesc> {
-    alt((
-        map((tag(&[0x00][..]), parse_varuint32), |(_, type_index)| {
-            ImportDesc::TypeIndex(type_index)
-        }),
-        map((tag(&[0x01][..]), parse_table_type), |(_, table_type)| {
-            ImportDesc::Table(table_type)
-        }),
-        map((tag(&[0x02][..]), parse_memory_type), |(_, memory_type)| {
-            ImportDesc::Memory(memory_type)
-        }),
-        map((tag(&[0x03][..]), parse_global_type), |(_, global_type)| {
-            ImportDesc::Global(global_type)
-        }),
-    ))
-    .parse(input)
+fn parse_import_desc(input: &[u8]) -> PResult<'_, ImportDesc> {
+    let (rest, kind) = u8(input)?;
+    match kind {
+        0x00 => map(parse_varuint32, ImportDesc::TypeIndex).parse(rest),
+        0x01 => map(parse_table_type, ImportDesc::Table).parse(rest),
+        0x02 => map(parse_memory_type, ImportDesc::Memory).parse(rest),
+        0x03 => map(parse_global_type, ImportDesc::Global).parse(rest),
+        _ => fail(input, invalid("import kind", kind)),
+    }
 }
 
 impl ParseSection<'_> for FunctionSection {
-    fn parse_from_payload(payload: &[u8]) -> IResult<&[u8], Self> {
-        map(
-            length_count(parse_varuint32, parse_varuint32),
-            |type_indices| FunctionSection { type_indices },
-        )
+    fn parse_from_payload(payload: &[u8]) -> PResult<'_, Self> {
+        map(section_entries(parse_varuint32), |type_indices| {
+            FunctionSection { type_indices }
+        })
         .parse(payload)
     }
 }
 
 impl ParseSection<'_> for TableSection {
-    fn parse_from_payload(payload: &[u8]) -> IResult<&[u8], Self> {
-        map(length_count(parse_varuint32, parse_table_type), |tables| {
-            TableSection { tables }
+    fn parse_from_payload(payload: &[u8]) -> PResult<'_, Self> {
+        map(section_entries(parse_table_type), |tables| TableSection {
+            tables,
         })
         .parse(payload)
     }
 }
 
 impl ParseSection<'_> for MemorySection {
-    fn parse_from_payload(payload: &[u8]) -> IResult<&[u8], Self> {
-        map(
-            length_count(parse_varuint32, parse_memory_type),
-            |memories| MemorySection { memories },
-        )
+    fn parse_from_payload(payload: &[u8]) -> PResult<'_, Self> {
+        map(section_entries(parse_memory_type), |memories| {
+            MemorySection { memories }
+        })
         .parse(payload)
     }
 }
 
 impl<'a> ParseSection<'a> for GlobalSection<'a> {
-    fn parse_from_payload(payload: &'a [u8]) -> IResult<&'a [u8], GlobalSection<'a>> {
-        map(length_count(parse_varuint32, parse_global), |globals| {
-            GlobalSection { globals }
+    fn parse_from_payload(payload: &'a [u8]) -> PResult<'a, GlobalSection<'a>> {
+        map(section_entries(parse_global), |globals| GlobalSection {
+            globals,
         })
         .parse(payload)
     }
 }
 
-fn parse_global(input: &[u8]) -> IResult<&[u8], Global<'_>> {
+fn parse_global(input: &[u8]) -> PResult<'_, Global<'_>> {
     map(
         (parse_global_type, parse_expression),
         |(global_type, expression)| Global {
@@ -131,15 +147,15 @@ fn parse_global(input: &[u8]) -> IResult<&[u8], Global<'_>> {
 }
 
 impl ParseSection<'_> for ExportSection {
-    fn parse_from_payload(payload: &[u8]) -> IResult<&[u8], Self> {
-        map(length_count(parse_varuint32, parse_export), |exports| {
-            ExportSection { exports }
+    fn parse_from_payload(payload: &[u8]) -> PResult<'_, Self> {
+        map(section_entries(parse_export), |exports| ExportSection {
+            exports,
         })
         .parse(payload)
     }
 }
 
-fn parse_export(input: &[u8]) -> IResult<&[u8], Export> {
+fn parse_export(input: &[u8]) -> PResult<'_, Export> {
     map((parse_name, parse_export_desc), |(name, desc)| Export {
         name,
         desc,
@@ -147,22 +163,19 @@ fn parse_export(input: &[u8]) -> IResult<&[u8], Export> {
     .parse(input)
 }
 
-fn parse_export_desc(input: &[u8]) -> IResult<&[u8], ExportDesc> {
-    map_res((u8, parse_varuint32), |(id, index)| match id {
-        0 => Ok(ExportDesc::FunctionIndex(index)),
-        1 => Ok(ExportDesc::TableIndex(index)),
-        2 => Ok(ExportDesc::MemoryIndex(index)),
-        3 => Ok(ExportDesc::GlobalIndex(index)),
-        _ => Err(nom::error::Error::<&[u8]> {
-            input,
-            code: nom::error::ErrorKind::Alt,
-        }),
-    })
-    .parse(input)
+fn parse_export_desc(input: &[u8]) -> PResult<'_, ExportDesc> {
+    let (rest, (kind, index)) = (u8, parse_varuint32).parse(input)?;
+    match kind {
+        0 => Ok((rest, ExportDesc::FunctionIndex(index))),
+        1 => Ok((rest, ExportDesc::TableIndex(index))),
+        2 => Ok((rest, ExportDesc::MemoryIndex(index))),
+        3 => Ok((rest, ExportDesc::GlobalIndex(index))),
+        _ => fail(input, invalid("export kind", kind)),
+    }
 }
 
 impl ParseSection<'_> for StartSection {
-    fn parse_from_payload(payload: &[u8]) -> IResult<&[u8], Self> {
+    fn parse_from_payload(payload: &[u8]) -> PResult<'_, Self> {
         map(parse_varuint32, |start_function_index| StartSection {
             start_function_index,
         })
@@ -171,16 +184,21 @@ impl ParseSection<'_> for StartSection {
 }
 
 impl<'a> ParseSection<'a> for ElementSection<'a> {
-    fn parse_from_payload(payload: &'a [u8]) -> IResult<&'a [u8], Self> {
-        map(length_count(parse_varuint32, parse_element), |elements| {
-            ElementSection { elements }
+    fn parse_from_payload(payload: &'a [u8]) -> PResult<'a, Self> {
+        map(section_entries(parse_element), |elements| ElementSection {
+            elements,
         })
         .parse(payload)
     }
 }
 
-fn parse_element(input: &[u8]) -> IResult<&[u8], Element<'_>> {
-    let (input, flag) = parse_varuint32(input)?;
+fn parse_element(input: &[u8]) -> PResult<'_, Element<'_>> {
+    let (input, flag) = match parse_varuint32(input)? {
+        (_, flag) if flag > 0b111 => {
+            return fail(input, ParseErrorKind::InvalidFlag("element segment", flag));
+        }
+        ok => ok,
+    };
     let (input, kind) = match flag & 0b11 {
         0b00 => {
             let (input, offset_expression) = parse_expression(input)?;
@@ -235,28 +253,111 @@ fn parse_element(input: &[u8]) -> IResult<&[u8], Element<'_>> {
 }
 
 impl<'a> ParseSection<'a> for CodeSection<'a> {
-    fn parse_from_payload(payload: &'a [u8]) -> IResult<&'a [u8], Self> {
-        map(length_count(parse_varuint32, parse_function_body), |code| {
-            CodeSection { code }
+    fn parse_from_payload(payload: &'a [u8]) -> PResult<'a, Self> {
+        map(section_entries(parse_function_body), |code| CodeSection {
+            code,
         })
         .parse(payload)
     }
 }
 
-fn parse_function_body(input: &[u8]) -> IResult<&[u8], FunctionBody<'_>> {
+fn parse_function_body(input: &[u8]) -> PResult<'_, FunctionBody<'_>> {
     let (input, raw_function_body) = flat_map(parse_varuint32, take).parse(input)?;
-    let (_, function_body) = map(
-        all_consuming((
-            length_count(parse_varuint32, parse_locals),
-            parse_expression,
-        )),
-        |(locals, expression)| FunctionBody { locals, expression },
-    )
-    .parse(raw_function_body)?;
+    let (_, function_body) = decode_function_body(raw_function_body)?;
     Ok((input, function_body))
 }
 
-fn parse_locals(input: &[u8]) -> IResult<&[u8], Locals> {
+/// Decodes the locals and expression of a body whose size prefix has been read.
+fn decode_function_body(input: &[u8]) -> PResult<'_, FunctionBody<'_>> {
+    let (rest, (locals, expression)) = (
+        length_count(parse_varuint32, parse_locals),
+        parse_expression,
+    )
+        .parse(input)?;
+    if !rest.is_empty() {
+        return fail(rest, ParseErrorKind::BodySizeMismatch(rest.len()));
+    }
+    Ok((rest, FunctionBody { locals, expression }))
+}
+
+/// Records the bytes of each body without decoding them. Offsets are relative to `payload`
+/// until [`ModuleParsed::from_slice_lazy`] makes them absolute.
+impl<'a> ParseSection<'a> for LazyCodeSection<'a> {
+    fn parse_from_payload(payload: &'a [u8]) -> PResult<'a, Self> {
+        let body = map(flat_map(parse_varuint32, take), |bytes: &'a [u8]| {
+            let offset = bytes.as_ptr() as usize - payload.as_ptr() as usize;
+            LazyFunctionBody::new(offset, Cow::Borrowed(bytes))
+        });
+        map(section_entries(body), |bodies| LazyCodeSection { bodies }).parse(payload)
+    }
+}
+
+impl LazyCodeSection<'_> {
+    pub fn len(&self) -> usize {
+        self.bodies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bodies.is_empty()
+    }
+
+    /// The body at `index`. It is decoded on the first call; later calls reuse the result.
+    pub fn get(&self, index: usize) -> Option<Result<FunctionBody<'_>, ParseError>> {
+        let body = self.bodies.get(index)?;
+        let decoded = body.decoded.get_or_init(|| body.decode(index as u32));
+        Some(match decoded {
+            Ok(DecodedBody { locals, expression }) => Ok(FunctionBody {
+                locals: locals.clone(),
+                expression: RawExpression {
+                    instructions: Cow::Borrowed(&body.bytes[expression.clone()]),
+                },
+            }),
+            Err(e) => Err(e.clone()),
+        })
+    }
+
+    /// Decodes the bodies in order; a malformed body yields an error without ending the iteration.
+    pub fn iter(&self) -> impl Iterator<Item = Result<FunctionBody<'_>, ParseError>> {
+        (0..self.len()).map(|index| self.get(index).unwrap())
+    }
+
+    /// Decodes all bodies into an eager code section, failing at the first malformed body.
+    pub fn to_code_section(&self) -> Result<CodeSection<'_>, ParseError> {
+        let code = self.iter().collect::<Result<_, _>>()?;
+        Ok(CodeSection { code })
+    }
+}
+
+impl LazyFunctionBody<'_> {
+    fn decode(&self, index: u32) -> Result<DecodedBody, ParseError> {
+        let bytes = &self.bytes[..];
+        match decode_function_body(bytes) {
+            Ok((_, FunctionBody { locals, expression })) => {
+                let start = expression.instructions.as_ptr() as usize - bytes.as_ptr() as usize;
+                Ok(DecodedBody {
+                    locals,
+                    expression: start..start + expression.instructions.len(),
+                })
+            }
+            Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                let mut error = e
+                    .in_section(SectionID::Code)
+                    .in_item(index)
+                    .into_parse_error(bytes);
+                error.offset += self.offset;
+                Err(error)
+            }
+            Err(nom::Err::Incomplete(_)) => Err(ParseError {
+                kind: ParseErrorKind::UnexpectedEof,
+                offset: self.offset + bytes.len(),
+                section: Some(SectionID::Code),
+                item: Some(index),
+            }),
+        }
+    }
+}
+
+fn parse_locals(input: &[u8]) -> PResult<'_, Locals> {
     map(
         (parse_varuint32, parse_value_type),
         |(count, value_type)| Locals { count, value_type },
@@ -265,24 +366,27 @@ fn parse_locals(input: &[u8]) -> IResult<&[u8], Locals> {
 }
 
 impl<'a> ParseSection<'a> for DataSection<'a> {
-    fn parse_from_payload(payload: &'a [u8]) -> IResult<&'a [u8], Self> {
-        map(
-            length_count(parse_varuint32, parse_data_segment),
-            |segments| DataSection { segments },
-        )
+    fn parse_from_payload(payload: &'a [u8]) -> PResult<'a, Self> {
+        map(section_entries(parse_data_segment), |segments| {
+            DataSection { segments }
+        })
         .parse(payload)
     }
 }
 
-fn parse_data_segment(input: &[u8]) -> IResult<&[u8], DataSegment<'_>> {
+fn parse_data_segment(input: &[u8]) -> PResult<'_, DataSegment<'_>> {
     map(
         (parse_data_mode, flat_map(parse_varuint32, take)),
-        |(mode, data)| DataSegment { mode, data },
+        |(mode, data)| DataSegment {
+            mode,
+            data: Cow::Borrowed(data),
+        },
     )
     .parse(input)
 }
 
-fn parse_data_mode(input: &[u8]) -> IResult<&[u8], DataMode<'_>> {
+fn parse_data_mode(input: &[u8]) -> PResult<'_, DataMode<'_>> {
+    let start = input;
     let (input, flag) = parse_varuint32(input)?;
     match flag {
         0 => map(parse_expression, |offset_expression| DataMode::Active {
@@ -299,45 +403,120 @@ fn parse_data_mode(input: &[u8]) -> IResult<&[u8], DataMode<'_>> {
             },
         )
         .parse(input),
-        _ => Err(nom::Err::Error(nom::error::Error::<&[u8]> {
-            input,
-            code: nom::error::ErrorKind::Alt,
-        })),
+        _ => fail(start, ParseErrorKind::InvalidFlag("data segment", flag)),
     }
 }
 
 impl ParseSection<'_> for DataCountSection {
-    fn parse_from_payload(payload: &[u8]) -> IResult<&[u8], Self> {
+    fn parse_from_payload(payload: &[u8]) -> PResult<'_, Self> {
         map(parse_varuint32, |count| DataCountSection { count }).parse(payload)
     }
 }
 
 impl<'a> ParseSection<'a> for CustomSection<'a> {
-    fn parse_from_payload(payload: &'a [u8]) -> IResult<&'a [u8], Self> {
+    fn parse_from_payload(payload: &'a [u8]) -> PResult<'a, Self> {
         let (payload, name) = parse_name(payload)?;
-        Ok((&[], CustomSection { name, payload }))
+        Ok((
+            &[],
+            CustomSection {
+                name,
+                payload: Cow::Borrowed(payload),
+            },
+        ))
     }
 }
-fn parse_magic(input: &[u8]) -> IResult<&[u8], &[u8; 4]> {
-    map(tag(&b"\0asm"[..]), |magic: &[u8]| {
-        magic.try_into().expect("magic should be exactly 4 bytes")
-    })
-    .parse(input)
+pub(super) fn parse_magic(input: &[u8]) -> PResult<'_, &[u8; 4]> {
+    match tag::<_, _, DecodeError>(&b"\0asm"[..]).parse(input) {
+        Ok((rest, magic)) => Ok((
+            rest,
+            magic.try_into().expect("magic should be exactly 4 bytes"),
+        )),
+        Err(_) => fail(input, ParseErrorKind::BadMagic),
+    }
 }
 
-fn parse_version(input: &[u8]) -> IResult<&[u8], u32> {
-    le_u32(input)
+pub(super) fn parse_version(input: &[u8]) -> PResult<'_, BinaryVersion> {
+    let (rest, (version, layer)) = (le_u16, le_u16).parse(input)?;
+    let version = BinaryVersion { version, layer };
+    // component-model binaries are layer 1
+    if layer == 1 {
+        return fail(input, ParseErrorKind::ComponentBinary(version.version));
+    }
+    if version != BinaryVersion::CORE_MODULE {
+        return fail(input, ParseErrorKind::UnsupportedVersion(version));
+    }
+    Ok((rest, version))
 }
 
-fn parse_sections(input: &[u8]) -> IResult<&[u8], Vec<Section<'_>>> {
-    many0(parse_section).parse(input)
+fn parse_sections(mut input: &[u8], lazy_code: bool) -> PResult<'_, Vec<Section<'_>>> {
+    let mut sections = Vec::new();
+    let mut last_position = 0u8;
+    while !input.is_empty() {
+        let (rest, section) = parse_section(input, lazy_code)?;
+        check_section_order(input, section.id(), &mut last_position)?;
+        sections.push(section);
+        input = rest;
+    }
+    Ok((input, sections))
 }
 
-fn parse_section(input: &[u8]) -> IResult<&[u8], Section<'_>> {
-    let (input, (id, payload)) =
-        (parse_section_id, flat_map(parse_varuint32, take)).parse(input)?;
+/// Fails if a section `id` starting at `input` may not follow the last non-custom section,
+/// whose position is `last_position`, and otherwise records it as the last one.
+/// Custom sections may appear anywhere.
+pub(super) fn check_section_order<'a>(
+    input: &'a [u8],
+    id: SectionID,
+    last_position: &mut u8,
+) -> Result<(), nom::Err<DecodeError<'a>>> {
+    let Some(position) = section_position(id) else {
+        return Ok(());
+    };
+    if position <= *last_position {
+        return Err(nom::Err::Error(
+            DecodeError::new(input, ParseErrorKind::SectionOutOfOrder(id)).in_section(id),
+        ));
+    }
+    *last_position = position;
+    Ok(())
+}
+
+/// Where a non-custom section belongs in a module. This differs from the id only for the
+/// data count section, which comes after the element section but before code and data.
+fn section_position(id: SectionID) -> Option<u8> {
+    match id {
+        SectionID::Custom => None,
+        SectionID::DataCount => Some(SectionID::Element as u8 + 1),
+        SectionID::Code | SectionID::Data => Some(id as u8 + 1),
+        _ => Some(id as u8),
+    }
+}
+
+pub(super) fn parse_section(input: &[u8], lazy_code: bool) -> PResult<'_, Section<'_>> {
+    let (input, (id, size)) = (parse_section_id, parse_varuint32).parse(input)?;
+    if size as usize > input.len() {
+        return Err(nom::Err::Error(
+            DecodeError::new(
+                input,
+                ParseErrorKind::SectionSizeOverrun {
+                    size,
+                    remaining: input.len(),
+                },
+            )
+            .in_section(id),
+        ));
+    }
+    let (payload, input) = input.split_at(size as usize);
+    let section =
+        parse_section_payload(id, payload, lazy_code).map_err(|e| e.map(|e| e.in_section(id)))?;
+    Ok((input, section))
+}
 
-    let section = match id {
+fn parse_section_payload(
+    id: SectionID,
+    payload: &[u8],
+    lazy_code: bool,
+) -> Result<Section<'_>, nom::Err<DecodeError<'_>>> {
+    Ok(match id {
         SectionID::Type => Section::Type(TypeSection::parse_all(payload)?),
         SectionID::Import => Section::Import(ImportSection::parse_all(payload)?),
         SectionID::Function => Section::Function(FunctionSection::parse_all(payload)?),
@@ -347,14 +526,39 @@ fn parse_section(input: &[u8]) -> IResult<&[u8], Section<'_>> {
         SectionID::Export => Section::Export(ExportSection::parse_all(payload)?),
         SectionID::Start => Section::Start(StartSection::parse_all(payload)?),
         SectionID::Element => Section::Element(ElementSection::parse_all(payload)?),
+        SectionID::Code if lazy_code => Section::LazyCode(LazyCodeSection::parse_all(payload)?),
         SectionID::Code => Section::Code(CodeSection::parse_all(payload)?),
         SectionID::Data => Section::Data(DataSection::parse_all(payload)?),
         SectionID::DataCount => Section::DataCount(DataCountSection::parse_all(payload)?),
         SectionID::Custom => Section::Custom(CustomSection::parse_all(payload)?),
+    })
+}
+
+/// Ranges of the function bodies (after their size prefix) or data segments in the payload
+/// of a code or data section, in item order; empty for other sections.
+/// The payload is expected to have been parsed successfully already.
+pub(super) fn item_ranges(id: SectionID, payload: &[u8]) -> Vec<Range<usize>> {
+    let range = |bytes: &[u8]| {
+        let start = bytes.as_ptr() as usize - payload.as_ptr() as usize;
+        start..start + bytes.len()
     };
-    Ok((input, section))
+    let ranges = match id {
+        SectionID::Code => {
+            section_entries(map(flat_map(parse_varuint32, take), range)).parse(payload)
+        }
+        SectionID::Data => {
+            section_entries(map(consumed(parse_data_segment), |(bytes, _)| range(bytes)))
+                .parse(payload)
+        }
+        _ => return Vec::new(),
+    };
+    ranges.map(|(_, ranges)| ranges).unwrap_or_default()
 }
 
-fn parse_section_id(input: &[u8]) -> IResult<&[u8], SectionID> {
-    map_res(u8, SectionID::try_from).parse(input)
+pub(super) fn parse_section_id(input: &[u8]) -> PResult<'_, SectionID> {
+    let (rest, id) = u8(input)?;
+    match SectionID::try_from(id) {
+        Ok(id) => Ok((rest, id)),
+        Err(_) => fail(input, ParseErrorKind::UnknownSectionId(id)),
+    }
 }
@@ -1,12 +1,13 @@
 use nom::{
-    IResult, Parser,
+    Parser,
     bytes::complete::take,
     combinator::{flat_map, map_res},
 };
 
 use super::integer::parse_varuint32;
+use crate::binary::error::PResult;
 
-pub fn parse_name(i: &[u8]) -> IResult<&[u8], String> {
+pub fn parse_name(i: &[u8]) -> PResult<'_, String> {
     map_res(flat_map(parse_varuint32, take), |b: &[u8]| {
         std::str::from_utf8(b).map(|s| s.to_string())
     })
@@ -0,0 +1,187 @@
+use std::ops::Range;
+
+use nom::Parser;
+
+use super::{
+    integer::parse_varuint32,
+    module::{
+        check_section_order, item_ranges, parse_magic, parse_section, parse_section_id,
+        parse_version,
+    },
+    to_parse_error,
+};
+use crate::{
+    ast::{BinaryVersion, Section, section::SectionID},
+    binary::error::{DecodeError, ParseError},
+};
+
+/// A section read by [`SectionReader`], with where it lives in the module binary.
+/// All offsets and ranges are relative to the start of the binary.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SectionEntry<'a> {
+    pub id: SectionID,
+    /// Offset of the section id byte.
+    pub offset: usize,
+    /// Range of the payload, which follows the id and size fields.
+    pub payload_range: Range<usize>,
+    pub payload: &'a [u8],
+    pub section: Section<'a>,
+    /// Ranges of the function bodies (after their size prefix) of a code section
+    /// or the segments of a data section, indexed like the items; empty for other sections.
+    pub item_ranges: Vec<Range<usize>>,
+}
+
+/// Iterates over the sections of a module binary one at a time,
+/// yielding the position and raw bytes of each alongside its decoded form.
+/// Iteration stops after the first error.
+#[derive(Debug, Clone)]
+pub struct SectionReader<'a> {
+    input: &'a [u8],
+    version: BinaryVersion,
+    /// Offset of the next section.
+    position: usize,
+    last_position: u8,
+    failed: bool,
+}
+
+impl<'a> SectionReader<'a> {
+    /// Reads the magic number and version; sections are read by iterating.
+    pub fn new(input: &'a [u8]) -> Result<Self, ParseError> {
+        let (rest, (_, version)) = (parse_magic, parse_version)
+            .parse(input)
+            .map_err(|e| to_parse_error(e, input))?;
+        Ok(Self {
+            input,
+            version,
+            position: input.len() - rest.len(),
+            last_position: 0,
+            failed: false,
+        })
+    }
+
+    pub fn version(&self) -> BinaryVersion {
+        self.version
+    }
+
+    fn read_section(&mut self) -> Result<SectionEntry<'a>, ParseError> {
+        let start = &self.input[self.position..];
+        let (rest, section) = parse_section(start, false)
+            .and_then(|(rest, section)| {
+                check_section_order(start, section.id(), &mut self.last_position)?;
+                Ok((rest, section))
+            })
+            .map_err(|e| to_parse_error(e, self.input))?;
+        let id = section.id();
+        let offset = self.position;
+        let end = self.input.len() - rest.len();
+        let payload_range = payload_start(start) + offset..end;
+        let payload = &self.input[payload_range.clone()];
+        let item_ranges = item_ranges(id, payload)
+            .into_iter()
+            .map(|r| r.start + payload_range.start..r.end + payload_range.start)
+            .collect();
+        self.position = end;
+        Ok(SectionEntry {
+            id,
+            offset,
+            payload_range,
+            payload,
+            section,
+            item_ranges,
+        })
+    }
+}
+
+/// Length of the id and size fields of a section which has been parsed successfully.
+fn payload_start(section: &[u8]) -> usize {
+    let (payload, _) = (parse_section_id, parse_varuint32::<DecodeError>)
+        .parse(section)
+        .expect("section header should have been parsed");
+    section.len() - payload.len()
+}
+
+impl<'a> Iterator for SectionReader<'a> {
+    type Item = Result<SectionEntry<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.position == self.input.len() {
+            return None;
+        }
+        let entry = self.read_section();
+        self.failed = entry.is_err();
+        Some(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::ModuleParsed, binary::error::ParseErrorKind};
+
+    #[test]
+    fn test_section_reader() {
+        let wasm = wat::parse_str(
+            r#"
+(module
+  (memory 1)
+  (func (export "f") (result i32) i32.const 1)
+  (func (export "g") nop)
+  (data (i32.const 0) "hi")
+  (data "passive"))
+"#,
+        )
+        .unwrap();
+        let module = ModuleParsed::from_slice(&wasm).unwrap();
+        let entries: Vec<_> = SectionReader::new(&wasm)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let sections: Vec<_> = entries.iter().map(|e| e.section.clone()).collect();
+        assert_eq!(sections, module.sections);
+        assert_eq!(entries[0].offset, 8);
+        for (entry, next) in entries.iter().zip(entries.iter().skip(1)) {
+            assert_eq!(entry.payload_range.end, next.offset);
+        }
+        for entry in &entries {
+            assert_eq!(entry.payload, &wasm[entry.payload_range.clone()]);
+            assert_eq!(entry.payload_range.start, entry.offset + 2);
+        }
+
+        let code = entries.iter().find(|e| e.id == SectionID::Code).unwrap();
+        let bodies: Vec<_> = code.item_ranges.iter().map(|r| &wasm[r.clone()]).collect();
+        assert_eq!(
+            bodies,
+            vec![&[0x00, 0x41, 0x01, 0x0b][..], &[0x00, 0x01, 0x0b]]
+        );
+
+        let data = entries.iter().find(|e| e.id == SectionID::Data).unwrap();
+        let segments: Vec<_> = data.item_ranges.iter().map(|r| &wasm[r.clone()]).collect();
+        assert_eq!(segments[0], &[0x00, 0x41, 0x00, 0x0b, 0x02, b'h', b'i']);
+        assert_eq!(segments[1], b"\x01\x07passive");
+        assert_eq!(data.payload_range.end, wasm.len());
+    }
+
+    #[test]
+    fn test_section_reader_error() {
+        let mut wasm = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        wasm.extend([0x05, 0x03, 0x01, 0x00, 0x01]); // memory section
+        wasm.extend([0x0c, 0x01]); // data count section without its count
+        let mut reader = SectionReader::new(&wasm).unwrap();
+        assert_eq!(reader.version(), BinaryVersion::CORE_MODULE);
+        assert_eq!(reader.next().unwrap().unwrap().item_ranges, vec![]);
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::SectionSizeOverrun {
+                size: 1,
+                remaining: 0
+            }
+        );
+        assert_eq!(reader.next(), None);
+
+        assert_eq!(
+            SectionReader::new(b"\0asm").unwrap_err().kind,
+            ParseErrorKind::UnexpectedEof
+        );
+    }
+}
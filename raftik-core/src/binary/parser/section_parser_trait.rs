@@ -1,11 +1,16 @@
-use nom::{IResult, Parser, combinator::all_consuming};
+use crate::binary::error::{DecodeError, PResult, ParseErrorKind};
 
 pub trait ParseSection<'a>: Sized {
-    fn parse_from_payload(payload: &'a [u8]) -> IResult<&'a [u8], Self>;
+    fn parse_from_payload(payload: &'a [u8]) -> PResult<'a, Self>;
 
-    fn parse_all(payload: &'a [u8]) -> Result<Self, nom::Err<nom::error::Error<&'a [u8]>>> {
-        all_consuming(Self::parse_from_payload)
-            .parse(payload)
-            .map(|(_, result)| result)
+    fn parse_all(payload: &'a [u8]) -> Result<Self, nom::Err<DecodeError<'a>>> {
+        let (rest, result) = Self::parse_from_payload(payload)?;
+        if !rest.is_empty() {
+            return Err(nom::Err::Error(DecodeError::new(
+                rest,
+                ParseErrorKind::SectionSizeMismatch(rest.len()),
+            )));
+        }
+        Ok(result)
     }
 }
@@ -0,0 +1,245 @@
+use nom::Parser;
+
+use super::{
+    integer::parse_varuint32,
+    module::{check_section_order, parse_magic, parse_section, parse_section_id, parse_version},
+    to_parse_error,
+};
+use crate::{
+    ast::{BinaryVersion, Section},
+    binary::error::{DecodeError, ParseError, ParseErrorKind},
+};
+
+/// Size of the magic number and the version fields.
+const HEADER_SIZE: usize = 8;
+
+/// What [`StreamParser::next_event`] found in the bytes pushed so far.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StreamEvent {
+    /// The magic number and version have been read.
+    Header(BinaryVersion),
+    /// A section whose payload has arrived completely.
+    Section(Section<'static>),
+    /// At least this many more bytes must be pushed before the next event.
+    NeedMoreData(usize),
+    /// The input ended after the last section.
+    End,
+}
+
+/// A push parser which decodes a module one section at a time as its bytes arrive,
+/// so that early sections can be processed before the rest is available.
+#[derive(Debug, Default)]
+pub struct StreamParser {
+    /// Bytes pushed but not consumed yet.
+    buffer: Vec<u8>,
+    /// Offset of `buffer[0]` in the module binary.
+    offset: usize,
+    header_read: bool,
+    last_position: u8,
+    end_of_input: bool,
+}
+
+impl StreamParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Tells the parser that no more bytes will be pushed;
+    /// from then on an incomplete section is an error instead of [`StreamEvent::NeedMoreData`].
+    pub fn end_of_input(&mut self) {
+        self.end_of_input = true;
+    }
+
+    /// Decodes the next header or section if its bytes are complete.
+    pub fn next_event(&mut self) -> Result<StreamEvent, ParseError> {
+        if !self.header_read {
+            if self.buffer.len() < HEADER_SIZE {
+                return self.need(HEADER_SIZE - self.buffer.len());
+            }
+            let (_, (_, version)) = (parse_magic, parse_version)
+                .parse(&self.buffer)
+                .map_err(|e| self.error(e))?;
+            self.header_read = true;
+            self.consume(HEADER_SIZE);
+            return Ok(StreamEvent::Header(version));
+        }
+        if self.buffer.is_empty() {
+            return if self.end_of_input {
+                Ok(StreamEvent::End)
+            } else {
+                self.need(1)
+            };
+        }
+        let Some(size) = self.section_size()? else {
+            return self.need(1);
+        };
+        if self.buffer.len() < size {
+            return self.need(size - self.buffer.len());
+        }
+        let input = &self.buffer[..size];
+        let section = parse_section(input, false)
+            .and_then(|(_, section)| {
+                check_section_order(input, section.id(), &mut self.last_position)?;
+                Ok(section)
+            })
+            .map_err(|e| self.error(e))?
+            .into_owned();
+        self.consume(size);
+        Ok(StreamEvent::Section(section))
+    }
+
+    /// Size of the section at the start of the buffer including its id and size fields,
+    /// or `None` if the size field has not arrived completely.
+    fn section_size(&self) -> Result<Option<usize>, ParseError> {
+        parse_section_id(&self.buffer).map_err(|e| self.error(e))?;
+        // the size is a LEB128 u32 of at most 5 bytes; longer ones are rejected below
+        let size_field = &self.buffer[1..];
+        let Some(len) = size_field
+            .iter()
+            .take(5)
+            .position(|b| b & 0x80 == 0)
+            .map(|i| i + 1)
+            .or((size_field.len() >= 5).then_some(5))
+        else {
+            return Ok(None);
+        };
+        let (_, size) =
+            parse_varuint32::<DecodeError>(&size_field[..len]).map_err(|e| self.error(e))?;
+        Ok(Some(1 + len + size as usize))
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.buffer.drain(..len);
+        self.offset += len;
+    }
+
+    fn need(&self, len: usize) -> Result<StreamEvent, ParseError> {
+        if self.end_of_input {
+            Err(ParseError {
+                kind: ParseErrorKind::UnexpectedEof,
+                offset: self.offset + self.buffer.len(),
+                section: None,
+                item: None,
+            })
+        } else {
+            Ok(StreamEvent::NeedMoreData(len))
+        }
+    }
+
+    /// Converts an error within the buffer, using offsets in the whole binary.
+    fn error(&self, e: nom::Err<DecodeError>) -> ParseError {
+        let mut error = to_parse_error(e, &self.buffer);
+        error.offset += self.offset;
+        error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{ModuleParsed, section::SectionID};
+
+    /// Pushes `wasm` in chunks of `chunk_size` bytes, collecting the events.
+    fn stream(wasm: &[u8], chunk_size: usize) -> Vec<StreamEvent> {
+        let mut parser = StreamParser::new();
+        let mut events = Vec::new();
+        let mut chunks = wasm.chunks(chunk_size);
+        loop {
+            match parser.next_event().unwrap() {
+                StreamEvent::NeedMoreData(_) => match chunks.next() {
+                    Some(chunk) => parser.push(chunk),
+                    None => parser.end_of_input(),
+                },
+                StreamEvent::End => break,
+                event => events.push(event),
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn test_stream_in_chunks() {
+        let wasm = wat::parse_str(
+            r#"
+(module
+  (import "env" "f" (func (param i32)))
+  (memory 1)
+  (func (export "g") (param i32) local.get 0 call 0)
+  (data (i32.const 0) "hello"))
+"#,
+        )
+        .unwrap();
+        let module = ModuleParsed::from_slice(&wasm).unwrap().into_owned();
+        let mut expected = vec![StreamEvent::Header(module.version)];
+        expected.extend(module.sections.into_iter().map(StreamEvent::Section));
+        for chunk_size in [1, 3, 7, wasm.len()] {
+            assert_eq!(
+                stream(&wasm, chunk_size),
+                expected,
+                "chunk size {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_need_more_data() {
+        let mut parser = StreamParser::new();
+        parser.push(&[0x00, 0x61, 0x73]);
+        assert_eq!(parser.next_event(), Ok(StreamEvent::NeedMoreData(5)));
+        parser.push(&[0x6d, 0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(
+            parser.next_event(),
+            Ok(StreamEvent::Header(BinaryVersion::CORE_MODULE))
+        );
+        // a type section of 4 bytes whose size field is split
+        parser.push(&[0x01]);
+        assert_eq!(parser.next_event(), Ok(StreamEvent::NeedMoreData(1)));
+        parser.push(&[0x84]);
+        assert_eq!(parser.next_event(), Ok(StreamEvent::NeedMoreData(1)));
+        parser.push(&[0x00, 0x01]);
+        assert_eq!(parser.next_event(), Ok(StreamEvent::NeedMoreData(3)));
+        parser.push(&[0x60, 0x00, 0x00]);
+        assert!(matches!(
+            parser.next_event(),
+            Ok(StreamEvent::Section(Section::Type(_)))
+        ));
+        assert_eq!(parser.next_event(), Ok(StreamEvent::NeedMoreData(1)));
+        parser.end_of_input();
+        assert_eq!(parser.next_event(), Ok(StreamEvent::End));
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let header = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        let mut parser = StreamParser::new();
+        parser.push(&header);
+        parser.push(&[0x05, 0x01, 0x00, 0x01, 0x01, 0x00]); // memory section, then a type section
+        parser.next_event().unwrap();
+        parser.next_event().unwrap();
+        assert_eq!(
+            parser.next_event(),
+            Err(ParseError {
+                kind: ParseErrorKind::SectionOutOfOrder(SectionID::Type),
+                offset: 11,
+                section: Some(SectionID::Type),
+                item: None,
+            })
+        );
+
+        let mut parser = StreamParser::new();
+        parser.push(&header);
+        parser.push(&[0x01, 0x04, 0x01]);
+        parser.next_event().unwrap();
+        assert_eq!(parser.next_event(), Ok(StreamEvent::NeedMoreData(3)));
+        parser.end_of_input();
+        assert_eq!(
+            parser.next_event().unwrap_err().kind,
+            ParseErrorKind::UnexpectedEof
+        );
+    }
+}
@@ -1,26 +1,25 @@
 use nom::{
-    IResult, Parser,
-    branch::alt,
-    bytes::complete::tag,
-    combinator::{map, map_res},
-    multi::length_count,
-    number::complete::u8,
+    Parser, bytes::complete::tag, combinator::map, multi::length_count, number::complete::u8,
 };
 
 use super::integer::parse_varuint32;
-use crate::ast::types::{
-    FunctionType, GlobalType, Limits, MemoryType, Mutability, ReferenceType, TableType, ValueType,
+use crate::{
+    ast::types::{
+        FunctionType, GlobalType, Limits, MemoryType, Mutability, ReferenceType, TableType,
+        ValueType,
+    },
+    binary::error::{PResult, ParseErrorKind, fail},
 };
 
-pub fn parse_value_type(input: &[u8]) -> IResult<&[u8], ValueType> {
-    let (input, value_type_byte) = u8(input)?;
-    let value_type: ValueType = value_type_byte.try_into().map_err(|_| {
-        nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::IsNot))
-    })?;
-    Ok((input, value_type))
+pub fn parse_value_type(input: &[u8]) -> PResult<'_, ValueType> {
+    let (rest, byte) = u8(input)?;
+    match ValueType::try_from(byte) {
+        Ok(value_type) => Ok((rest, value_type)),
+        Err(_) => fail(input, invalid("value type", byte)),
+    }
 }
 
-pub fn parse_function_type(input: &[u8]) -> IResult<&[u8], FunctionType> {
+pub fn parse_function_type(input: &[u8]) -> PResult<'_, FunctionType> {
     map(
         (
             tag(&[0x60u8][..]),
@@ -32,32 +31,32 @@ pub fn parse_function_type(input: &[u8]) -> IResult<&[u8], FunctionType> {
     .parse(input)
 }
 
-pub fn parse_reference_type(input: &[u8]) -> IResult<&[u8], ReferenceType> {
-    map_res(u8, |byte| byte.try_into()).parse(input)
+pub fn parse_reference_type(input: &[u8]) -> PResult<'_, ReferenceType> {
+    let (rest, byte) = u8(input)?;
+    match ReferenceType::try_from(byte) {
+        Ok(ref_type) => Ok((rest, ref_type)),
+        Err(_) => fail(input, invalid("reference type", byte)),
+    }
 }
 
-pub fn parse_limits(input: &[u8]) -> IResult<&[u8], Limits> {
-    alt((
-        map((tag(&[0x00][..]), parse_varuint32), |(_, min)| Limits {
+pub fn parse_limits(input: &[u8]) -> PResult<'_, Limits> {
+    let (rest, flag) = u8(input)?;
+    match flag {
+        0x00 => map(parse_varuint32, |min| Limits { min, max: None }).parse(rest),
+        0x01 => map((parse_varuint32, parse_varuint32), |(min, max)| Limits {
             min,
-            max: None,
-        }),
-        map(
-            (tag(&[0x01][..]), parse_varuint32, parse_varuint32),
-            |(_, min, max)| Limits {
-                min,
-                max: Some(max),
-            },
-        ),
-    ))
-    .parse(input)
+            max: Some(max),
+        })
+        .parse(rest),
+        _ => fail(input, invalid("limits flag", flag)),
+    }
 }
 
-pub fn parse_memory_type(input: &[u8]) -> IResult<&[u8], MemoryType> {
+pub fn parse_memory_type(input: &[u8]) -> PResult<'_, MemoryType> {
     map(parse_limits, |limits| MemoryType { limits }).parse(input)
 }
 
-pub fn parse_table_type(input: &[u8]) -> IResult<&[u8], TableType> {
+pub fn parse_table_type(input: &[u8]) -> PResult<'_, TableType> {
     map(
         (parse_reference_type, parse_limits),
         |(ref_type, limits)| TableType { ref_type, limits },
@@ -65,15 +64,21 @@ pub fn parse_table_type(input: &[u8]) -> IResult<&[u8], TableType> {
     .parse(input)
 }
 
-pub fn parse_mutability(input: &[u8]) -> IResult<&[u8], Mutability> {
-    alt((
-        map(tag(&[0x00][..]), |_| Mutability::Const),
-        map(tag(&[0x01][..]), |_| Mutability::Var),
-    ))
-    .parse(input)
+pub fn parse_mutability(input: &[u8]) -> PResult<'_, Mutability> {
+    let (rest, byte) = u8(input)?;
+    match byte {
+        0x00 => Ok((rest, Mutability::Const)),
+        0x01 => Ok((rest, Mutability::Var)),
+        _ => fail(input, invalid("mutability", byte)),
+    }
+}
+
+/// Error kind for a byte which does not encode any of the expected values.
+pub fn invalid(what: &'static str, byte: u8) -> ParseErrorKind {
+    ParseErrorKind::InvalidByte { what, byte }
 }
 
-pub fn parse_global_type(input: &[u8]) -> IResult<&[u8], GlobalType> {
+pub fn parse_global_type(input: &[u8]) -> PResult<'_, GlobalType> {
     map(
         (parse_value_type, parse_mutability),
         |(val_type, mutability)| GlobalType {
@@ -152,7 +157,7 @@ mod tests {
     fn test_parse_limits_fails() {
         let input1 = [0x02, 0x01]; // Invalid tag for limits
         let result1 = parse_limits(&input1);
-        assert!(result1.is_err());
+        assert_eq!(result1, fail(&input1[..], invalid("limits flag", 0x02)));
     }
 
     #[test]
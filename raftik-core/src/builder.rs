@@ -0,0 +1,509 @@
+use std::borrow::Cow;
+
+use crate::ast::{
+    BinaryVersion, CodeSection, DataCountSection, DataSection, ElementSection, ExportSection,
+    FunctionSection, GlobalSection, ImportSection, MemorySection, ModuleOwned, Section,
+    StartSection, TableSection, TypeSection,
+    instructions::{Opcode, RawExpression},
+    section::{
+        DataMode, DataSegment, Element, ElementItems, ElementKind, Export, ExportDesc,
+        FunctionBody, Global, Import, ImportDesc, Locals,
+    },
+    types::{FunctionType, GlobalType, MemoryType, ReferenceType, TableType, ValueType},
+};
+
+/// Builds a module item by item, handing out the index of each item as it is added.
+///
+/// Imports come first in each index space, so all imports of a kind must be
+/// added before any definition of that kind.
+#[derive(Debug, Default)]
+pub struct ModuleBuilder {
+    types: Vec<FunctionType>,
+    imports: Vec<Import>,
+    functions: Vec<u32>,
+    code: Vec<FunctionBody<'static>>,
+    tables: Vec<TableType>,
+    memories: Vec<MemoryType>,
+    globals: Vec<Global<'static>>,
+    exports: Vec<Export>,
+    start: Option<u32>,
+    elements: Vec<Element<'static>>,
+    data: Vec<DataSegment<'static>>,
+    /// Set when a function uses `memory.init` or `data.drop`.
+    needs_data_count: bool,
+    imported_functions: u32,
+    imported_tables: u32,
+    imported_memories: u32,
+    imported_globals: u32,
+}
+
+impl ModuleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the index of `function_type`, adding it only if it is not already present.
+    pub fn add_type(&mut self, function_type: FunctionType) -> u32 {
+        match self.types.iter().position(|t| *t == function_type) {
+            Some(index) => index as u32,
+            None => self.push_type(function_type),
+        }
+    }
+
+    /// Adds `function_type` even if an equal type is present,
+    /// as explicit types in the text format are.
+    pub(crate) fn push_type(&mut self, function_type: FunctionType) -> u32 {
+        self.types.push(function_type);
+        self.types.len() as u32 - 1
+    }
+
+    pub(crate) fn get_type(&self, type_index: u32) -> Option<&FunctionType> {
+        self.types.get(type_index as usize)
+    }
+
+    /// Number of functions so far, which is the index of the next one.
+    pub fn function_count(&self) -> u32 {
+        self.imported_functions + self.functions.len() as u32
+    }
+
+    pub fn table_count(&self) -> u32 {
+        self.imported_tables + self.tables.len() as u32
+    }
+
+    pub fn memory_count(&self) -> u32 {
+        self.imported_memories + self.memories.len() as u32
+    }
+
+    pub fn global_count(&self) -> u32 {
+        self.imported_globals + self.globals.len() as u32
+    }
+
+    /// # Panics
+    /// Panics if a function has already been defined.
+    pub fn import_function(&mut self, module: &str, name: &str, ty: FunctionType) -> u32 {
+        assert!(
+            self.functions.is_empty(),
+            "functions must be imported before any is defined"
+        );
+        let type_index = self.add_type(ty);
+        self.add_import(module, name, ImportDesc::TypeIndex(type_index))
+    }
+
+    /// # Panics
+    /// Panics if a table has already been defined.
+    pub fn import_table(&mut self, module: &str, name: &str, table_type: TableType) -> u32 {
+        assert!(
+            self.tables.is_empty(),
+            "tables must be imported before any is defined"
+        );
+        self.add_import(module, name, ImportDesc::Table(table_type))
+    }
+
+    /// # Panics
+    /// Panics if a memory has already been defined.
+    pub fn import_memory(&mut self, module: &str, name: &str, memory_type: MemoryType) -> u32 {
+        assert!(
+            self.memories.is_empty(),
+            "memories must be imported before any is defined"
+        );
+        self.add_import(module, name, ImportDesc::Memory(memory_type))
+    }
+
+    /// # Panics
+    /// Panics if a global has already been defined.
+    pub fn import_global(&mut self, module: &str, name: &str, global_type: GlobalType) -> u32 {
+        assert!(
+            self.globals.is_empty(),
+            "globals must be imported before any is defined"
+        );
+        self.add_import(module, name, ImportDesc::Global(global_type))
+    }
+
+    /// Adds an import without checking that no item of its kind has been defined yet.
+    pub(crate) fn add_import(&mut self, module: &str, name: &str, desc: ImportDesc) -> u32 {
+        let (index, count) = match desc {
+            ImportDesc::TypeIndex(_) => (self.function_count(), &mut self.imported_functions),
+            ImportDesc::Table(_) => (self.table_count(), &mut self.imported_tables),
+            ImportDesc::Memory(_) => (self.memory_count(), &mut self.imported_memories),
+            ImportDesc::Global(_) => (self.global_count(), &mut self.imported_globals),
+        };
+        *count += 1;
+        self.imports.push(Import {
+            module: module.to_string(),
+            name: name.to_string(),
+            desc,
+        });
+        index
+    }
+
+    /// Adds a function whose body is `body` without the closing `end`.
+    /// Adjacent locals of the same type are grouped together.
+    pub fn add_function(&mut self, ty: FunctionType, locals: &[ValueType], body: &[Opcode]) -> u32 {
+        let type_index = self.add_type(ty);
+        self.add_function_with_type_index(type_index, locals, body)
+    }
+
+    pub(crate) fn add_function_with_type_index(
+        &mut self,
+        type_index: u32,
+        locals: &[ValueType],
+        body: &[Opcode],
+    ) -> u32 {
+        let mut grouped: Vec<Locals> = Vec::new();
+        for &value_type in locals {
+            match grouped.last_mut() {
+                Some(last) if last.value_type == value_type => last.count += 1,
+                _ => grouped.push(Locals {
+                    count: 1,
+                    value_type,
+                }),
+            }
+        }
+        self.needs_data_count |= body
+            .iter()
+            .any(|op| matches!(op, Opcode::MemoryInit(..) | Opcode::DataDrop(_)));
+        self.functions.push(type_index);
+        self.code.push(FunctionBody {
+            locals: grouped,
+            expression: RawExpression::from_opcodes(body),
+        });
+        self.function_count() - 1
+    }
+
+    pub fn add_table(&mut self, table_type: TableType) -> u32 {
+        self.tables.push(table_type);
+        self.table_count() - 1
+    }
+
+    pub fn add_memory(&mut self, memory_type: MemoryType) -> u32 {
+        self.memories.push(memory_type);
+        self.memory_count() - 1
+    }
+
+    pub fn add_global(&mut self, global_type: GlobalType, init: &[Opcode]) -> u32 {
+        self.globals.push(Global {
+            global_type,
+            expression: RawExpression::from_opcodes(init),
+        });
+        self.global_count() - 1
+    }
+
+    pub fn export(&mut self, name: &str, desc: ExportDesc) {
+        self.exports.push(Export {
+            name: name.to_string(),
+            desc,
+        });
+    }
+
+    pub fn set_start(&mut self, function_index: u32) {
+        self.start = Some(function_index);
+    }
+
+    /// Adds an element segment. An active segment of non-funcref items without a table index
+    /// gets table 0, since only funcref elements can leave it out in the binary format.
+    pub fn add_element(&mut self, mut element: Element<'static>) -> u32 {
+        if let (
+            ElementKind::Active { table_index, .. },
+            ElementItems::Expressions(ReferenceType::ExternRef, _),
+        ) = (&mut element.kind, &element.items)
+        {
+            table_index.get_or_insert(0);
+        }
+        self.elements.push(element);
+        self.elements.len() as u32 - 1
+    }
+
+    /// Adds an active element segment placing `functions` in the table at `offset`.
+    pub fn add_active_elements(
+        &mut self,
+        table_index: u32,
+        offset: &[Opcode],
+        functions: Vec<u32>,
+    ) -> u32 {
+        self.add_element(Element {
+            kind: ElementKind::Active {
+                table_index: (table_index != 0).then_some(table_index),
+                offset_expression: RawExpression::from_opcodes(offset),
+            },
+            items: ElementItems::Functions(functions),
+        })
+    }
+
+    /// Adds an active data segment which initializes memory at `offset`.
+    pub fn add_active_data(
+        &mut self,
+        memory_index: u32,
+        offset: &[Opcode],
+        data: impl Into<Vec<u8>>,
+    ) -> u32 {
+        self.add_data(DataSegment {
+            mode: DataMode::Active {
+                memory_index: (memory_index != 0).then_some(memory_index),
+                offset_expression: RawExpression::from_opcodes(offset),
+            },
+            data: Cow::Owned(data.into()),
+        })
+    }
+
+    pub fn add_passive_data(&mut self, data: impl Into<Vec<u8>>) -> u32 {
+        self.add_data(DataSegment {
+            mode: DataMode::Passive,
+            data: Cow::Owned(data.into()),
+        })
+    }
+
+    fn add_data(&mut self, segment: DataSegment<'static>) -> u32 {
+        self.data.push(segment);
+        self.data.len() as u32 - 1
+    }
+
+    /// Assembles the sections in binary order, leaving out empty ones.
+    /// A data count section is included when a function uses `memory.init` or `data.drop`,
+    /// which do not validate without it.
+    pub fn build(self) -> ModuleOwned {
+        let mut sections = Vec::new();
+        if !self.types.is_empty() {
+            sections.push(Section::Type(TypeSection { types: self.types }));
+        }
+        if !self.imports.is_empty() {
+            sections.push(Section::Import(ImportSection {
+                imports: self.imports,
+            }));
+        }
+        if !self.functions.is_empty() {
+            sections.push(Section::Function(FunctionSection {
+                type_indices: self.functions,
+            }));
+        }
+        if !self.tables.is_empty() {
+            sections.push(Section::Table(TableSection {
+                tables: self.tables,
+            }));
+        }
+        if !self.memories.is_empty() {
+            sections.push(Section::Memory(MemorySection {
+                memories: self.memories,
+            }));
+        }
+        if !self.globals.is_empty() {
+            sections.push(Section::Global(GlobalSection {
+                globals: self.globals,
+            }));
+        }
+        if !self.exports.is_empty() {
+            sections.push(Section::Export(ExportSection {
+                exports: self.exports,
+            }));
+        }
+        if let Some(start_function_index) = self.start {
+            sections.push(Section::Start(StartSection {
+                start_function_index,
+            }));
+        }
+        if !self.elements.is_empty() {
+            sections.push(Section::Element(ElementSection {
+                elements: self.elements,
+            }));
+        }
+        if self.needs_data_count {
+            sections.push(Section::DataCount(DataCountSection {
+                count: self.data.len() as u32,
+            }));
+        }
+        if !self.code.is_empty() {
+            sections.push(Section::Code(CodeSection { code: self.code }));
+        }
+        if !self.data.is_empty() {
+            sections.push(Section::Data(DataSection {
+                segments: self.data,
+            }));
+        }
+        ModuleOwned {
+            version: BinaryVersion::CORE_MODULE,
+            sections,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{
+            ModuleParsed,
+            instructions::{MemArg, MemoryOp, NumericOp},
+            section::SectionID,
+            types::{Limits, Mutability, NumberType},
+        },
+        validation::validate_module,
+    };
+
+    fn i32_binop() -> FunctionType {
+        FunctionType {
+            params: vec![NumberType::I32.into(), NumberType::I32.into()],
+            results: vec![NumberType::I32.into()],
+        }
+    }
+
+    #[test]
+    fn test_build_matches_wat() {
+        let mut builder = ModuleBuilder::new();
+        let add = builder.add_function(
+            i32_binop(),
+            &[],
+            &[
+                Opcode::LocalGet(0),
+                Opcode::LocalGet(1),
+                Opcode::Numeric(NumericOp::I32Add),
+            ],
+        );
+        builder.export("add", ExportDesc::FunctionIndex(add));
+        let module = builder.build();
+
+        let wasm = wat::parse_str(
+            r#"(module (func (export "add") (param i32 i32) (result i32)
+                 local.get 0 local.get 1 i32.add))"#,
+        )
+        .unwrap();
+        assert_eq!(module, ModuleParsed::from_slice(&wasm).unwrap());
+    }
+
+    #[test]
+    fn test_indices_and_type_deduplication() {
+        let mut builder = ModuleBuilder::new();
+        let imported = builder.import_function("env", "add", i32_binop());
+        let memory = builder.import_memory(
+            "env",
+            "memory",
+            MemoryType {
+                limits: Limits { min: 1, max: None },
+            },
+        );
+        let add = builder.add_function(
+            i32_binop(),
+            &[NumberType::I32.into(), NumberType::I32.into()],
+            &[
+                Opcode::LocalGet(0),
+                Opcode::LocalGet(1),
+                Opcode::Call(imported),
+            ],
+        );
+        assert_eq!((imported, memory, add), (0, 0, 1));
+
+        let module = builder.build();
+        let Some(Section::Type(types)) = module.sec_by_id(SectionID::Type) else {
+            unreachable!()
+        };
+        assert_eq!(types.types.len(), 1);
+        let Some(Section::Code(code)) = module.sec_by_id(SectionID::Code) else {
+            unreachable!()
+        };
+        assert_eq!(
+            code.code[0].locals,
+            vec![Locals {
+                count: 2,
+                value_type: NumberType::I32.into()
+            }]
+        );
+        assert!(validate_module(&module).is_ok());
+    }
+
+    #[test]
+    fn test_build_segments() {
+        let mut builder = ModuleBuilder::new();
+        let void = FunctionType {
+            params: vec![],
+            results: vec![],
+        };
+        let table = builder.add_table(TableType {
+            ref_type: ReferenceType::FuncRef,
+            limits: Limits { min: 1, max: None },
+        });
+        let memory = builder.add_memory(MemoryType {
+            limits: Limits { min: 1, max: None },
+        });
+        let counter = builder.add_global(
+            GlobalType {
+                val_type: NumberType::I32.into(),
+                mutability: Mutability::Var,
+            },
+            &[Opcode::I32Const(0)],
+        );
+        let passive = builder.add_passive_data(b"hello".to_vec());
+        let init = builder.add_function(
+            void,
+            &[],
+            &[
+                Opcode::I32Const(0),
+                Opcode::I32Const(0),
+                Opcode::I32Const(5),
+                Opcode::MemoryInit(passive, memory),
+                Opcode::DataDrop(passive),
+                Opcode::I32Const(16),
+                Opcode::Memory(
+                    MemoryOp::I32Load,
+                    MemArg {
+                        align: 2,
+                        offset: 0,
+                        memory_index: memory,
+                    },
+                ),
+                Opcode::GlobalSet(counter),
+            ],
+        );
+        builder.add_active_data(memory, &[Opcode::I32Const(16)], b"\x01\0\0\0".to_vec());
+        builder.add_active_elements(table, &[Opcode::I32Const(0)], vec![init]);
+        builder.set_start(init);
+        let module = builder.build();
+
+        let r = validate_module(&module);
+        assert!(r.is_ok(), "{:#?}", r);
+        let bytes = module.to_bytes();
+        assert_eq!(ModuleParsed::from_slice(&bytes).unwrap(), module);
+    }
+
+    #[test]
+    fn test_build_externref_elements() {
+        let mut builder = ModuleBuilder::new();
+        builder.add_table(TableType {
+            ref_type: ReferenceType::ExternRef,
+            limits: Limits { min: 1, max: None },
+        });
+        let element = builder.add_element(Element {
+            kind: ElementKind::Active {
+                table_index: None,
+                offset_expression: RawExpression::from_opcodes(&[Opcode::I32Const(0)]),
+            },
+            items: ElementItems::Expressions(
+                ReferenceType::ExternRef,
+                vec![RawExpression::from_opcodes(&[Opcode::RefNull(
+                    ReferenceType::ExternRef,
+                )])],
+            ),
+        });
+        let module = builder.build();
+
+        let Some(Section::Element(elements)) = module.sec_by_id(SectionID::Element) else {
+            unreachable!("element section expected");
+        };
+        assert!(matches!(
+            elements.elements[element as usize].kind,
+            ElementKind::Active {
+                table_index: Some(0),
+                ..
+            }
+        ));
+        let r = validate_module(&module);
+        assert!(r.is_ok(), "{:#?}", r);
+        let bytes = module.to_bytes();
+        assert_eq!(ModuleParsed::from_slice(&bytes).unwrap(), module);
+    }
+
+    #[test]
+    #[should_panic(expected = "functions must be imported before any is defined")]
+    fn test_import_after_definition() {
+        let mut builder = ModuleBuilder::new();
+        builder.add_function(i32_binop(), &[], &[Opcode::Unreachable]);
+        builder.import_function("env", "f", i32_binop());
+    }
+}
@@ -0,0 +1,36 @@
+mod code;
+pub mod error;
+mod func;
+mod handle;
+mod host;
+mod instance;
+mod interpreter;
+mod linker;
+mod numeric;
+mod store;
+mod value;
+
+pub use func::{Func, TypedFunc};
+pub use handle::{Global, Memory, Table};
+pub use host::{Caller, HostFunction, HostResults, IntoFunc};
+pub use instance::{
+    ExportInstance, Extern, FunctionInstance, GlobalInstance, Instance, MAX_TABLE_SIZE,
+    MemoryInstance, ModuleInstance, PAGE_SIZE, TableInstance,
+};
+pub use linker::Linker;
+pub use store::Store;
+pub use value::{Value, WasmType, WasmTypeList};
+
+/// Instantiates the module `wat` in `store` with `imports`.
+#[cfg(test)]
+fn instantiate(
+    store: &mut Store,
+    wat: &str,
+    imports: &[Extern],
+) -> Result<Instance, error::InstantiationError> {
+    let wasm = wat::parse_str(wat).unwrap();
+    store.instantiate(
+        &crate::ast::ModuleParsed::from_slice(&wasm).unwrap(),
+        imports,
+    )
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use nom::combinator::iterator;
+
+use crate::{
+    ast::{
+        instructions::{Opcode, RawExpression},
+        section::Locals,
+        types::ValueType,
+    },
+    binary::parser::instructions::parse_instruction,
+};
+
+/// A function body or constant expression decoded once for execution,
+/// with the structure of its blocks resolved so that branches can jump directly.
+#[derive(Debug, Default)]
+pub(super) struct Code {
+    /// Declared locals, following the parameters.
+    pub locals: Vec<ValueType>,
+    pub instructions: Vec<Opcode>,
+    /// Index of the matching `end` for each `block`, `loop`, `if` and `else`.
+    pub ends: HashMap<usize, usize>,
+    /// Index of the `else` for each `if` which has one.
+    pub elses: HashMap<usize, usize>,
+}
+
+impl Code {
+    /// Decodes an expression which has been validated.
+    pub fn new(locals: &[Locals], expression: &RawExpression) -> Self {
+        let mut code = Code {
+            locals: locals
+                .iter()
+                .flat_map(|l| std::iter::repeat_n(l.value_type, l.count as usize))
+                .collect(),
+            ..Default::default()
+        };
+        let mut open = Vec::new();
+        let mut it = iterator(&expression.instructions[..], parse_instruction);
+        for (pc, opcode) in (&mut it).enumerate() {
+            match opcode {
+                Opcode::Block(_) | Opcode::Loop(_) | Opcode::If(_) => open.push(pc),
+                Opcode::Else => {
+                    let start = *open.last().expect("else should be inside if");
+                    code.elses.insert(start, pc);
+                }
+                Opcode::End => {
+                    let start = open.pop().expect("end should close a block");
+                    code.ends.insert(start, pc);
+                    if let Some(&else_pc) = code.elses.get(&start) {
+                        code.ends.insert(else_pc, pc);
+                    }
+                }
+                _ => (),
+            }
+            code.instructions.push(opcode);
+        }
+        it.finish().expect("validated expression should decode");
+        code
+    }
+}
@@ -0,0 +1,197 @@
+use thiserror::Error;
+
+use crate::{
+    ast::types::{FunctionType, GlobalType, MemoryType, TableType, ValueType},
+    validation::error::ValidationError,
+};
+
+/// Conditions which abort execution, as defined by the specification.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum Trap {
+    #[error("unreachable executed")]
+    Unreachable,
+
+    #[error("integer divide by zero")]
+    IntegerDivideByZero,
+
+    #[error("integer overflow")]
+    IntegerOverflow,
+
+    #[error("invalid conversion to integer")]
+    InvalidConversionToInteger,
+
+    #[error("out of bounds memory access")]
+    MemoryOutOfBounds,
+
+    #[error("out of bounds table access")]
+    TableOutOfBounds,
+
+    #[error("undefined element {0}")]
+    UndefinedElement(u32),
+
+    #[error("uninitialized element {0}")]
+    UninitializedElement(u32),
+
+    #[error("indirect call type mismatch")]
+    IndirectCallTypeMismatch,
+
+    #[error("call stack exhausted")]
+    CallStackExhausted,
+
+    /// Raised by a host function.
+    #[error("host function trapped: {0}")]
+    Host(String),
+
+    #[error("host function returned {actual:?} instead of {expected:?}")]
+    HostResultTypeMismatch {
+        expected: Vec<ValueType>,
+        actual: Vec<ValueType>,
+    },
+
+    /// Not a trap of the specification: the interpreter does not implement the instruction.
+    #[error("unsupported instruction: {0}")]
+    Unsupported(&'static str),
+}
+
+#[derive(Error, Debug)]
+pub enum InstantiationError {
+    #[error("invalid module: {0}")]
+    Invalid(#[from] ValidationError),
+
+    #[error("module has {expected} imports but {actual} were given")]
+    ImportCountMismatch { expected: usize, actual: usize },
+
+    #[error("import {module}.{name} is incompatible: {reason}")]
+    IncompatibleImport {
+        module: String,
+        name: String,
+        reason: Box<ImportMismatch>,
+    },
+
+    #[error(transparent)]
+    Allocation(#[from] AllocationError),
+
+    #[error("trap during instantiation: {0}")]
+    Trap(#[from] Trap),
+}
+
+/// Why a table or memory cannot be created.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum AllocationError {
+    #[error("cannot allocate a table of {0} elements")]
+    Table(u32),
+
+    #[error("cannot allocate a memory of {0} pages")]
+    Memory(u32),
+}
+
+/// Why a value does not satisfy the type of an import.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum ImportMismatch {
+    #[error("no {kind} at address {address} in this store")]
+    UnknownAddress { kind: &'static str, address: u32 },
+
+    #[error("expected a {expected}, found a {actual}")]
+    Kind {
+        expected: &'static str,
+        actual: &'static str,
+    },
+
+    #[error("expected function type {expected:?}, found {actual:?}")]
+    Function {
+        expected: FunctionType,
+        actual: FunctionType,
+    },
+
+    #[error("expected table type {expected:?}, found {actual:?}")]
+    Table {
+        expected: TableType,
+        actual: TableType,
+    },
+
+    #[error("expected memory type {expected:?}, found {actual:?}")]
+    Memory {
+        expected: MemoryType,
+        actual: MemoryType,
+    },
+
+    #[error("expected global type {expected:?}, found {actual:?}")]
+    Global {
+        expected: GlobalType,
+        actual: GlobalType,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum LinkError {
+    #[error("unknown import {module}.{name}")]
+    MissingImport { module: String, name: String },
+
+    #[error("{module}.{name} is already defined")]
+    DuplicateDefinition { module: String, name: String },
+
+    #[error(transparent)]
+    Instantiation(#[from] InstantiationError),
+
+    #[error(transparent)]
+    Access(#[from] AccessError),
+}
+
+/// Why the host cannot reach or modify an object in a store as requested.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum AccessError {
+    #[error("handle belongs to another store")]
+    WrongStore,
+
+    #[error("no export named {0:?}")]
+    UnknownExport(String),
+
+    #[error("export {name:?} is a {actual}, not a {expected}")]
+    ExportKind {
+        name: String,
+        expected: &'static str,
+        actual: &'static str,
+    },
+
+    #[error("global is immutable")]
+    ImmutableGlobal,
+
+    #[error("expected a value of type {expected:?}, found {actual:?}")]
+    TypeMismatch {
+        expected: ValueType,
+        actual: ValueType,
+    },
+
+    #[error("no function at address {0} in this store")]
+    UnknownFunction(u32),
+
+    #[error("table index {0} out of bounds")]
+    OutOfBounds(u32),
+
+    #[error("table cannot grow by {0} elements")]
+    GrowFailed(u32),
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum InvokeError {
+    #[error("no exported function named {0:?}")]
+    NoSuchFunction(String),
+
+    #[error("argument types {actual:?} do not match parameter types {expected:?}")]
+    ArgumentTypeMismatch {
+        expected: Vec<ValueType>,
+        actual: Vec<ValueType>,
+    },
+
+    #[error("function type {actual:?} does not match the expected type {expected:?}")]
+    SignatureMismatch {
+        expected: FunctionType,
+        actual: FunctionType,
+    },
+
+    #[error(transparent)]
+    Access(#[from] AccessError),
+
+    #[error(transparent)]
+    Trap(#[from] Trap),
+}
@@ -0,0 +1,208 @@
+use std::marker::PhantomData;
+
+use super::{
+    error::{AccessError, InvokeError},
+    interpreter,
+    store::{Store, StoreId},
+    value::{Value, WasmTypeList},
+};
+use crate::ast::types::FunctionType;
+
+/// A function in a [`Store`], e.g. one exported by an instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Func {
+    pub(super) store: StoreId,
+    pub(super) address: u32,
+}
+
+impl Func {
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    pub fn ty<'a>(&self, store: &'a Store) -> Result<&'a FunctionType, AccessError> {
+        store.check_id(self.store)?;
+        Ok(&store.functions[self.address as usize].ty)
+    }
+
+    pub fn call(&self, store: &mut Store, args: &[Value]) -> Result<Vec<Value>, InvokeError> {
+        store.check_id(self.store)?;
+        store.invoke(self.address, args)
+    }
+
+    /// Checks the function type once so that calls can pass and return Rust values.
+    pub fn typed<Params: WasmTypeList, Results: WasmTypeList>(
+        &self,
+        store: &Store,
+    ) -> Result<TypedFunc<Params, Results>, InvokeError> {
+        let expected = FunctionType {
+            params: Params::types(),
+            results: Results::types(),
+        };
+        let actual = self.ty(store)?;
+        if *actual != expected {
+            return Err(InvokeError::SignatureMismatch {
+                expected,
+                actual: actual.clone(),
+            });
+        }
+        Ok(TypedFunc {
+            func: *self,
+            marker: PhantomData,
+        })
+    }
+}
+
+/// A [`Func`] whose type is known to be `Params -> Results`.
+#[derive(Debug)]
+pub struct TypedFunc<Params, Results> {
+    func: Func,
+    marker: PhantomData<fn(Params) -> Results>,
+}
+
+impl<Params, Results> Clone for TypedFunc<Params, Results> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Params, Results> Copy for TypedFunc<Params, Results> {}
+
+impl<Params: WasmTypeList, Results: WasmTypeList> TypedFunc<Params, Results> {
+    pub fn func(&self) -> Func {
+        self.func
+    }
+
+    pub fn call(&self, store: &mut Store, params: Params) -> Result<Results, InvokeError> {
+        store.check_id(self.func.store)?;
+        let results = interpreter::call(store, self.func.address, params.into_values())?;
+        Ok(Results::from_values(&results).expect("results match the checked function type"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::types::{NumberType, ValueType},
+        exec::{error::Trap, instantiate},
+    };
+
+    #[test]
+    fn test_typed_funcs() {
+        let mut store = Store::new();
+        let instance = instantiate(
+            &mut store,
+            r#"
+(module
+  (func (export "add") (param i32 i32) (result i32) (i32.add (local.get 0) (local.get 1)))
+  (func (export "swap") (param i64 f64) (result f64 i64) (local.get 1) (local.get 0))
+  (func (export "nothing"))
+  (func (export "trap") (param i32) (result i32) (i32.div_u (i32.const 1) (local.get 0)))
+  (memory (export "memory") 1))
+"#,
+            &[],
+        )
+        .unwrap();
+        let add = instance.get_func(&store, "add").unwrap();
+        let typed = add.typed::<(i32, i32), i32>(&store).unwrap();
+        assert_eq!(typed.call(&mut store, (1, 2)), Ok(3));
+        assert_eq!(typed.call(&mut store, (i32::MAX, 1)), Ok(i32::MIN));
+        assert_eq!(
+            add.call(&mut store, &[Value::I32(2), Value::I32(3)]),
+            Ok(vec![Value::I32(5)])
+        );
+
+        let swap = instance.get_func(&store, "swap").unwrap();
+        let swap = swap.typed::<(i64, f64), (f64, i64)>(&store).unwrap();
+        assert_eq!(swap.call(&mut store, (7, 0.5)), Ok((0.5, 7)));
+
+        let nothing = instance.get_func(&store, "nothing").unwrap();
+        assert_eq!(
+            nothing
+                .typed::<(), ()>(&store)
+                .unwrap()
+                .call(&mut store, ()),
+            Ok(())
+        );
+
+        let trap = instance.get_func(&store, "trap").unwrap();
+        let trap = trap.typed::<i32, i32>(&store).unwrap();
+        assert_eq!(
+            trap.call(&mut store, 0),
+            Err(InvokeError::Trap(Trap::IntegerDivideByZero))
+        );
+
+        let i32_type = ValueType::Number(NumberType::I32);
+        assert_eq!(
+            add.typed::<(i32, i64), i32>(&store).unwrap_err(),
+            InvokeError::SignatureMismatch {
+                expected: FunctionType {
+                    params: vec![i32_type, ValueType::Number(NumberType::I64)],
+                    results: vec![i32_type],
+                },
+                actual: FunctionType {
+                    params: vec![i32_type, i32_type],
+                    results: vec![i32_type],
+                },
+            }
+        );
+        assert!(add.typed::<(i32, i32), ()>(&store).is_err());
+        assert_eq!(
+            instance.get_func(&store, "memory"),
+            Err(AccessError::ExportKind {
+                name: "memory".to_string(),
+                expected: "function",
+                actual: "memory",
+            })
+        );
+        assert_eq!(
+            instance.get_func(&store, "missing"),
+            Err(AccessError::UnknownExport("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_export_access() {
+        let mut store = Store::new();
+        let instance = instantiate(
+            &mut store,
+            r#"
+(module
+  (memory (export "memory") 1)
+  (table (export "table") 2 funcref)
+  (global (export "counter") (mut i32) (i32.const 1))
+  (func (export "load") (result i32) (i32.load (i32.const 8)))
+  (func (export "counter_value") (result i32) (global.get 0)))
+"#,
+            &[],
+        )
+        .unwrap();
+        let memory = instance.get_memory(&store, "memory").unwrap();
+        memory.data_mut(&mut store).unwrap()[8] = 42;
+        assert_eq!(memory.size(&store), Ok(1));
+        let counter_global = instance.get_global(&store, "counter").unwrap();
+        counter_global.set(&mut store, Value::I32(5)).unwrap();
+        assert_eq!(
+            instance.get_table(&store, "table").unwrap().size(&store),
+            Ok(2)
+        );
+        assert!(instance.get_memory(&store, "table").is_err());
+        assert!(instance.get_global(&store, "load").is_err());
+        assert!(instance.get_table(&store, "missing").is_err());
+
+        let load = instance.get_func(&store, "load").unwrap();
+        let counter = instance.get_func(&store, "counter_value").unwrap();
+        assert_eq!(
+            load.typed::<(), i32>(&store).unwrap().call(&mut store, ()),
+            Ok(42)
+        );
+        assert_eq!(
+            counter
+                .typed::<(), i32>(&store)
+                .unwrap()
+                .call(&mut store, ()),
+            Ok(5)
+        );
+    }
+}
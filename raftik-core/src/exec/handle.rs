@@ -0,0 +1,244 @@
+use super::{
+    error::AccessError,
+    instance::{GlobalInstance, MemoryInstance, TableInstance},
+    store::{Store, StoreId},
+    value::Value,
+};
+use crate::ast::types::{GlobalType, MemoryType, Mutability, TableType};
+
+/// A global in a [`Store`], e.g. one exported by an instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Global {
+    pub(super) store: StoreId,
+    pub(super) address: u32,
+}
+
+impl Global {
+    fn instance<'a>(&self, store: &'a Store) -> Result<&'a GlobalInstance, AccessError> {
+        store.check_id(self.store)?;
+        Ok(&store.globals[self.address as usize])
+    }
+
+    pub fn ty<'a>(&self, store: &'a Store) -> Result<&'a GlobalType, AccessError> {
+        Ok(&self.instance(store)?.ty)
+    }
+
+    pub fn get(&self, store: &Store) -> Result<Value, AccessError> {
+        Ok(self.instance(store)?.value)
+    }
+
+    /// Sets the value of a mutable global.
+    pub fn set(&self, store: &mut Store, value: Value) -> Result<(), AccessError> {
+        let ty = self.ty(store)?.clone();
+        if let Mutability::Const = ty.mutability {
+            return Err(AccessError::ImmutableGlobal);
+        }
+        store.check_value(ty.val_type, value)?;
+        store.globals[self.address as usize].value = value;
+        Ok(())
+    }
+}
+
+/// A table in a [`Store`], e.g. one exported by an instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Table {
+    pub(super) store: StoreId,
+    pub(super) address: u32,
+}
+
+impl Table {
+    fn instance<'a>(&self, store: &'a Store) -> Result<&'a TableInstance, AccessError> {
+        store.check_id(self.store)?;
+        Ok(&store.tables[self.address as usize])
+    }
+
+    pub fn ty<'a>(&self, store: &'a Store) -> Result<&'a TableType, AccessError> {
+        Ok(&self.instance(store)?.ty)
+    }
+
+    pub fn size(&self, store: &Store) -> Result<u32, AccessError> {
+        Ok(self.instance(store)?.size())
+    }
+
+    pub fn get(&self, store: &Store, index: u32) -> Result<Value, AccessError> {
+        self.instance(store)?
+            .elements
+            .get(index as usize)
+            .copied()
+            .ok_or(AccessError::OutOfBounds(index))
+    }
+
+    /// Sets the element at `index` to a reference of the element type.
+    pub fn set(&self, store: &mut Store, index: u32, value: Value) -> Result<(), AccessError> {
+        store.check_value(self.ty(store)?.ref_type.into(), value)?;
+        let element = store.tables[self.address as usize]
+            .elements
+            .get_mut(index as usize)
+            .ok_or(AccessError::OutOfBounds(index))?;
+        *element = value;
+        Ok(())
+    }
+
+    /// Grows by `delta` elements set to `init`, returning the old size.
+    pub fn grow(&self, store: &mut Store, delta: u32, init: Value) -> Result<u32, AccessError> {
+        store.check_value(self.ty(store)?.ref_type.into(), init)?;
+        store.tables[self.address as usize]
+            .grow(delta, init)
+            .ok_or(AccessError::GrowFailed(delta))
+    }
+}
+
+/// A memory in a [`Store`], e.g. one exported by an instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Memory {
+    pub(super) store: StoreId,
+    pub(super) address: u32,
+}
+
+impl Memory {
+    fn instance<'a>(&self, store: &'a Store) -> Result<&'a MemoryInstance, AccessError> {
+        store.check_id(self.store)?;
+        Ok(&store.memories[self.address as usize])
+    }
+
+    /// The type with the current size as its minimum.
+    pub fn ty<'a>(&self, store: &'a Store) -> Result<&'a MemoryType, AccessError> {
+        Ok(self.instance(store)?.ty())
+    }
+
+    /// Size in pages.
+    pub fn size(&self, store: &Store) -> Result<u32, AccessError> {
+        Ok(self.instance(store)?.size())
+    }
+
+    pub fn data<'a>(&self, store: &'a Store) -> Result<&'a [u8], AccessError> {
+        Ok(self.instance(store)?.data())
+    }
+
+    pub fn data_mut<'a>(&self, store: &'a mut Store) -> Result<&'a mut [u8], AccessError> {
+        store.check_id(self.store)?;
+        Ok(store.memories[self.address as usize].data_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::types::{NumberType, ReferenceType},
+        exec::{Extern, error::InvokeError, instantiate},
+    };
+
+    #[test]
+    fn test_globals_and_tables() {
+        let mut store = Store::new();
+        let instance = instantiate(
+            &mut store,
+            r#"
+(module
+  (global (export "counter") (mut i32) (i32.const 1))
+  (global (export "constant") i64 (i64.const 2))
+  (table (export "table") 1 2 funcref)
+  (func $f (export "f") (result i32) (global.get 0))
+  (func (export "call") (param i32) (result i32) (call_indirect (result i32) (local.get 0))))
+"#,
+            &[],
+        )
+        .unwrap();
+        let counter = instance.get_global(&store, "counter").unwrap();
+        let constant = instance.get_global(&store, "constant").unwrap();
+        assert_eq!(counter.get(&store), Ok(Value::I32(1)));
+        assert_eq!(counter.set(&mut store, Value::I32(5)), Ok(()));
+        assert_eq!(
+            counter.set(&mut store, Value::I64(5)),
+            Err(AccessError::TypeMismatch {
+                expected: NumberType::I32.into(),
+                actual: NumberType::I64.into(),
+            })
+        );
+        assert_eq!(
+            constant.set(&mut store, Value::I64(3)),
+            Err(AccessError::ImmutableGlobal)
+        );
+        assert_eq!(constant.get(&store), Ok(Value::I64(2)));
+        assert!(instance.get_global(&store, "table").is_err());
+
+        let table = instance.get_table(&store, "table").unwrap();
+        let f = match instance.export(&store, "f") {
+            Ok(Extern::Function(a)) => a,
+            e => unreachable!("unexpected export {:?}", e),
+        };
+        assert_eq!(table.size(&store), Ok(1));
+        assert_eq!(table.get(&store, 0), Ok(Value::FuncRef(None)));
+        assert_eq!(table.set(&mut store, 0, Value::FuncRef(Some(f))), Ok(()));
+        assert_eq!(
+            table.set(&mut store, 0, Value::FuncRef(Some(u32::MAX))),
+            Err(AccessError::UnknownFunction(u32::MAX))
+        );
+        assert_eq!(
+            table.set(&mut store, 0, Value::ExternRef(None)),
+            Err(AccessError::TypeMismatch {
+                expected: ReferenceType::FuncRef.into(),
+                actual: ReferenceType::ExternRef.into(),
+            })
+        );
+        assert_eq!(
+            table.set(&mut store, 1, Value::FuncRef(None)),
+            Err(AccessError::OutOfBounds(1))
+        );
+        assert_eq!(table.grow(&mut store, 1, Value::FuncRef(Some(f))), Ok(1));
+        assert_eq!(
+            table.grow(&mut store, 1, Value::FuncRef(None)),
+            Err(AccessError::GrowFailed(1))
+        );
+        assert_eq!(table.get(&store, 1), Ok(Value::FuncRef(Some(f))));
+        assert_eq!(table.get(&store, 2), Err(AccessError::OutOfBounds(2)));
+        assert_eq!(
+            store.invoke_export(instance, "call", &[Value::I32(1)]),
+            Ok(vec![Value::I32(5)])
+        );
+    }
+
+    #[test]
+    fn test_handles_from_another_store() {
+        let wat = r#"
+(module
+  (global (export "global") (mut i32) (i32.const 1))
+  (table (export "table") 1 funcref)
+  (memory (export "memory") 1)
+  (func (export "f") (result i32) (i32.const 1)))
+"#;
+        let mut store = Store::new();
+        let mut other = Store::new();
+        let instance = instantiate(&mut store, wat, &[]).unwrap();
+        // the same module, so that the addresses exist in both stores
+        instantiate(&mut other, wat, &[]).unwrap();
+        let global = instance.get_global(&store, "global").unwrap();
+        let table = instance.get_table(&store, "table").unwrap();
+        let memory = instance.get_memory(&store, "memory").unwrap();
+        let f = instance.get_func(&store, "f").unwrap();
+        let typed = f.typed::<(), i32>(&store).unwrap();
+
+        let wrong = AccessError::WrongStore;
+        assert_eq!(global.get(&other), Err(wrong.clone()));
+        assert_eq!(global.set(&mut other, Value::I32(2)), Err(wrong.clone()));
+        assert_eq!(table.get(&other, 0), Err(wrong.clone()));
+        assert_eq!(
+            table.grow(&mut other, 1, Value::FuncRef(None)),
+            Err(wrong.clone())
+        );
+        assert_eq!(memory.size(&other), Err(wrong.clone()));
+        assert_eq!(memory.data_mut(&mut other), Err(wrong.clone()));
+        assert_eq!(f.ty(&other), Err(wrong.clone()));
+        assert_eq!(instance.export(&other, "f"), Err(wrong.clone()));
+        let wrong = InvokeError::Access(wrong);
+        assert_eq!(f.call(&mut other, &[]), Err(wrong.clone()));
+        assert_eq!(typed.call(&mut other, ()), Err(wrong.clone()));
+        assert_eq!(other.invoke_export(instance, "f", &[]), Err(wrong));
+        assert_eq!(
+            store.invoke(u32::MAX, &[]),
+            Err(InvokeError::Access(AccessError::UnknownFunction(u32::MAX)))
+        );
+        assert_eq!(typed.call(&mut store, ()), Ok(1));
+    }
+}
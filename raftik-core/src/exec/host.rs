@@ -0,0 +1,282 @@
+use std::rc::Rc;
+
+use super::{
+    error::Trap,
+    instance::{Extern, Instance, MemoryInstance},
+    store::Store,
+    value::{Value, WasmType, WasmTypeList},
+};
+use crate::ast::types::{FunctionType, ValueType};
+
+/// A function implemented in Rust. It receives arguments of the parameter types of its
+/// [`FunctionType`] and returns values of the result types or a trap.
+pub type HostFunction = Rc<dyn Fn(&mut Caller, &[Value]) -> Result<Vec<Value>, Trap>>;
+
+/// What a host function can reach while it runs: the store and the instance which called it.
+pub struct Caller<'a> {
+    store: &'a mut Store,
+    instance: Option<Instance>,
+}
+
+impl<'a> Caller<'a> {
+    pub(super) fn new(store: &'a mut Store, instance: Option<Instance>) -> Self {
+        Self { store, instance }
+    }
+
+    pub fn store(&mut self) -> &mut Store {
+        self.store
+    }
+
+    /// The calling instance, or `None` if the host function was invoked directly.
+    pub fn instance(&self) -> Option<Instance> {
+        self.instance
+    }
+
+    /// Memory 0 of the calling instance.
+    pub fn memory(&mut self) -> Option<&mut MemoryInstance> {
+        let instance = &self.store.instances[self.instance?.address as usize];
+        let address = *instance.memories.first()?;
+        Some(&mut self.store.memories[address as usize])
+    }
+
+    /// An export of the calling instance.
+    pub fn export(&self, name: &str) -> Option<Extern> {
+        self.instance?.export(self.store, name).ok()
+    }
+}
+
+/// What a typed host function returns: its results, or its results or a trap.
+pub trait HostResults {
+    fn types() -> Vec<ValueType>;
+    fn into_results(self) -> Result<Vec<Value>, Trap>;
+}
+
+impl<T: WasmTypeList> HostResults for T {
+    fn types() -> Vec<ValueType> {
+        T::types()
+    }
+
+    fn into_results(self) -> Result<Vec<Value>, Trap> {
+        Ok(self.into_values())
+    }
+}
+
+impl<T: WasmTypeList> HostResults for Result<T, Trap> {
+    fn types() -> Vec<ValueType> {
+        T::types()
+    }
+
+    fn into_results(self) -> Result<Vec<Value>, Trap> {
+        self.map(T::into_values)
+    }
+}
+
+/// Closures which can become host functions, with their [`FunctionType`] derived from their
+/// signature. `Params` tells apart closures with and without a leading [`Caller`] argument.
+pub trait IntoFunc<Params, Results> {
+    fn into_func(self) -> (FunctionType, HostFunction);
+}
+
+macro_rules! into_func {
+    ($(($($t:ident)*))+) => {
+        $(
+            #[allow(non_snake_case)]
+            impl<Func, Res, $($t),*> IntoFunc<($($t,)*), Res> for Func
+            where
+                Func: Fn($($t),*) -> Res + 'static,
+                Res: HostResults,
+                $($t: WasmType,)*
+            {
+                fn into_func(self) -> (FunctionType, HostFunction) {
+                    let ty = FunctionType {
+                        params: <($($t,)*) as WasmTypeList>::types(),
+                        results: Res::types(),
+                    };
+                    let f = move |_: &mut Caller, args: &[Value]| {
+                        let ($($t,)*) = <($($t,)*) as WasmTypeList>::from_values(args)
+                            .expect("arguments should match the parameter types");
+                        self($($t),*).into_results()
+                    };
+                    (ty, Rc::new(f))
+                }
+            }
+
+            #[allow(non_snake_case)]
+            impl<Func, Res, $($t),*> IntoFunc<(Caller<'static>, $($t,)*), Res> for Func
+            where
+                Func: Fn(&mut Caller, $($t),*) -> Res + 'static,
+                Res: HostResults,
+                $($t: WasmType,)*
+            {
+                fn into_func(self) -> (FunctionType, HostFunction) {
+                    let ty = FunctionType {
+                        params: <($($t,)*) as WasmTypeList>::types(),
+                        results: Res::types(),
+                    };
+                    let f = move |caller: &mut Caller, args: &[Value]| {
+                        let ($($t,)*) = <($($t,)*) as WasmTypeList>::from_values(args)
+                            .expect("arguments should match the parameter types");
+                        self(caller, $($t),*).into_results()
+                    };
+                    (ty, Rc::new(f))
+                }
+            }
+        )+
+    };
+}
+
+into_func! {
+    ()
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+    (A B C D E F G)
+    (A B C D E F G H)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::{
+        ast::types::NumberType,
+        exec::{
+            error::{InstantiationError, InvokeError},
+            instantiate,
+        },
+    };
+
+    #[test]
+    fn test_typed_host_functions() {
+        let mut store = Store::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let log_in_host = log.clone();
+        let imports = [
+            store.wrap(|a: i32, b: i64| a as f32 * b as f32),
+            store.wrap(move |caller: &mut Caller, ptr: i32, len: i32| {
+                let memory = caller.memory().ok_or(Trap::Host("no memory".to_string()))?;
+                let bytes = memory.read(ptr as u64, len as usize)?;
+                log_in_host
+                    .borrow_mut()
+                    .push(String::from_utf8_lossy(bytes).into_owned());
+                Ok(())
+            }),
+            store.wrap(|divisor: i32| {
+                if divisor == 0 {
+                    return Err(Trap::Host("division by zero".to_string()));
+                }
+                Ok((100 / divisor, 100 % divisor))
+            }),
+        ];
+        let instance = instantiate(
+            &mut store,
+            r#"
+(module
+  (import "env" "mul" (func $mul (param i32 i64) (result f32)))
+  (import "env" "log" (func $log (param i32 i32)))
+  (import "env" "divmod" (func $divmod (param i32) (result i32 i32)))
+  (memory 1)
+  (data (i32.const 0) "hello")
+  (func (export "mul") (result f32) (call $mul (i32.const 3) (i64.const 7)))
+  (func (export "log") (call $log (i32.const 0) (i32.const 5)))
+  (func (export "divmod") (param i32) (result i32 i32) (call $divmod (local.get 0))))
+"#,
+            &imports,
+        )
+        .unwrap();
+        let mut invoke = |name, args: &[Value]| store.invoke_export(instance, name, args);
+        assert_eq!(invoke("mul", &[]), Ok(vec![Value::F32(21.0)]));
+        assert_eq!(invoke("log", &[]), Ok(vec![]));
+        assert_eq!(*log.borrow(), vec!["hello".to_string()]);
+        assert_eq!(
+            invoke("divmod", &[Value::I32(7)]),
+            Ok(vec![Value::I32(14), Value::I32(2)])
+        );
+        assert_eq!(
+            invoke("divmod", &[Value::I32(0)]),
+            Err(InvokeError::Trap(Trap::Host(
+                "division by zero".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_dynamic_host_functions() {
+        let mut store = Store::new();
+        let i32_type = ValueType::Number(NumberType::I32);
+        let ty = FunctionType {
+            params: vec![i32_type],
+            results: vec![i32_type],
+        };
+        // calls back into the instance which called it
+        let twice = store.define_function(ty.clone(), |caller, args| {
+            let Some(Extern::Function(inc)) = caller.export("inc") else {
+                return Err(Trap::Host("inc is not exported".to_string()));
+            };
+            let once = caller.store().invoke(inc, args).unwrap();
+            Ok(caller.store().invoke(inc, &once).unwrap())
+        });
+        let wrong = store.define_function(ty, |_, _| Ok(vec![Value::I64(0)]));
+        let wat = r#"
+(module
+  (import "env" "f" (func $f (param i32) (result i32)))
+  (func (export "inc") (param i32) (result i32) (i32.add (local.get 0) (i32.const 1)))
+  (func (export "run") (param i32) (result i32) (call $f (local.get 0))))
+"#;
+        let instance = instantiate(&mut store, wat, &[twice]).unwrap();
+        assert_eq!(
+            store.invoke_export(instance, "run", &[Value::I32(5)]),
+            Ok(vec![Value::I32(7)])
+        );
+        let instance = instantiate(&mut store, wat, &[wrong]).unwrap();
+        assert_eq!(
+            store.invoke_export(instance, "run", &[Value::I32(5)]),
+            Err(InvokeError::Trap(Trap::HostResultTypeMismatch {
+                expected: vec![i32_type],
+                actual: vec![ValueType::Number(NumberType::I64)],
+            }))
+        );
+
+        let unit = store.wrap(|| ());
+        assert!(matches!(
+            instantiate(&mut store, wat, &[unit]),
+            Err(InstantiationError::IncompatibleImport { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reentrant_host_functions() {
+        let mut store = Store::new();
+        // calls the export `g`, which calls back into this function
+        let f = store.wrap(|caller: &mut Caller| -> Result<(), Trap> {
+            let Some(Extern::Function(g)) = caller.export("g") else {
+                return Err(Trap::Host("no export g".to_string()));
+            };
+            match caller.store().invoke(g, &[]) {
+                Ok(_) => Ok(()),
+                Err(InvokeError::Trap(trap)) => Err(trap),
+                Err(e) => Err(Trap::Host(e.to_string())),
+            }
+        });
+        let instance = instantiate(
+            &mut store,
+            r#"
+(module
+  (import "env" "f" (func $f))
+  (func (export "g") (call $f)))
+"#,
+            &[f],
+        )
+        .unwrap();
+        assert_eq!(
+            store.invoke_export(instance, "g", &[]),
+            Err(InvokeError::Trap(Trap::CallStackExhausted))
+        );
+        // the depth is restored after the trap
+        assert_eq!(store.call_depth, 0);
+    }
+}
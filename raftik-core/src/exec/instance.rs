@@ -0,0 +1,308 @@
+use std::rc::Rc;
+
+use super::{
+    code::Code,
+    error::{AccessError, AllocationError, Trap},
+    func::Func,
+    handle::{Global, Memory, Table},
+    host::HostFunction,
+    store::{Store, StoreId},
+    value::Value,
+};
+use crate::ast::types::{FunctionType, GlobalType, MemoryType, TableType};
+
+pub const PAGE_SIZE: usize = 65536;
+
+/// Memories are limited to 4GiB, i.e. 65536 pages.
+const MAX_PAGES: u32 = 65536;
+
+/// Implementation limit on the number of table elements.
+pub const MAX_TABLE_SIZE: u32 = 10_000_000;
+
+#[derive(Debug)]
+pub struct MemoryInstance {
+    pub(super) ty: MemoryType,
+    data: Vec<u8>,
+}
+
+impl MemoryInstance {
+    /// Fails if the minimum size exceeds 4GiB or cannot be allocated.
+    pub fn new(ty: MemoryType) -> Result<Self, AllocationError> {
+        let pages = ty.limits.min;
+        let len = (pages <= MAX_PAGES)
+            .then_some(pages as usize * PAGE_SIZE)
+            .ok_or(AllocationError::Memory(pages))?;
+        let mut data = Vec::new();
+        data.try_reserve_exact(len)
+            .map_err(|_| AllocationError::Memory(pages))?;
+        data.resize(len, 0);
+        Ok(Self { ty, data })
+    }
+
+    /// The type with the current size as its minimum.
+    pub fn ty(&self) -> &MemoryType {
+        &self.ty
+    }
+
+    /// Size in pages.
+    pub fn size(&self) -> u32 {
+        (self.data.len() / PAGE_SIZE) as u32
+    }
+
+    /// Grows by `delta` pages, returning the old size, or `None` if the maximum would be
+    /// exceeded or the memory cannot be allocated.
+    pub fn grow(&mut self, delta: u32) -> Option<u32> {
+        let old = self.size();
+        let max = self.ty.limits.max.unwrap_or(MAX_PAGES).min(MAX_PAGES);
+        let new = old.checked_add(delta).filter(|&new| new <= max)?;
+        self.data
+            .try_reserve_exact(delta as usize * PAGE_SIZE)
+            .ok()?;
+        self.data.resize(new as usize * PAGE_SIZE, 0);
+        self.ty.limits.min = new;
+        Some(old)
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    pub fn read(&self, address: u64, len: usize) -> Result<&[u8], Trap> {
+        let range =
+            checked_range(address, len as u64, self.data.len()).ok_or(Trap::MemoryOutOfBounds)?;
+        Ok(&self.data[range])
+    }
+
+    pub fn write(&mut self, address: u64, bytes: &[u8]) -> Result<(), Trap> {
+        let range = checked_range(address, bytes.len() as u64, self.data.len())
+            .ok_or(Trap::MemoryOutOfBounds)?;
+        self.data[range].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// `start..start + len` if it lies within `0..size`.
+pub(super) fn checked_range(start: u64, len: u64, size: usize) -> Option<std::ops::Range<usize>> {
+    let end = start.checked_add(len)?;
+    (end <= size as u64).then_some(start as usize..end as usize)
+}
+
+#[derive(Debug)]
+pub struct TableInstance {
+    pub(super) ty: TableType,
+    pub(super) elements: Vec<Value>,
+}
+
+impl TableInstance {
+    /// Fails if the minimum size exceeds [`MAX_TABLE_SIZE`] or cannot be allocated.
+    pub fn new(ty: TableType) -> Result<Self, AllocationError> {
+        let size = ty.limits.min;
+        if size > MAX_TABLE_SIZE {
+            return Err(AllocationError::Table(size));
+        }
+        let mut elements = Vec::new();
+        elements
+            .try_reserve_exact(size as usize)
+            .map_err(|_| AllocationError::Table(size))?;
+        elements.resize(size as usize, Value::null(ty.ref_type));
+        Ok(Self { ty, elements })
+    }
+
+    pub fn size(&self) -> u32 {
+        self.elements.len() as u32
+    }
+
+    /// Grows by `delta` elements set to `init`, returning the old size, or `None` if the
+    /// maximum or [`MAX_TABLE_SIZE`] would be exceeded or the elements cannot be allocated.
+    pub fn grow(&mut self, delta: u32, init: Value) -> Option<u32> {
+        let old = self.size();
+        let max = self
+            .ty
+            .limits
+            .max
+            .unwrap_or(MAX_TABLE_SIZE)
+            .min(MAX_TABLE_SIZE);
+        let new = old.checked_add(delta).filter(|&new| new <= max)?;
+        self.elements.try_reserve_exact(delta as usize).ok()?;
+        self.elements.resize(new as usize, init);
+        self.ty.limits.min = new;
+        Some(old)
+    }
+}
+
+#[derive(Debug)]
+pub struct GlobalInstance {
+    pub(super) ty: GlobalType,
+    pub(super) value: Value,
+}
+
+#[derive(Debug)]
+pub struct FunctionInstance {
+    pub ty: FunctionType,
+    pub(super) kind: FunctionKind,
+}
+
+pub(super) enum FunctionKind {
+    Wasm {
+        /// Address of the module instance whose index spaces the code refers to.
+        module: u32,
+        code: Rc<Code>,
+    },
+    Host(HostFunction),
+}
+
+impl std::fmt::Debug for FunctionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FunctionKind::Wasm { module, .. } => write!(f, "Wasm {{ module: {} }}", module),
+            FunctionKind::Host(_) => write!(f, "Host"),
+        }
+    }
+}
+
+/// Addresses in a [`Store`] of an external value which can be imported or exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extern {
+    Function(u32),
+    Table(u32),
+    Memory(u32),
+    Global(u32),
+}
+
+impl Extern {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Extern::Function(_) => "function",
+            Extern::Table(_) => "table",
+            Extern::Memory(_) => "memory",
+            Extern::Global(_) => "global",
+        }
+    }
+
+    pub fn address(&self) -> u32 {
+        match *self {
+            Extern::Function(a) | Extern::Table(a) | Extern::Memory(a) | Extern::Global(a) => a,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportInstance {
+    pub name: String,
+    pub value: Extern,
+}
+
+/// The index spaces of an instantiated module, mapping indices in its code to addresses
+/// in the store. Imports come first in each index space.
+#[derive(Debug, Default)]
+pub struct ModuleInstance {
+    pub types: Vec<FunctionType>,
+    pub functions: Vec<u32>,
+    pub tables: Vec<u32>,
+    pub memories: Vec<u32>,
+    pub globals: Vec<u32>,
+    pub elements: Vec<u32>,
+    pub datas: Vec<u32>,
+    pub exports: Vec<ExportInstance>,
+}
+
+/// A handle to a module instance in a [`Store`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instance {
+    pub(super) store: StoreId,
+    pub(super) address: u32,
+}
+
+impl Instance {
+    pub fn exports<'a>(&self, store: &'a Store) -> Result<&'a [ExportInstance], AccessError> {
+        store.check_id(self.store)?;
+        Ok(&store.instances[self.address as usize].exports)
+    }
+
+    pub fn export(&self, store: &Store, name: &str) -> Result<Extern, AccessError> {
+        self.exports(store)?
+            .iter()
+            .find(|e| e.name == name)
+            .map(|e| e.value)
+            .ok_or_else(|| AccessError::UnknownExport(name.to_string()))
+    }
+
+    /// The address of the export `name`, which must be of the given kind.
+    fn export_address(
+        &self,
+        store: &Store,
+        name: &str,
+        kind: &'static str,
+    ) -> Result<u32, AccessError> {
+        let value = self.export(store, name)?;
+        if value.kind() != kind {
+            return Err(AccessError::ExportKind {
+                name: name.to_string(),
+                expected: kind,
+                actual: value.kind(),
+            });
+        }
+        Ok(value.address())
+    }
+
+    pub fn get_func(&self, store: &Store, name: &str) -> Result<Func, AccessError> {
+        Ok(Func {
+            store: self.store,
+            address: self.export_address(store, name, "function")?,
+        })
+    }
+
+    pub fn get_memory(&self, store: &Store, name: &str) -> Result<Memory, AccessError> {
+        Ok(Memory {
+            store: self.store,
+            address: self.export_address(store, name, "memory")?,
+        })
+    }
+
+    pub fn get_table(&self, store: &Store, name: &str) -> Result<Table, AccessError> {
+        Ok(Table {
+            store: self.store,
+            address: self.export_address(store, name, "table")?,
+        })
+    }
+
+    pub fn get_global(&self, store: &Store, name: &str) -> Result<Global, AccessError> {
+        Ok(Global {
+            store: self.store,
+            address: self.export_address(store, name, "global")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::types::{Limits, ReferenceType};
+
+    #[test]
+    fn test_grow_limits() {
+        let mut memory = MemoryInstance::new(MemoryType {
+            limits: Limits { min: 1, max: None },
+        })
+        .unwrap();
+        assert_eq!(memory.grow(MAX_PAGES), None);
+        assert_eq!(memory.grow(u32::MAX), None);
+        assert_eq!(memory.grow(1), Some(1));
+        assert_eq!(memory.size(), 2);
+
+        let mut table = TableInstance::new(TableType {
+            ref_type: ReferenceType::FuncRef,
+            limits: Limits { min: 1, max: None },
+        })
+        .unwrap();
+        let null = Value::FuncRef(None);
+        assert_eq!(table.grow(u32::MAX - 1, null), None);
+        assert_eq!(table.grow(MAX_TABLE_SIZE, null), None);
+        assert_eq!(table.grow(2, null), Some(1));
+        assert_eq!(table.size(), 3);
+    }
+}
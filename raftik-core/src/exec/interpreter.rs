@@ -0,0 +1,766 @@
+use std::rc::Rc;
+
+use super::{
+    code::Code,
+    error::Trap,
+    host::{Caller, HostFunction},
+    instance::{
+        FunctionKind, Instance, MemoryInstance, ModuleInstance, TableInstance, checked_range,
+    },
+    numeric,
+    store::Store,
+    value::{Value, WasmType},
+};
+use crate::ast::instructions::{BlockType, MemArg, MemoryOp, Opcode, RawExpression};
+
+/// Calls deeper than this trap instead of exhausting memory.
+const MAX_CALL_DEPTH: usize = 10_000;
+
+/// How many calls a machine started by a host function counts as, since nested machines
+/// take up the native stack rather than frames on the heap.
+const NESTED_MACHINE_DEPTH: usize = 100;
+
+#[derive(Debug, Default)]
+pub(super) struct ValueStack(Vec<Value>);
+
+impl ValueStack {
+    pub fn push(&mut self, value: impl Into<Value>) {
+        self.0.push(value.into());
+    }
+
+    pub fn pop(&mut self) -> Value {
+        self.0
+            .pop()
+            .expect("validated code should not underflow the stack")
+    }
+
+    pub fn pop_as<T: WasmType>(&mut self) -> T {
+        T::from_value(self.pop()).expect("validated code should pop the expected type")
+    }
+
+    /// Pops an `i32` used as an index or length, which is unsigned.
+    fn pop_u32(&mut self) -> u32 {
+        self.pop_as::<i32>() as u32
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Keeps the top `arity` values and drops the `arity` values below `height`.
+    fn unwind(&mut self, height: usize, arity: usize) {
+        let results = self.0.split_off(self.0.len() - arity);
+        self.0.truncate(height);
+        self.0.extend(results);
+    }
+}
+
+#[derive(Debug)]
+struct Label {
+    /// Number of values a branch to this label carries.
+    arity: usize,
+    /// Stack height below the block's parameters.
+    height: usize,
+    /// Where a branch continues: after the `end` of a block, or at the start of a loop.
+    continuation: usize,
+    is_loop: bool,
+}
+
+#[derive(Debug)]
+struct Frame {
+    /// Address of the module instance the code belongs to.
+    module: u32,
+    code: Rc<Code>,
+    pc: usize,
+    locals: Vec<Value>,
+    labels: Vec<Label>,
+    arity: usize,
+    height: usize,
+}
+
+struct Machine<'a> {
+    store: &'a mut Store,
+    stack: ValueStack,
+    frames: Vec<Frame>,
+}
+
+/// Calls a function whose arguments have been type-checked. Host functions may call back
+/// into the store, so the depth of calls is kept in the store across nested machines.
+pub(super) fn call(store: &mut Store, address: u32, args: Vec<Value>) -> Result<Vec<Value>, Trap> {
+    let depth = store.call_depth;
+    if depth > 0 {
+        store.call_depth += NESTED_MACHINE_DEPTH;
+    }
+    let mut machine = Machine {
+        store,
+        stack: ValueStack(args),
+        frames: Vec::new(),
+    };
+    let result = machine.call(address).and_then(|_| machine.run());
+    // frames left by a trap are abandoned
+    machine.store.call_depth = depth;
+    result.map(|_| machine.stack.0)
+}
+
+/// Evaluates a constant expression, such as a global initializer or a segment offset,
+/// in the module instance at `module`.
+pub(super) fn evaluate(
+    store: &mut Store,
+    module: u32,
+    expression: &RawExpression,
+) -> Result<Value, Trap> {
+    let depth = store.call_depth;
+    store.call_depth += 1;
+    let mut machine = Machine {
+        store,
+        stack: ValueStack::default(),
+        frames: vec![Frame {
+            module,
+            code: Rc::new(Code::new(&[], expression)),
+            pc: 0,
+            locals: Vec::new(),
+            labels: Vec::new(),
+            arity: 1,
+            height: 0,
+        }],
+    };
+    let result = machine.run();
+    machine.store.call_depth = depth;
+    result.map(|_| machine.stack.pop())
+}
+
+impl Machine<'_> {
+    fn run(&mut self) -> Result<(), Trap> {
+        while let Some(frame) = self.frames.last_mut() {
+            let code = frame.code.clone();
+            let pc = frame.pc;
+            match code.instructions.get(pc) {
+                Some(opcode) => {
+                    frame.pc += 1;
+                    self.execute(&code, pc, opcode)?;
+                }
+                // falling off the end of the body returns
+                None => self.return_from_function(),
+            }
+        }
+        Ok(())
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("there should be a frame")
+    }
+
+    fn module(&self) -> &ModuleInstance {
+        let frame = self.frames.last().expect("there should be a frame");
+        &self.store.instances[frame.module as usize]
+    }
+
+    fn call(&mut self, address: u32) -> Result<(), Trap> {
+        if self.store.call_depth >= MAX_CALL_DEPTH {
+            return Err(Trap::CallStackExhausted);
+        }
+        let function = &self.store.functions[address as usize];
+        let height = self.stack.len() - function.ty.params.len();
+        let (module, code) = match &function.kind {
+            FunctionKind::Wasm { module, code } => (*module, code.clone()),
+            FunctionKind::Host(f) => return self.call_host(f.clone(), address, height),
+        };
+        let mut locals = self.stack.0.split_off(height);
+        locals.extend(code.locals.iter().map(|&t| Value::default_of(t)));
+        self.frames.push(Frame {
+            module,
+            code,
+            pc: 0,
+            locals,
+            labels: Vec::new(),
+            arity: function.ty.results.len(),
+            height,
+        });
+        self.store.call_depth += 1;
+        Ok(())
+    }
+
+    /// Calls a host function with the arguments above `height`, checking its results.
+    fn call_host(&mut self, f: HostFunction, address: u32, height: usize) -> Result<(), Trap> {
+        let args = self.stack.0.split_off(height);
+        let instance = self.frames.last().map(|frame| Instance {
+            store: self.store.id,
+            address: frame.module,
+        });
+        self.store.call_depth += 1;
+        let results = f(&mut Caller::new(self.store, instance), &args);
+        self.store.call_depth -= 1;
+        let results = results?;
+        let expected = &self.store.functions[address as usize].ty.results;
+        let actual: Vec<_> = results.iter().map(Value::value_type).collect();
+        if actual != *expected {
+            return Err(Trap::HostResultTypeMismatch {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+        self.stack.0.extend(results);
+        Ok(())
+    }
+
+    fn return_from_function(&mut self) {
+        let frame = self.frames.pop().expect("there should be a frame");
+        self.store.call_depth -= 1;
+        self.stack.unwind(frame.height, frame.arity);
+    }
+
+    /// Numbers of parameters and results of a block.
+    fn block_arity(&self, block_type: BlockType) -> (usize, usize) {
+        match block_type {
+            BlockType::Empty => (0, 0),
+            BlockType::Value(_) => (0, 1),
+            BlockType::TypeIndex(i) => {
+                let ty = &self.module().types[i as usize];
+                (ty.params.len(), ty.results.len())
+            }
+        }
+    }
+
+    fn enter_block(&mut self, block_type: BlockType, continuation: usize, is_loop: bool) {
+        let (params, results) = self.block_arity(block_type);
+        let height = self.stack.len() - params;
+        self.frame().labels.push(Label {
+            arity: if is_loop { params } else { results },
+            height,
+            continuation,
+            is_loop,
+        });
+    }
+
+    fn branch(&mut self, depth: u32) {
+        let labels = &self.frame().labels;
+        let Some(index) = labels.len().checked_sub(depth as usize + 1) else {
+            // the outermost label is the function body itself
+            return self.return_from_function();
+        };
+        let label = &labels[index];
+        let (height, arity, continuation) = (label.height, label.arity, label.continuation);
+        let keep = if label.is_loop { index + 1 } else { index };
+        self.stack.unwind(height, arity);
+        let frame = self.frame();
+        frame.labels.truncate(keep);
+        frame.pc = continuation;
+    }
+
+    fn execute(&mut self, code: &Code, pc: usize, opcode: &Opcode) -> Result<(), Trap> {
+        match opcode {
+            Opcode::Unreachable => return Err(Trap::Unreachable),
+            Opcode::Nop => (),
+            Opcode::Block(t) => self.enter_block(*t, code.ends[&pc] + 1, false),
+            Opcode::Loop(t) => self.enter_block(*t, pc + 1, true),
+            Opcode::If(t) => {
+                let end = code.ends[&pc];
+                if self.stack.pop_as::<i32>() != 0 {
+                    self.enter_block(*t, end + 1, false);
+                } else if let Some(&else_pc) = code.elses.get(&pc) {
+                    self.enter_block(*t, end + 1, false);
+                    self.frame().pc = else_pc + 1;
+                } else {
+                    self.frame().pc = end + 1;
+                }
+            }
+            // the then branch has finished; its `end` pops the label
+            Opcode::Else => self.frame().pc = code.ends[&pc],
+            Opcode::End => {
+                self.frame().labels.pop();
+            }
+            Opcode::Br(depth) => self.branch(*depth),
+            Opcode::BrIf(depth) => {
+                if self.stack.pop_as::<i32>() != 0 {
+                    self.branch(*depth);
+                }
+            }
+            Opcode::BrTable(depths, default) => {
+                let i = self.stack.pop_u32() as usize;
+                self.branch(*depths.get(i).unwrap_or(default));
+            }
+            Opcode::Return => self.return_from_function(),
+            Opcode::Call(index) => self.call(self.module().functions[*index as usize])?,
+            Opcode::CallIndirect(type_index, table_index) => {
+                let i = self.stack.pop_u32();
+                let table =
+                    &self.store.tables[self.module().tables[*table_index as usize] as usize];
+                let address = match table.elements.get(i as usize) {
+                    None => return Err(Trap::UndefinedElement(i)),
+                    Some(Value::FuncRef(Some(address))) => *address,
+                    Some(_) => return Err(Trap::UninitializedElement(i)),
+                };
+                let expected = &self.module().types[*type_index as usize];
+                if self.store.functions[address as usize].ty != *expected {
+                    return Err(Trap::IndirectCallTypeMismatch);
+                }
+                self.call(address)?;
+            }
+            Opcode::Drop => {
+                self.stack.pop();
+            }
+            Opcode::Select | Opcode::SelectTyped(_) => {
+                let condition = self.stack.pop_as::<i32>();
+                let second = self.stack.pop();
+                let first = self.stack.pop();
+                self.stack.push(if condition != 0 { first } else { second });
+            }
+            Opcode::LocalGet(i) => {
+                let value = self.frame().locals[*i as usize];
+                self.stack.push(value);
+            }
+            Opcode::LocalSet(i) => {
+                let value = self.stack.pop();
+                self.frame().locals[*i as usize] = value;
+            }
+            Opcode::LocalTee(i) => {
+                let value = self.stack.pop();
+                self.stack.push(value);
+                self.frame().locals[*i as usize] = value;
+            }
+            Opcode::GlobalGet(i) => {
+                let global = self.module().globals[*i as usize];
+                self.stack.push(self.store.globals[global as usize].value);
+            }
+            Opcode::GlobalSet(i) => {
+                let global = self.module().globals[*i as usize];
+                self.store.globals[global as usize].value = self.stack.pop();
+            }
+            Opcode::I32Const(v) => self.stack.push(*v),
+            Opcode::I64Const(v) => self.stack.push(*v),
+            Opcode::F32Const(v) => self.stack.push(*v),
+            Opcode::F64Const(v) => self.stack.push(*v),
+            Opcode::RefNull(t) => self.stack.push(Value::null(*t)),
+            Opcode::RefIsNull => {
+                let is_null = self.stack.pop().is_null();
+                self.stack.push(is_null as i32);
+            }
+            Opcode::RefFunc(i) => {
+                let function = self.module().functions[*i as usize];
+                self.stack.push(Value::FuncRef(Some(function)));
+            }
+            Opcode::TableGet(t) => {
+                let i = self.stack.pop_u32() as usize;
+                let table = self.table(*t);
+                let value = *table.elements.get(i).ok_or(Trap::TableOutOfBounds)?;
+                self.stack.push(value);
+            }
+            Opcode::TableSet(t) => {
+                let value = self.stack.pop();
+                let i = self.stack.pop_u32() as usize;
+                let table = self.table(*t);
+                *table.elements.get_mut(i).ok_or(Trap::TableOutOfBounds)? = value;
+            }
+            Opcode::TableInit(e, t) => {
+                let (n, s, d) = self.pop_range_operands();
+                let module = self.module();
+                let (table, element) = (module.tables[*t as usize], module.elements[*e as usize]);
+                self.store.table_init(table, element, d, s, n)?;
+            }
+            Opcode::ElemDrop(e) => {
+                let element = self.module().elements[*e as usize];
+                self.store.elements[element as usize] = Vec::new();
+            }
+            Opcode::TableCopy(dt, st) => {
+                let (n, s, d) = self.pop_range_operands();
+                let module = self.module();
+                let (dt, st) = (module.tables[*dt as usize], module.tables[*st as usize]);
+                let tables = &mut self.store.tables;
+                let source = checked_range(s, n, tables[st as usize].elements.len())
+                    .ok_or(Trap::TableOutOfBounds)?;
+                let dest = checked_range(d, n, tables[dt as usize].elements.len())
+                    .ok_or(Trap::TableOutOfBounds)?;
+                let values = tables[st as usize].elements[source].to_vec();
+                tables[dt as usize].elements[dest].copy_from_slice(&values);
+            }
+            Opcode::TableGrow(t) => {
+                let delta = self.stack.pop_u32();
+                let init = self.stack.pop();
+                let old = self.table(*t).grow(delta, init);
+                self.stack.push(old.map_or(-1, |old| old as i32));
+            }
+            Opcode::TableSize(t) => {
+                let size = self.table(*t).size();
+                self.stack.push(size as i32);
+            }
+            Opcode::TableFill(t) => {
+                let n = self.stack.pop_u32();
+                let value = self.stack.pop();
+                let d = self.stack.pop_u32();
+                let table = self.table(*t);
+                let dest = checked_range(d as u64, n as u64, table.elements.len())
+                    .ok_or(Trap::TableOutOfBounds)?;
+                table.elements[dest].fill(value);
+            }
+            Opcode::Memory(op, mem_arg) => self.execute_memory(*op, mem_arg)?,
+            Opcode::MemorySize(m) => {
+                let size = self.memory(*m).size();
+                self.stack.push(size as i32);
+            }
+            Opcode::MemoryGrow(m) => {
+                let delta = self.stack.pop_u32();
+                let old = self.memory(*m).grow(delta);
+                self.stack.push(old.map_or(-1, |old| old as i32));
+            }
+            Opcode::MemoryInit(data, m) => {
+                let (n, s, d) = self.pop_range_operands();
+                let module = self.module();
+                let (memory, data) = (module.memories[*m as usize], module.datas[*data as usize]);
+                self.store.memory_init(memory, data, d, s, n)?;
+            }
+            Opcode::DataDrop(data) => {
+                let data = self.module().datas[*data as usize];
+                self.store.datas[data as usize] = Vec::new();
+            }
+            Opcode::MemoryCopy(dm, sm) => {
+                let (n, s, d) = self.pop_range_operands();
+                let bytes = self.memory(*sm).read(s, n as usize)?.to_vec();
+                self.memory(*dm).write(d, &bytes)?;
+            }
+            Opcode::MemoryFill(m) => {
+                let n = self.stack.pop_u32();
+                let value = self.stack.pop_as::<i32>() as u8;
+                let d = self.stack.pop_u32();
+                let memory = self.memory(*m);
+                let dest = checked_range(d as u64, n as u64, memory.data().len())
+                    .ok_or(Trap::MemoryOutOfBounds)?;
+                memory.data_mut()[dest].fill(value);
+            }
+            Opcode::Numeric(op) => numeric::execute(*op, &mut self.stack)?,
+            Opcode::V128Const(_)
+            | Opcode::I8x16Shuffle(_)
+            | Opcode::VectorLane(..)
+            | Opcode::VectorMemory(..)
+            | Opcode::VectorMemoryLane(..)
+            | Opcode::Vector(_) => return Err(Trap::Unsupported("vector instructions")),
+        }
+        Ok(())
+    }
+
+    fn table(&mut self, index: u32) -> &mut TableInstance {
+        let address = self.module().tables[index as usize];
+        &mut self.store.tables[address as usize]
+    }
+
+    fn memory(&mut self, index: u32) -> &mut MemoryInstance {
+        let address = self.module().memories[index as usize];
+        &mut self.store.memories[address as usize]
+    }
+
+    /// Pops the length, source and destination of a copy or initialization.
+    fn pop_range_operands(&mut self) -> (u64, u64, u64) {
+        let n = self.stack.pop_u32();
+        let s = self.stack.pop_u32();
+        let d = self.stack.pop_u32();
+        (n as u64, s as u64, d as u64)
+    }
+
+    fn execute_memory(&mut self, op: MemoryOp, mem_arg: &MemArg) -> Result<(), Trap> {
+        // stores have the value on top of the address
+        let value = op.results().is_empty().then(|| self.stack.pop());
+        let address = self.stack.pop_u32() as u64 + mem_arg.offset as u64;
+        let memory = self.memory(mem_arg.memory_index);
+        macro_rules! load {
+            ($t:ty as $result:ty) => {{
+                let bytes = memory.read(address, size_of::<$t>())?;
+                let value = <$t>::from_le_bytes(bytes.try_into().expect("size should match"));
+                self.stack.push(value as $result);
+            }};
+        }
+        macro_rules! store {
+            ($source:ty as $t:ty) => {{
+                let value = <$source>::from_value(value.expect("stores should pop a value"))
+                    .expect("validated code should store the expected type");
+                memory.write(address, &(value as $t).to_le_bytes())?;
+            }};
+        }
+        match op {
+            MemoryOp::I32Load => load!(i32 as i32),
+            MemoryOp::I64Load => load!(i64 as i64),
+            MemoryOp::F32Load => load!(f32 as f32),
+            MemoryOp::F64Load => load!(f64 as f64),
+            MemoryOp::I32Load8S => load!(i8 as i32),
+            MemoryOp::I32Load8U => load!(u8 as i32),
+            MemoryOp::I32Load16S => load!(i16 as i32),
+            MemoryOp::I32Load16U => load!(u16 as i32),
+            MemoryOp::I64Load8S => load!(i8 as i64),
+            MemoryOp::I64Load8U => load!(u8 as i64),
+            MemoryOp::I64Load16S => load!(i16 as i64),
+            MemoryOp::I64Load16U => load!(u16 as i64),
+            MemoryOp::I64Load32S => load!(i32 as i64),
+            MemoryOp::I64Load32U => load!(u32 as i64),
+            MemoryOp::I32Store => store!(i32 as i32),
+            MemoryOp::I64Store => store!(i64 as i64),
+            MemoryOp::F32Store => store!(f32 as f32),
+            MemoryOp::F64Store => store!(f64 as f64),
+            MemoryOp::I32Store8 => store!(i32 as u8),
+            MemoryOp::I32Store16 => store!(i32 as u16),
+            MemoryOp::I64Store8 => store!(i64 as u8),
+            MemoryOp::I64Store16 => store!(i64 as u16),
+            MemoryOp::I64Store32 => store!(i64 as u32),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::{Instance, error::InvokeError, instantiate};
+
+    /// A module instantiated in a store of its own.
+    struct Runner {
+        store: Store,
+        instance: Instance,
+    }
+
+    impl Runner {
+        fn new(wat: &str) -> Self {
+            let mut store = Store::new();
+            let instance = instantiate(&mut store, wat, &[]).unwrap();
+            Runner { store, instance }
+        }
+
+        fn invoke_export(&mut self, name: &str, args: &[Value]) -> Result<Vec<Value>, InvokeError> {
+            self.store.invoke_export(self.instance, name, args)
+        }
+    }
+
+    fn invoke(instance: &mut Runner, name: &str, args: &[Value]) -> Vec<Value> {
+        instance.invoke_export(name, args).unwrap()
+    }
+
+    fn trap(instance: &mut Runner, name: &str, args: &[Value]) -> Trap {
+        match instance.invoke_export(name, args) {
+            Err(InvokeError::Trap(trap)) => trap,
+            r => unreachable!("expected a trap: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let mut instance = Runner::new(
+            r#"
+(module
+  (func $fac (export "fac") (param i64) (result i64)
+    (if (result i64) (i64.eqz (local.get 0))
+      (then (i64.const 1))
+      (else (i64.mul (local.get 0) (call $fac (i64.sub (local.get 0) (i64.const 1)))))))
+  (func (export "sum") (param i32) (result i32) (local i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get 0)))
+        (local.set 1 (i32.add (local.get 1) (local.get 0)))
+        (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+        (br $next)))
+    (local.get 1))
+  (func (export "classify") (param i32) (result i32)
+    (block (block (block
+      (br_table 0 1 2 (local.get 0)))
+      (return (i32.const 10)))
+      (return (i32.const 20)))
+    (i32.const 30))
+  (func (export "swap") (param i32 i32) (result i32 i32)
+    (local.get 1) (local.get 0)
+    (block (param i32 i32) (result i32 i32)))
+  (func (export "early") (result i32)
+    (block (result i32) (i32.const 1) (i32.const 2) (br 0))
+    (drop)
+    (i32.const 3)
+    (return)
+    (unreachable))
+  (func (export "select") (param i32) (result i32)
+    (select (i32.const 1) (i32.const 2) (local.get 0))))
+"#,
+        );
+        assert_eq!(
+            invoke(&mut instance, "fac", &[Value::I64(20)]),
+            vec![Value::I64(2432902008176640000)]
+        );
+        assert_eq!(
+            invoke(&mut instance, "sum", &[Value::I32(100)]),
+            vec![Value::I32(5050)]
+        );
+        for (i, expected) in [(0, 10), (1, 20), (2, 30), (7, 30)] {
+            assert_eq!(
+                invoke(&mut instance, "classify", &[Value::I32(i)]),
+                vec![Value::I32(expected)]
+            );
+        }
+        assert_eq!(
+            invoke(&mut instance, "swap", &[Value::I32(1), Value::I32(2)]),
+            vec![Value::I32(2), Value::I32(1)]
+        );
+        assert_eq!(invoke(&mut instance, "early", &[]), vec![Value::I32(3)]);
+        assert_eq!(
+            invoke(&mut instance, "select", &[Value::I32(0)]),
+            vec![Value::I32(2)]
+        );
+    }
+
+    #[test]
+    fn test_memory_and_globals() {
+        let mut instance = Runner::new(
+            r#"
+(module
+  (memory 1 2)
+  (global $counter (mut i32) (i32.const 40))
+  (global $base i64 (i64.const -1))
+  (func (export "next") (result i32)
+    (global.set $counter (i32.add (global.get $counter) (i32.const 2)))
+    (global.get $counter))
+  (func (export "bytes") (result i64 i32 i32)
+    (i64.store offset=8 (i32.const 0) (global.get $base))
+    (i32.store16 (i32.const 16) (i32.const 0x12345))
+    (i64.load32_u offset=4 (i32.const 8))
+    (i32.load8_s (i32.const 9))
+    (i32.load16_u (i32.const 16)))
+  (func (export "grow") (param i32) (result i32 i32)
+    (memory.grow (local.get 0))
+    (memory.size))
+  (func (export "load") (param i32) (result i32)
+    (i32.load (local.get 0)))
+  (func (export "fill_copy") (result i32)
+    (memory.fill (i32.const 100) (i32.const 0xab) (i32.const 4))
+    (memory.copy (i32.const 102) (i32.const 100) (i32.const 4))
+    (i32.load (i32.const 102))))
+"#,
+        );
+        assert_eq!(invoke(&mut instance, "next", &[]), vec![Value::I32(42)]);
+        assert_eq!(invoke(&mut instance, "next", &[]), vec![Value::I32(44)]);
+        assert_eq!(
+            invoke(&mut instance, "bytes", &[]),
+            vec![Value::I64(0xffffffff), Value::I32(-1), Value::I32(0x2345)]
+        );
+        assert_eq!(
+            invoke(&mut instance, "fill_copy", &[]),
+            vec![Value::I32(0xabababab_u32 as i32)]
+        );
+        assert_eq!(
+            invoke(&mut instance, "load", &[Value::I32(65532)]),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            trap(&mut instance, "load", &[Value::I32(65533)]),
+            Trap::MemoryOutOfBounds
+        );
+        assert_eq!(
+            trap(&mut instance, "load", &[Value::I32(-1)]),
+            Trap::MemoryOutOfBounds
+        );
+        assert_eq!(
+            invoke(&mut instance, "grow", &[Value::I32(1)]),
+            vec![Value::I32(1), Value::I32(2)]
+        );
+        assert_eq!(
+            invoke(&mut instance, "grow", &[Value::I32(1)]),
+            vec![Value::I32(-1), Value::I32(2)]
+        );
+        assert_eq!(
+            invoke(&mut instance, "load", &[Value::I32(65533)]),
+            vec![Value::I32(0)]
+        );
+    }
+
+    #[test]
+    fn test_tables_and_indirect_calls() {
+        let mut instance = Runner::new(
+            r#"
+(module
+  (type $unary (func (param i32) (result i32)))
+  (table $t 3 funcref)
+  (func $double (param i32) (result i32) (i32.mul (local.get 0) (i32.const 2)))
+  (func $nullary (result i32) (i32.const 0))
+  (elem declare func $double $nullary)
+  (func (export "init")
+    (table.set $t (i32.const 0) (ref.func $double))
+    (table.set $t (i32.const 1) (ref.func $nullary)))
+  (func (export "call") (param i32 i32) (result i32)
+    (call_indirect $t (type $unary) (local.get 0) (local.get 1)))
+  (func (export "grow") (result i32 i32)
+    (table.grow $t (ref.null func) (i32.const 2))
+    (table.size $t)))
+"#,
+        );
+        invoke(&mut instance, "init", &[]);
+        let call = |instance: &mut Runner, arg, index| {
+            instance.invoke_export("call", &[Value::I32(arg), Value::I32(index)])
+        };
+        assert_eq!(call(&mut instance, 21, 0), Ok(vec![Value::I32(42)]));
+        assert_eq!(
+            call(&mut instance, 21, 1),
+            Err(Trap::IndirectCallTypeMismatch.into())
+        );
+        assert_eq!(
+            call(&mut instance, 21, 2),
+            Err(Trap::UninitializedElement(2).into())
+        );
+        assert_eq!(
+            call(&mut instance, 21, 3),
+            Err(Trap::UndefinedElement(3).into())
+        );
+        assert_eq!(
+            invoke(&mut instance, "grow", &[]),
+            vec![Value::I32(3), Value::I32(5)]
+        );
+    }
+
+    #[test]
+    fn test_traps() {
+        let mut instance = Runner::new(
+            r#"
+(module
+  (func (export "unreachable") unreachable)
+  (func (export "div_s") (param i32 i32) (result i32) (i32.div_s (local.get 0) (local.get 1)))
+  (func (export "rem_s") (param i32 i32) (result i32) (i32.rem_s (local.get 0) (local.get 1)))
+  (func (export "trunc") (param f64) (result i32) (i32.trunc_f64_u (local.get 0)))
+  (func $recurse (export "recurse") (call $recurse)))
+"#,
+        );
+        assert_eq!(trap(&mut instance, "unreachable", &[]), Trap::Unreachable);
+        let (min, zero, minus_one) = (Value::I32(i32::MIN), Value::I32(0), Value::I32(-1));
+        assert_eq!(
+            trap(&mut instance, "div_s", &[min, zero]),
+            Trap::IntegerDivideByZero
+        );
+        assert_eq!(
+            trap(&mut instance, "div_s", &[min, minus_one]),
+            Trap::IntegerOverflow
+        );
+        assert_eq!(
+            invoke(&mut instance, "rem_s", &[min, minus_one]),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            trap(&mut instance, "trunc", &[Value::F64(f64::NAN)]),
+            Trap::InvalidConversionToInteger
+        );
+        assert_eq!(
+            trap(&mut instance, "trunc", &[Value::F64(-1.0)]),
+            Trap::IntegerOverflow
+        );
+        assert_eq!(
+            trap(&mut instance, "trunc", &[Value::F64(4294967296.0)]),
+            Trap::IntegerOverflow
+        );
+        assert_eq!(
+            invoke(&mut instance, "trunc", &[Value::F64(4294967295.9)]),
+            vec![Value::I32(-1)]
+        );
+        assert_eq!(
+            trap(&mut instance, "recurse", &[]),
+            Trap::CallStackExhausted
+        );
+        assert_eq!(
+            instance.invoke_export("trunc", &[Value::I32(0)]),
+            Err(InvokeError::ArgumentTypeMismatch {
+                expected: vec![Value::F64(0.0).value_type()],
+                actual: vec![Value::I32(0).value_type()],
+            })
+        );
+        assert_eq!(
+            instance.invoke_export("missing", &[]),
+            Err(InvokeError::NoSuchFunction("missing".to_string()))
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use super::{
+    error::LinkError,
+    host::IntoFunc,
+    instance::{Extern, Instance},
+    store::Store,
+};
+use crate::ast::{ModuleParsed, Section, section::SectionID};
+
+/// Definitions keyed by module and name, from which imports of modules are resolved.
+#[derive(Debug, Default, Clone)]
+pub struct Linker {
+    definitions: HashMap<(String, String), Extern>,
+}
+
+impl Linker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines `value`, which is allocated in the store the linker is used with.
+    /// A value from another store is rejected when a module importing it is instantiated.
+    pub fn define(
+        &mut self,
+        module: &str,
+        name: &str,
+        value: Extern,
+    ) -> Result<&mut Self, LinkError> {
+        let key = (module.to_string(), name.to_string());
+        if self.definitions.contains_key(&key) {
+            return Err(LinkError::DuplicateDefinition {
+                module: module.to_string(),
+                name: name.to_string(),
+            });
+        }
+        self.definitions.insert(key, value);
+        Ok(self)
+    }
+
+    /// Allocates a host function with [`Store::wrap`] and defines it.
+    pub fn func_wrap<Params, Results>(
+        &mut self,
+        store: &mut Store,
+        module: &str,
+        name: &str,
+        f: impl IntoFunc<Params, Results>,
+    ) -> Result<&mut Self, LinkError> {
+        let function = store.wrap(f);
+        self.define(module, name, function)
+    }
+
+    /// Defines every export of `instance` under the module name `module`.
+    pub fn instance(
+        &mut self,
+        store: &Store,
+        module: &str,
+        instance: Instance,
+    ) -> Result<&mut Self, LinkError> {
+        for export in instance.exports(store)? {
+            self.define(module, &export.name, export.value)?;
+        }
+        Ok(self)
+    }
+
+    pub fn get(&self, module: &str, name: &str) -> Option<Extern> {
+        self.definitions
+            .get(&(module.to_string(), name.to_string()))
+            .copied()
+    }
+
+    /// Instantiates `module` with its imports resolved from the definitions.
+    /// Type mismatches are reported by [`Store::instantiate`].
+    pub fn instantiate(
+        &self,
+        store: &mut Store,
+        module: &ModuleParsed,
+    ) -> Result<Instance, LinkError> {
+        let mut imports = Vec::new();
+        if let Some(Section::Import(s)) = module.sec_by_id(SectionID::Import) {
+            for import in &s.imports {
+                let value = self.get(&import.module, &import.name).ok_or_else(|| {
+                    LinkError::MissingImport {
+                        module: import.module.clone(),
+                        name: import.name.clone(),
+                    }
+                })?;
+                imports.push(value);
+            }
+        }
+        Ok(store.instantiate(module, &imports)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::types::{
+            GlobalType, Limits, MemoryType, Mutability, NumberType, ReferenceType, TableType,
+        },
+        exec::{
+            Value,
+            error::{ImportMismatch, InstantiationError},
+        },
+    };
+
+    fn parse(wat: &str) -> Vec<u8> {
+        wat::parse_str(wat).unwrap()
+    }
+
+    fn limits(min: u32, max: Option<u32>) -> Limits {
+        Limits { min, max }
+    }
+
+    #[test]
+    fn test_link_instances_and_host_definitions() {
+        let mut store = Store::new();
+        let mut linker = Linker::new();
+        let memory = store
+            .define_memory(MemoryType {
+                limits: limits(1, Some(4)),
+            })
+            .unwrap();
+        let global = store.define_global(
+            GlobalType {
+                val_type: NumberType::I32.into(),
+                mutability: Mutability::Var,
+            },
+            Value::I32(10),
+        );
+        linker
+            .func_wrap(&mut store, "env", "square", |x: i32| x * x)
+            .unwrap()
+            .define("env", "memory", memory)
+            .unwrap()
+            .define("env", "counter", global)
+            .unwrap();
+
+        let wasm = parse(
+            r#"
+(module
+  (import "env" "square" (func $square (param i32) (result i32)))
+  (import "env" "counter" (global $counter (mut i32)))
+  (func (export "square_counter") (result i32) (call $square (global.get $counter))))
+"#,
+        );
+        let math = linker
+            .instantiate(&mut store, &ModuleParsed::from_slice(&wasm).unwrap())
+            .unwrap();
+        linker.instance(&store, "math", math).unwrap();
+
+        let wasm = parse(
+            r#"
+(module
+  (import "math" "square_counter" (func $square_counter (result i32)))
+  (import "env" "memory" (memory 1))
+  (func (export "run") (result i32)
+    (i32.store (i32.const 0) (call $square_counter))
+    (i32.load (i32.const 0))))
+"#,
+        );
+        let app = linker
+            .instantiate(&mut store, &ModuleParsed::from_slice(&wasm).unwrap())
+            .unwrap();
+        assert_eq!(
+            store.invoke_export(app, "run", &[]),
+            Ok(vec![Value::I32(100)])
+        );
+        let Extern::Memory(memory) = memory else {
+            unreachable!()
+        };
+        assert_eq!(store.memories[memory as usize].data()[..4], [100, 0, 0, 0]);
+        assert!(matches!(
+            linker.define("math", "square_counter", global),
+            Err(LinkError::DuplicateDefinition { .. })
+        ));
+    }
+
+    #[test]
+    fn test_link_errors() {
+        let mut store = Store::new();
+        let mut linker = Linker::new();
+        let i32_type = NumberType::I32.into();
+        let memory = store
+            .define_memory(MemoryType {
+                limits: limits(1, None),
+            })
+            .unwrap();
+        let table = store
+            .define_table(
+                TableType {
+                    ref_type: ReferenceType::ExternRef,
+                    limits: limits(1, Some(1)),
+                },
+                Value::ExternRef(None),
+            )
+            .unwrap();
+        let global = store.define_global(
+            GlobalType {
+                val_type: i32_type,
+                mutability: Mutability::Const,
+            },
+            Value::I32(0),
+        );
+        linker
+            .func_wrap(&mut store, "env", "f", |_: i64| ())
+            .unwrap()
+            .define("env", "memory", memory)
+            .unwrap()
+            .define("env", "table", table)
+            .unwrap()
+            .define("env", "global", global)
+            .unwrap()
+            // e.g. an address from another store
+            .define("other", "f", Extern::Function(u32::MAX))
+            .unwrap();
+
+        let link = |store: &mut Store, import: &str| {
+            let wasm = parse(&format!("(module (import {}))", import));
+            linker.instantiate(store, &ModuleParsed::from_slice(&wasm).unwrap())
+        };
+        let reason = |result: Result<Instance, LinkError>| match result {
+            Err(LinkError::Instantiation(InstantiationError::IncompatibleImport {
+                reason,
+                ..
+            })) => *reason,
+            r => unreachable!("unexpected result: {:?}", r),
+        };
+
+        assert!(matches!(
+            link(&mut store, r#""env" "g" (func)"#),
+            Err(LinkError::MissingImport { module, name }) if module == "env" && name == "g"
+        ));
+        assert!(matches!(
+            reason(link(&mut store, r#""env" "f" (func (param i32))"#)),
+            ImportMismatch::Function { .. }
+        ));
+        assert!(matches!(
+            reason(link(&mut store, r#""env" "memory" (memory 1 2)"#)),
+            ImportMismatch::Memory { .. }
+        ));
+        assert!(matches!(
+            reason(link(&mut store, r#""env" "memory" (memory 2)"#)),
+            ImportMismatch::Memory { .. }
+        ));
+        assert!(matches!(
+            reason(link(&mut store, r#""env" "table" (table 1 funcref)"#)),
+            ImportMismatch::Table { .. }
+        ));
+        assert!(matches!(
+            reason(link(&mut store, r#""env" "global" (global (mut i32))"#)),
+            ImportMismatch::Global { .. }
+        ));
+        assert_eq!(
+            reason(link(&mut store, r#""env" "global" (memory 1)"#)),
+            ImportMismatch::Kind {
+                expected: "memory",
+                actual: "global"
+            }
+        );
+        assert_eq!(
+            reason(link(&mut store, r#""other" "f" (func)"#)),
+            ImportMismatch::UnknownAddress {
+                kind: "function",
+                address: u32::MAX
+            }
+        );
+        for import in [
+            r#""env" "f" (func (param i64))"#,
+            r#""env" "memory" (memory 0)"#,
+            r#""env" "table" (table 0 2 externref)"#,
+            r#""env" "global" (global i32)"#,
+        ] {
+            assert!(link(&mut store, import).is_ok(), "{}", import);
+        }
+    }
+}
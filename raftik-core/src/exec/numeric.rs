@@ -0,0 +1,362 @@
+use super::{
+    error::Trap,
+    interpreter::ValueStack,
+    value::{Value, WasmType},
+};
+use crate::ast::instructions::NumericOp;
+
+fn unary<T: WasmType, R: Into<Value>>(stack: &mut ValueStack, f: impl FnOnce(T) -> R) {
+    let a = stack.pop_as::<T>();
+    stack.push(f(a));
+}
+
+fn binary<T: WasmType, R: Into<Value>>(stack: &mut ValueStack, f: impl FnOnce(T, T) -> R) {
+    let b = stack.pop_as::<T>();
+    let a = stack.pop_as::<T>();
+    stack.push(f(a, b));
+}
+
+fn try_unary<T: WasmType, R: Into<Value>>(
+    stack: &mut ValueStack,
+    f: impl FnOnce(T) -> Result<R, Trap>,
+) -> Result<(), Trap> {
+    let a = stack.pop_as::<T>();
+    stack.push(f(a)?);
+    Ok(())
+}
+
+fn try_binary<T: WasmType, R: Into<Value>>(
+    stack: &mut ValueStack,
+    f: impl FnOnce(T, T) -> Result<R, Trap>,
+) -> Result<(), Trap> {
+    let b = stack.pop_as::<T>();
+    let a = stack.pop_as::<T>();
+    stack.push(f(a, b)?);
+    Ok(())
+}
+
+/// Checks for division by zero, and for overflow when `a` is the minimum and `b` is -1.
+macro_rules! checked_div {
+    ($a:expr, $b:expr, $op:ident) => {
+        if $b == 0 {
+            Err(Trap::IntegerDivideByZero)
+        } else {
+            $a.$op($b).ok_or(Trap::IntegerOverflow)
+        }
+    };
+}
+
+/// `rem_s` of the minimum by -1 is 0 rather than an overflow.
+macro_rules! checked_rem {
+    ($a:expr, $b:expr) => {
+        if $b == 0 {
+            Err(Trap::IntegerDivideByZero)
+        } else {
+            Ok($a.wrapping_rem($b))
+        }
+    };
+}
+
+/// Truncates towards zero, trapping unless the result lies in `min..max`.
+fn truncate(value: f64, min: f64, max: f64) -> Result<f64, Trap> {
+    if value.is_nan() {
+        return Err(Trap::InvalidConversionToInteger);
+    }
+    let value = value.trunc();
+    if value < min || value >= max {
+        return Err(Trap::IntegerOverflow);
+    }
+    Ok(value)
+}
+
+const I32_MIN: f64 = -2147483648.0;
+const I32_END: f64 = 2147483648.0;
+const U32_END: f64 = 4294967296.0;
+const I64_MIN: f64 = -9223372036854775808.0;
+const I64_END: f64 = 9223372036854775808.0;
+const U64_END: f64 = 18446744073709551616.0;
+
+/// `min` and `max` of floats propagate NaN and order -0 below +0.
+macro_rules! float_min_max {
+    ($min:ident, $max:ident, $t:ty) => {
+        fn $min(a: $t, b: $t) -> $t {
+            if a.is_nan() || b.is_nan() {
+                a + b
+            } else if a == b {
+                if a.is_sign_negative() { a } else { b }
+            } else {
+                a.min(b)
+            }
+        }
+
+        fn $max(a: $t, b: $t) -> $t {
+            if a.is_nan() || b.is_nan() {
+                a + b
+            } else if a == b {
+                if a.is_sign_positive() { a } else { b }
+            } else {
+                a.max(b)
+            }
+        }
+    };
+}
+
+float_min_max!(f32_min, f32_max, f32);
+float_min_max!(f64_min, f64_max, f64);
+
+pub(super) fn execute(op: NumericOp, stack: &mut ValueStack) -> Result<(), Trap> {
+    use NumericOp::*;
+    let s = stack;
+    match op {
+        I32Eqz => unary(s, |a: i32| (a == 0) as i32),
+        I32Eq => binary(s, |a: i32, b| (a == b) as i32),
+        I32Ne => binary(s, |a: i32, b| (a != b) as i32),
+        I32LtS => binary(s, |a: i32, b| (a < b) as i32),
+        I32LtU => binary(s, |a: i32, b| ((a as u32) < (b as u32)) as i32),
+        I32GtS => binary(s, |a: i32, b| (a > b) as i32),
+        I32GtU => binary(s, |a: i32, b| (a as u32 > b as u32) as i32),
+        I32LeS => binary(s, |a: i32, b| (a <= b) as i32),
+        I32LeU => binary(s, |a: i32, b| (a as u32 <= b as u32) as i32),
+        I32GeS => binary(s, |a: i32, b| (a >= b) as i32),
+        I32GeU => binary(s, |a: i32, b| (a as u32 >= b as u32) as i32),
+        I64Eqz => unary(s, |a: i64| (a == 0) as i32),
+        I64Eq => binary(s, |a: i64, b| (a == b) as i32),
+        I64Ne => binary(s, |a: i64, b| (a != b) as i32),
+        I64LtS => binary(s, |a: i64, b| (a < b) as i32),
+        I64LtU => binary(s, |a: i64, b| ((a as u64) < (b as u64)) as i32),
+        I64GtS => binary(s, |a: i64, b| (a > b) as i32),
+        I64GtU => binary(s, |a: i64, b| (a as u64 > b as u64) as i32),
+        I64LeS => binary(s, |a: i64, b| (a <= b) as i32),
+        I64LeU => binary(s, |a: i64, b| (a as u64 <= b as u64) as i32),
+        I64GeS => binary(s, |a: i64, b| (a >= b) as i32),
+        I64GeU => binary(s, |a: i64, b| (a as u64 >= b as u64) as i32),
+        F32Eq => binary(s, |a: f32, b| (a == b) as i32),
+        F32Ne => binary(s, |a: f32, b| (a != b) as i32),
+        F32Lt => binary(s, |a: f32, b| (a < b) as i32),
+        F32Gt => binary(s, |a: f32, b| (a > b) as i32),
+        F32Le => binary(s, |a: f32, b| (a <= b) as i32),
+        F32Ge => binary(s, |a: f32, b| (a >= b) as i32),
+        F64Eq => binary(s, |a: f64, b| (a == b) as i32),
+        F64Ne => binary(s, |a: f64, b| (a != b) as i32),
+        F64Lt => binary(s, |a: f64, b| (a < b) as i32),
+        F64Gt => binary(s, |a: f64, b| (a > b) as i32),
+        F64Le => binary(s, |a: f64, b| (a <= b) as i32),
+        F64Ge => binary(s, |a: f64, b| (a >= b) as i32),
+        I32Clz => unary(s, |a: i32| a.leading_zeros() as i32),
+        I32Ctz => unary(s, |a: i32| a.trailing_zeros() as i32),
+        I32Popcnt => unary(s, |a: i32| a.count_ones() as i32),
+        I32Add => binary(s, i32::wrapping_add),
+        I32Sub => binary(s, i32::wrapping_sub),
+        I32Mul => binary(s, i32::wrapping_mul),
+        I32DivS => try_binary(s, |a: i32, b| checked_div!(a, b, checked_div))?,
+        I32DivU => try_binary(s, |a: i32, b| {
+            checked_div!(a as u32, b as u32, checked_div).map(|r| r as i32)
+        })?,
+        I32RemS => try_binary(s, |a: i32, b| checked_rem!(a, b))?,
+        I32RemU => try_binary(s, |a: i32, b| {
+            checked_rem!(a as u32, b as u32).map(|r| r as i32)
+        })?,
+        I32And => binary(s, |a: i32, b| a & b),
+        I32Or => binary(s, |a: i32, b| a | b),
+        I32Xor => binary(s, |a: i32, b| a ^ b),
+        I32Shl => binary(s, |a: i32, b| a.wrapping_shl(b as u32)),
+        I32ShrS => binary(s, |a: i32, b| a.wrapping_shr(b as u32)),
+        I32ShrU => binary(s, |a: i32, b| (a as u32).wrapping_shr(b as u32) as i32),
+        I32Rotl => binary(s, |a: i32, b| a.rotate_left(b as u32 % 32)),
+        I32Rotr => binary(s, |a: i32, b| a.rotate_right(b as u32 % 32)),
+        I64Clz => unary(s, |a: i64| a.leading_zeros() as i64),
+        I64Ctz => unary(s, |a: i64| a.trailing_zeros() as i64),
+        I64Popcnt => unary(s, |a: i64| a.count_ones() as i64),
+        I64Add => binary(s, i64::wrapping_add),
+        I64Sub => binary(s, i64::wrapping_sub),
+        I64Mul => binary(s, i64::wrapping_mul),
+        I64DivS => try_binary(s, |a: i64, b| checked_div!(a, b, checked_div))?,
+        I64DivU => try_binary(s, |a: i64, b| {
+            checked_div!(a as u64, b as u64, checked_div).map(|r| r as i64)
+        })?,
+        I64RemS => try_binary(s, |a: i64, b| checked_rem!(a, b))?,
+        I64RemU => try_binary(s, |a: i64, b| {
+            checked_rem!(a as u64, b as u64).map(|r| r as i64)
+        })?,
+        I64And => binary(s, |a: i64, b| a & b),
+        I64Or => binary(s, |a: i64, b| a | b),
+        I64Xor => binary(s, |a: i64, b| a ^ b),
+        I64Shl => binary(s, |a: i64, b| a.wrapping_shl(b as u32)),
+        I64ShrS => binary(s, |a: i64, b| a.wrapping_shr(b as u32)),
+        I64ShrU => binary(s, |a: i64, b| (a as u64).wrapping_shr(b as u32) as i64),
+        I64Rotl => binary(s, |a: i64, b| a.rotate_left((b % 64) as u32)),
+        I64Rotr => binary(s, |a: i64, b| a.rotate_right((b % 64) as u32)),
+        F32Abs => unary(s, f32::abs),
+        F32Neg => unary(s, |a: f32| -a),
+        F32Ceil => unary(s, f32::ceil),
+        F32Floor => unary(s, f32::floor),
+        F32Trunc => unary(s, f32::trunc),
+        F32Nearest => unary(s, f32::round_ties_even),
+        F32Sqrt => unary(s, f32::sqrt),
+        F32Add => binary(s, |a: f32, b| a + b),
+        F32Sub => binary(s, |a: f32, b| a - b),
+        F32Mul => binary(s, |a: f32, b| a * b),
+        F32Div => binary(s, |a: f32, b| a / b),
+        F32Min => binary(s, f32_min),
+        F32Max => binary(s, f32_max),
+        F32Copysign => binary(s, f32::copysign),
+        F64Abs => unary(s, f64::abs),
+        F64Neg => unary(s, |a: f64| -a),
+        F64Ceil => unary(s, f64::ceil),
+        F64Floor => unary(s, f64::floor),
+        F64Trunc => unary(s, f64::trunc),
+        F64Nearest => unary(s, f64::round_ties_even),
+        F64Sqrt => unary(s, f64::sqrt),
+        F64Add => binary(s, |a: f64, b| a + b),
+        F64Sub => binary(s, |a: f64, b| a - b),
+        F64Mul => binary(s, |a: f64, b| a * b),
+        F64Div => binary(s, |a: f64, b| a / b),
+        F64Min => binary(s, f64_min),
+        F64Max => binary(s, f64_max),
+        F64Copysign => binary(s, f64::copysign),
+        I32WrapI64 => unary(s, |a: i64| a as i32),
+        I32TruncF32S => try_unary(s, |a: f32| Ok(truncate(a as f64, I32_MIN, I32_END)? as i32))?,
+        I32TruncF32U => try_unary(s, |a: f32| {
+            Ok(truncate(a as f64, 0.0, U32_END)? as u32 as i32)
+        })?,
+        I32TruncF64S => try_unary(s, |a: f64| Ok(truncate(a, I32_MIN, I32_END)? as i32))?,
+        I32TruncF64U => try_unary(s, |a: f64| Ok(truncate(a, 0.0, U32_END)? as u32 as i32))?,
+        I64ExtendI32S => unary(s, |a: i32| a as i64),
+        I64ExtendI32U => unary(s, |a: i32| a as u32 as i64),
+        I64TruncF32S => try_unary(s, |a: f32| Ok(truncate(a as f64, I64_MIN, I64_END)? as i64))?,
+        I64TruncF32U => try_unary(s, |a: f32| {
+            Ok(truncate(a as f64, 0.0, U64_END)? as u64 as i64)
+        })?,
+        I64TruncF64S => try_unary(s, |a: f64| Ok(truncate(a, I64_MIN, I64_END)? as i64))?,
+        I64TruncF64U => try_unary(s, |a: f64| Ok(truncate(a, 0.0, U64_END)? as u64 as i64))?,
+        F32ConvertI32S => unary(s, |a: i32| a as f32),
+        F32ConvertI32U => unary(s, |a: i32| a as u32 as f32),
+        F32ConvertI64S => unary(s, |a: i64| a as f32),
+        F32ConvertI64U => unary(s, |a: i64| a as u64 as f32),
+        F32DemoteF64 => unary(s, |a: f64| a as f32),
+        F64ConvertI32S => unary(s, |a: i32| a as f64),
+        F64ConvertI32U => unary(s, |a: i32| a as u32 as f64),
+        F64ConvertI64S => unary(s, |a: i64| a as f64),
+        F64ConvertI64U => unary(s, |a: i64| a as u64 as f64),
+        F64PromoteF32 => unary(s, |a: f32| a as f64),
+        I32ReinterpretF32 => unary(s, |a: f32| a.to_bits() as i32),
+        I64ReinterpretF64 => unary(s, |a: f64| a.to_bits() as i64),
+        F32ReinterpretI32 => unary(s, |a: i32| f32::from_bits(a as u32)),
+        F64ReinterpretI64 => unary(s, |a: i64| f64::from_bits(a as u64)),
+        I32Extend8S => unary(s, |a: i32| a as i8 as i32),
+        I32Extend16S => unary(s, |a: i32| a as i16 as i32),
+        I64Extend8S => unary(s, |a: i64| a as i8 as i64),
+        I64Extend16S => unary(s, |a: i64| a as i16 as i64),
+        I64Extend32S => unary(s, |a: i64| a as i32 as i64),
+        // `as` saturates and maps NaN to 0, which is what the saturating truncations do
+        I32TruncSatF32S => unary(s, |a: f32| a as i32),
+        I32TruncSatF32U => unary(s, |a: f32| a as u32 as i32),
+        I32TruncSatF64S => unary(s, |a: f64| a as i32),
+        I32TruncSatF64U => unary(s, |a: f64| a as u32 as i32),
+        I64TruncSatF32S => unary(s, |a: f32| a as i64),
+        I64TruncSatF32U => unary(s, |a: f32| a as u64 as i64),
+        I64TruncSatF64S => unary(s, |a: f64| a as i64),
+        I64TruncSatF64U => unary(s, |a: f64| a as u64 as i64),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(op: NumericOp, args: &[Value]) -> Result<Value, Trap> {
+        let mut stack = ValueStack::default();
+        for arg in args {
+            stack.push(*arg);
+        }
+        execute(op, &mut stack)?;
+        Ok(stack.pop())
+    }
+
+    #[test]
+    fn test_integer_ops() {
+        use NumericOp::*;
+        let i32s = |a: i32, b: i32| [Value::I32(a), Value::I32(b)];
+        assert_eq!(run(I32Add, &i32s(i32::MAX, 1)), Ok(Value::I32(i32::MIN)));
+        assert_eq!(run(I32DivU, &i32s(-1, 2)), Ok(Value::I32(i32::MAX)));
+        assert_eq!(run(I32RemU, &i32s(-1, 0)), Err(Trap::IntegerDivideByZero));
+        assert_eq!(run(I32Shl, &i32s(1, 33)), Ok(Value::I32(2)));
+        assert_eq!(run(I32ShrS, &i32s(-8, 1)), Ok(Value::I32(-4)));
+        assert_eq!(run(I32ShrU, &i32s(-8, 1)), Ok(Value::I32(0x7ffffffc)));
+        assert_eq!(
+            run(I32Rotl, &i32s(0x80000001_u32 as i32, 1)),
+            Ok(Value::I32(3))
+        );
+        assert_eq!(run(I32LtU, &i32s(-1, 1)), Ok(Value::I32(0)));
+        assert_eq!(run(I32Clz, &[Value::I32(1)]), Ok(Value::I32(31)));
+        assert_eq!(run(I32Extend8S, &[Value::I32(0x80)]), Ok(Value::I32(-128)));
+        assert_eq!(
+            run(I64DivS, &[Value::I64(i64::MIN), Value::I64(-1)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(
+            run(I64Rotr, &[Value::I64(1), Value::I64(-1)]),
+            Ok(Value::I64(2))
+        );
+        assert_eq!(
+            run(I64ExtendI32U, &[Value::I32(-1)]),
+            Ok(Value::I64(0xffffffff))
+        );
+    }
+
+    #[test]
+    fn test_float_ops() {
+        use NumericOp::*;
+        let f32s = |a: f32, b: f32| [Value::F32(a), Value::F32(b)];
+        assert_eq!(run(F32Min, &f32s(0.0, -0.0)), Ok(Value::F32(-0.0)));
+        assert!(matches!(run(F32Min, &f32s(f32::NAN, 1.0)), Ok(Value::F32(v)) if v.is_nan()));
+        assert_eq!(
+            run(F64Max, &[Value::F64(-0.0), Value::F64(0.0)]),
+            Ok(Value::F64(0.0))
+        );
+        assert_eq!(run(F32Nearest, &[Value::F32(2.5)]), Ok(Value::F32(2.0)));
+        assert_eq!(run(F64Nearest, &[Value::F64(-3.5)]), Ok(Value::F64(-4.0)));
+        assert_eq!(run(F32Copysign, &f32s(1.0, -0.0)), Ok(Value::F32(-1.0)));
+        assert_eq!(run(F32Eq, &f32s(f32::NAN, f32::NAN)), Ok(Value::I32(0)));
+        assert_eq!(
+            run(F64ConvertI64U, &[Value::I64(-1)]),
+            Ok(Value::F64(18446744073709551616.0))
+        );
+        assert_eq!(
+            run(I32ReinterpretF32, &[Value::F32(-0.0)]),
+            Ok(Value::I32(i32::MIN))
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        use NumericOp::*;
+        assert_eq!(
+            run(I32TruncF32S, &[Value::F32(-2147483648.0)]),
+            Ok(Value::I32(i32::MIN))
+        );
+        assert_eq!(
+            run(I32TruncF32S, &[Value::F32(2147483648.0)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(run(I32TruncF64U, &[Value::F64(-0.9)]), Ok(Value::I32(0)));
+        assert_eq!(
+            run(I64TruncF64S, &[Value::F64(f64::INFINITY)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(
+            run(I64TruncF32U, &[Value::F32(f32::NAN)]),
+            Err(Trap::InvalidConversionToInteger)
+        );
+        assert_eq!(run(I32TruncSatF32U, &[Value::F32(-5.0)]), Ok(Value::I32(0)));
+        assert_eq!(
+            run(I32TruncSatF64S, &[Value::F64(1e10)]),
+            Ok(Value::I32(i32::MAX))
+        );
+        assert_eq!(
+            run(I64TruncSatF64S, &[Value::F64(f64::NAN)]),
+            Ok(Value::I64(0))
+        );
+    }
+}
@@ -0,0 +1,740 @@
+use std::{
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::{
+    code::Code,
+    error::{AccessError, AllocationError, ImportMismatch, InstantiationError, InvokeError, Trap},
+    host::{Caller, IntoFunc},
+    instance::{
+        ExportInstance, Extern, FunctionInstance, FunctionKind, GlobalInstance, Instance,
+        MemoryInstance, ModuleInstance, TableInstance, checked_range,
+    },
+    interpreter,
+    value::Value,
+};
+use crate::{
+    ast::{
+        ModuleParsed, Section,
+        instructions::RawExpression,
+        section::{DataMode, ElementItems, ElementKind, ExportDesc, Import, ImportDesc, SectionID},
+        types::{FunctionType, GlobalType, Limits, MemoryType, TableType, ValueType},
+    },
+    validation::validate_module,
+};
+
+/// Identifies a [`Store`], so that handles are only used with the store they came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct StoreId(u64);
+
+impl Default for StoreId {
+    /// A new id, different from that of any other store.
+    fn default() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        StoreId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// All runtime objects. Module instances refer to them by their address,
+/// which is the index in the vector holding them.
+#[derive(Debug, Default)]
+pub struct Store {
+    pub(super) id: StoreId,
+    pub(crate) functions: Vec<FunctionInstance>,
+    pub(crate) tables: Vec<TableInstance>,
+    pub(crate) memories: Vec<MemoryInstance>,
+    pub(crate) globals: Vec<GlobalInstance>,
+    /// Element segments; dropped ones are empty.
+    pub(crate) elements: Vec<Vec<Value>>,
+    /// Data segments; dropped ones are empty.
+    pub(crate) datas: Vec<Vec<u8>>,
+    pub(crate) instances: Vec<ModuleInstance>,
+    /// Number of active calls, including those of machines suspended in host functions.
+    pub(super) call_depth: usize,
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validates `module`, allocates its objects with `imports` standing for its imports in order,
+    /// initializes tables and memories from active segments and runs the start function.
+    /// Objects allocated before a failure stay in the store, as in the specification.
+    pub fn instantiate(
+        &mut self,
+        module: &ModuleParsed,
+        imports: &[Extern],
+    ) -> Result<Instance, InstantiationError> {
+        validate_module(module)?;
+        let address = self.instances.len() as u32;
+        let mut instance = ModuleInstance::default();
+        if let Some(Section::Type(s)) = module.sec_by_id(SectionID::Type) {
+            instance.types = s.types.clone();
+        }
+        self.resolve_imports(module, imports, &mut instance)?;
+        for section in &module.sections {
+            match section {
+                Section::Table(s) => {
+                    for ty in &s.tables {
+                        instance.tables.push(self.tables.len() as u32);
+                        self.tables.push(TableInstance::new(ty.clone())?);
+                    }
+                }
+                Section::Memory(s) => {
+                    for ty in &s.memories {
+                        instance.memories.push(self.memories.len() as u32);
+                        self.memories.push(MemoryInstance::new(ty.clone())?);
+                    }
+                }
+                Section::Code(s) => {
+                    let codes = s.code.iter().map(|b| Code::new(&b.locals, &b.expression));
+                    self.allocate_functions(module, address, codes, &mut instance);
+                }
+                Section::LazyCode(s) => {
+                    let codes = s.iter().map(|b| {
+                        let b = b.expect("validated function body should decode");
+                        Code::new(&b.locals, &b.expression)
+                    });
+                    self.allocate_functions(module, address, codes, &mut instance);
+                }
+                _ => (),
+            }
+        }
+        // constant expressions may refer to functions and earlier globals of the instance
+        self.instances.push(instance);
+        for section in &module.sections {
+            match section {
+                Section::Global(s) => {
+                    for global in &s.globals {
+                        let value = interpreter::evaluate(self, address, &global.expression)?;
+                        let global_address = self.globals.len() as u32;
+                        self.globals.push(GlobalInstance {
+                            ty: global.global_type.clone(),
+                            value,
+                        });
+                        self.instances[address as usize]
+                            .globals
+                            .push(global_address);
+                    }
+                }
+                Section::Element(s) => {
+                    for element in &s.elements {
+                        let functions = &self.instances[address as usize].functions;
+                        let items = match &element.items {
+                            ElementItems::Functions(indices) => indices
+                                .iter()
+                                .map(|&i| Value::FuncRef(Some(functions[i as usize])))
+                                .collect(),
+                            ElementItems::Expressions(_, expressions) => expressions
+                                .iter()
+                                .map(|e| interpreter::evaluate(self, address, e))
+                                .collect::<Result<_, _>>()?,
+                        };
+                        let element_address = self.elements.len() as u32;
+                        self.elements.push(items);
+                        self.instances[address as usize]
+                            .elements
+                            .push(element_address);
+                    }
+                }
+                Section::Data(s) => {
+                    for segment in &s.segments {
+                        let data_address = self.datas.len() as u32;
+                        self.datas.push(segment.data.to_vec());
+                        self.instances[address as usize].datas.push(data_address);
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.export(module, address);
+        self.initialize_segments(module, address)?;
+        if let Some(Section::Start(start)) = module.sec_by_id(SectionID::Start) {
+            let function =
+                self.instances[address as usize].functions[start.start_function_index as usize];
+            interpreter::call(self, function, Vec::new())?;
+        }
+        Ok(Instance {
+            store: self.id,
+            address,
+        })
+    }
+
+    fn resolve_imports(
+        &self,
+        module: &ModuleParsed,
+        imports: &[Extern],
+        instance: &mut ModuleInstance,
+    ) -> Result<(), InstantiationError> {
+        let descs: &[Import] = match module.sec_by_id(SectionID::Import) {
+            Some(Section::Import(s)) => &s.imports,
+            _ => &[],
+        };
+        if descs.len() != imports.len() {
+            return Err(InstantiationError::ImportCountMismatch {
+                expected: descs.len(),
+                actual: imports.len(),
+            });
+        }
+        for (import, &value) in descs.iter().zip(imports) {
+            self.check_import(&import.desc, &instance.types, value)
+                .map_err(|reason| InstantiationError::IncompatibleImport {
+                    module: import.module.clone(),
+                    name: import.name.clone(),
+                    reason: Box::new(reason),
+                })?;
+            match value {
+                Extern::Function(a) => instance.functions.push(a),
+                Extern::Table(a) => instance.tables.push(a),
+                Extern::Memory(a) => instance.memories.push(a),
+                Extern::Global(a) => instance.globals.push(a),
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that `value` exists in this store and matches the type an import declares. Tables
+    /// and memories may be larger than required, and must have a maximum no larger than a
+    /// declared one.
+    fn check_import(
+        &self,
+        desc: &ImportDesc,
+        types: &[FunctionType],
+        value: Extern,
+    ) -> Result<(), ImportMismatch> {
+        let (address, len) = match value {
+            Extern::Function(a) => (a, self.functions.len()),
+            Extern::Table(a) => (a, self.tables.len()),
+            Extern::Memory(a) => (a, self.memories.len()),
+            Extern::Global(a) => (a, self.globals.len()),
+        };
+        // the value may come from another store
+        if address as usize >= len {
+            return Err(ImportMismatch::UnknownAddress {
+                kind: value.kind(),
+                address,
+            });
+        }
+        match (desc, value) {
+            (ImportDesc::TypeIndex(t), Extern::Function(a)) => {
+                let (expected, actual) = (&types[*t as usize], &self.functions[a as usize].ty);
+                if expected != actual {
+                    return Err(ImportMismatch::Function {
+                        expected: expected.clone(),
+                        actual: actual.clone(),
+                    });
+                }
+            }
+            (ImportDesc::Table(expected), Extern::Table(a)) => {
+                let actual = &self.tables[a as usize].ty;
+                if actual.ref_type != expected.ref_type
+                    || !limits_match(&actual.limits, &expected.limits)
+                {
+                    return Err(ImportMismatch::Table {
+                        expected: expected.clone(),
+                        actual: actual.clone(),
+                    });
+                }
+            }
+            (ImportDesc::Memory(expected), Extern::Memory(a)) => {
+                let actual = &self.memories[a as usize].ty;
+                if !limits_match(&actual.limits, &expected.limits) {
+                    return Err(ImportMismatch::Memory {
+                        expected: expected.clone(),
+                        actual: actual.clone(),
+                    });
+                }
+            }
+            (ImportDesc::Global(expected), Extern::Global(a)) => {
+                let actual = &self.globals[a as usize].ty;
+                if expected != actual {
+                    return Err(ImportMismatch::Global {
+                        expected: expected.clone(),
+                        actual: actual.clone(),
+                    });
+                }
+            }
+            (desc, value) => {
+                return Err(ImportMismatch::Kind {
+                    expected: match desc {
+                        ImportDesc::TypeIndex(_) => "function",
+                        ImportDesc::Table(_) => "table",
+                        ImportDesc::Memory(_) => "memory",
+                        ImportDesc::Global(_) => "global",
+                    },
+                    actual: value.kind(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn allocate_functions(
+        &mut self,
+        module: &ModuleParsed,
+        address: u32,
+        codes: impl Iterator<Item = Code>,
+        instance: &mut ModuleInstance,
+    ) {
+        let type_indices: &[u32] = match module.sec_by_id(SectionID::Function) {
+            Some(Section::Function(s)) => &s.type_indices,
+            _ => &[],
+        };
+        for (&t, code) in type_indices.iter().zip(codes) {
+            instance.functions.push(self.functions.len() as u32);
+            self.functions.push(FunctionInstance {
+                ty: instance.types[t as usize].clone(),
+                kind: FunctionKind::Wasm {
+                    module: address,
+                    code: Rc::new(code),
+                },
+            });
+        }
+    }
+
+    fn export(&mut self, module: &ModuleParsed, address: u32) {
+        let Some(Section::Export(s)) = module.sec_by_id(SectionID::Export) else {
+            return;
+        };
+        let instance = &mut self.instances[address as usize];
+        instance.exports = s
+            .exports
+            .iter()
+            .map(|e| ExportInstance {
+                name: e.name.clone(),
+                value: match e.desc {
+                    ExportDesc::FunctionIndex(i) => {
+                        Extern::Function(instance.functions[i as usize])
+                    }
+                    ExportDesc::TableIndex(i) => Extern::Table(instance.tables[i as usize]),
+                    ExportDesc::MemoryIndex(i) => Extern::Memory(instance.memories[i as usize]),
+                    ExportDesc::GlobalIndex(i) => Extern::Global(instance.globals[i as usize]),
+                },
+            })
+            .collect();
+    }
+
+    /// Copies active segments into tables and memories, then drops them along with
+    /// declarative segments.
+    fn initialize_segments(&mut self, module: &ModuleParsed, address: u32) -> Result<(), Trap> {
+        if let Some(Section::Element(s)) = module.sec_by_id(SectionID::Element) {
+            for (i, element) in s.elements.iter().enumerate() {
+                let element_address = self.instances[address as usize].elements[i];
+                match &element.kind {
+                    ElementKind::Active {
+                        table_index,
+                        offset_expression,
+                    } => {
+                        let offset = self.evaluate_offset(address, offset_expression)?;
+                        let table = self.instances[address as usize].tables
+                            [table_index.unwrap_or(0) as usize];
+                        let len = self.elements[element_address as usize].len() as u64;
+                        self.table_init(table, element_address, offset, 0, len)?;
+                    }
+                    ElementKind::Declarative => (),
+                    ElementKind::Passive => continue,
+                }
+                self.elements[element_address as usize] = Vec::new();
+            }
+        }
+        if let Some(Section::Data(s)) = module.sec_by_id(SectionID::Data) {
+            for (i, segment) in s.segments.iter().enumerate() {
+                let DataMode::Active {
+                    memory_index,
+                    offset_expression,
+                } = &segment.mode
+                else {
+                    continue;
+                };
+                let data_address = self.instances[address as usize].datas[i];
+                let offset = self.evaluate_offset(address, offset_expression)?;
+                let memory =
+                    self.instances[address as usize].memories[memory_index.unwrap_or(0) as usize];
+                let len = self.datas[data_address as usize].len() as u64;
+                self.memory_init(memory, data_address, offset, 0, len)?;
+                self.datas[data_address as usize] = Vec::new();
+            }
+        }
+        Ok(())
+    }
+
+    fn evaluate_offset(&mut self, address: u32, expression: &RawExpression) -> Result<u64, Trap> {
+        match interpreter::evaluate(self, address, expression)? {
+            Value::I32(offset) => Ok(offset as u32 as u64),
+            v => unreachable!("validated offset should be i32: {:?}", v),
+        }
+    }
+
+    /// Copies `n` references from `source` in an element segment to `dest` in a table.
+    pub(super) fn table_init(
+        &mut self,
+        table: u32,
+        element: u32,
+        dest: u64,
+        source: u64,
+        n: u64,
+    ) -> Result<(), Trap> {
+        let element = &self.elements[element as usize];
+        let table = &mut self.tables[table as usize];
+        let source = checked_range(source, n, element.len()).ok_or(Trap::TableOutOfBounds)?;
+        let dest = checked_range(dest, n, table.elements.len()).ok_or(Trap::TableOutOfBounds)?;
+        table.elements[dest].copy_from_slice(&element[source]);
+        Ok(())
+    }
+
+    /// Copies `n` bytes from `source` in a data segment to `dest` in a memory.
+    pub(super) fn memory_init(
+        &mut self,
+        memory: u32,
+        data: u32,
+        dest: u64,
+        source: u64,
+        n: u64,
+    ) -> Result<(), Trap> {
+        let data = &self.datas[data as usize];
+        let source = checked_range(source, n, data.len()).ok_or(Trap::MemoryOutOfBounds)?;
+        self.memories[memory as usize].write(dest, &data[source])
+    }
+
+    /// Allocates a host function of type `ty`, which can be given as a function import.
+    /// `f` receives arguments of the parameter types and must return values of the result types.
+    pub fn define_function(
+        &mut self,
+        ty: FunctionType,
+        f: impl Fn(&mut Caller, &[Value]) -> Result<Vec<Value>, Trap> + 'static,
+    ) -> Extern {
+        self.functions.push(FunctionInstance {
+            ty,
+            kind: FunctionKind::Host(Rc::new(f)),
+        });
+        Extern::Function(self.functions.len() as u32 - 1)
+    }
+
+    /// Allocates a host function whose type is derived from the closure's signature,
+    /// such as `|a: i32, b: i64| -> f32` or `|caller: &mut Caller, a: i32| -> Result<i32, Trap>`.
+    pub fn wrap<Params, Results>(&mut self, f: impl IntoFunc<Params, Results>) -> Extern {
+        let (ty, f) = f.into_func();
+        self.functions.push(FunctionInstance {
+            ty,
+            kind: FunctionKind::Host(f),
+        });
+        Extern::Function(self.functions.len() as u32 - 1)
+    }
+
+    /// Allocates a memory of type `ty`, which can be given as a memory import.
+    pub fn define_memory(&mut self, ty: MemoryType) -> Result<Extern, AllocationError> {
+        self.memories.push(MemoryInstance::new(ty)?);
+        Ok(Extern::Memory(self.memories.len() as u32 - 1))
+    }
+
+    /// Allocates a table of type `ty` whose elements are `init`.
+    ///
+    /// # Panics
+    ///
+    /// If `init` is not a reference of the element type or refers to a function
+    /// not in this store.
+    pub fn define_table(&mut self, ty: TableType, init: Value) -> Result<Extern, AllocationError> {
+        self.check_value(ty.ref_type.into(), init)
+            .expect("table element should be valid");
+        let mut table = TableInstance::new(ty)?;
+        table.elements.fill(init);
+        self.tables.push(table);
+        Ok(Extern::Table(self.tables.len() as u32 - 1))
+    }
+
+    /// Allocates a global of type `ty` holding `value`.
+    ///
+    /// # Panics
+    ///
+    /// If `value` is not of the value type of the global or refers to a function
+    /// not in this store.
+    pub fn define_global(&mut self, ty: GlobalType, value: Value) -> Extern {
+        self.check_value(ty.val_type, value)
+            .expect("global value should be valid");
+        self.globals.push(GlobalInstance { ty, value });
+        Extern::Global(self.globals.len() as u32 - 1)
+    }
+
+    /// Checks that a handle with the store id `id` belongs to this store.
+    pub(super) fn check_id(&self, id: StoreId) -> Result<(), AccessError> {
+        if id != self.id {
+            return Err(AccessError::WrongStore);
+        }
+        Ok(())
+    }
+
+    /// Checks that a value given by the host has type `expected`
+    /// and refers to a function in this store, if any.
+    pub(super) fn check_value(&self, expected: ValueType, value: Value) -> Result<(), AccessError> {
+        let actual = value.value_type();
+        if actual != expected {
+            return Err(AccessError::TypeMismatch { expected, actual });
+        }
+        match value {
+            Value::FuncRef(Some(a)) if a as usize >= self.functions.len() => {
+                Err(AccessError::UnknownFunction(a))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Calls the function at `address`; the arguments must match its parameter types.
+    pub fn invoke(&mut self, address: u32, args: &[Value]) -> Result<Vec<Value>, InvokeError> {
+        let ty = &self
+            .functions
+            .get(address as usize)
+            .ok_or(AccessError::UnknownFunction(address))?
+            .ty;
+        let actual: Vec<_> = args.iter().map(Value::value_type).collect();
+        if actual != ty.params {
+            return Err(InvokeError::ArgumentTypeMismatch {
+                expected: ty.params.clone(),
+                actual,
+            });
+        }
+        Ok(interpreter::call(self, address, args.to_vec())?)
+    }
+
+    pub fn invoke_export(
+        &mut self,
+        instance: Instance,
+        name: &str,
+        args: &[Value],
+    ) -> Result<Vec<Value>, InvokeError> {
+        match instance.export(self, name) {
+            Ok(Extern::Function(address)) => self.invoke(address, args),
+            Err(e @ AccessError::WrongStore) => Err(e.into()),
+            _ => Err(InvokeError::NoSuchFunction(name.to_string())),
+        }
+    }
+}
+
+/// Whether limits of an imported object satisfy those the import declares.
+fn limits_match(actual: &Limits, expected: &Limits) -> bool {
+    actual.min >= expected.min
+        && match (actual.max, expected.max) {
+            (_, None) => true,
+            (Some(actual), Some(expected)) => actual <= expected,
+            (None, Some(_)) => false,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::types::ReferenceType,
+        exec::{MAX_TABLE_SIZE, instantiate},
+    };
+
+    #[test]
+    fn test_segments_and_start() {
+        let mut store = Store::new();
+        let instance = instantiate(
+            &mut store,
+            r#"
+(module
+  (memory 1)
+  (table 4 funcref)
+  (global $started (mut i32) (i32.const 0))
+  (func $one (result i32) (i32.const 1))
+  (func $two (result i32) (i32.const 2))
+  (func $start (global.set $started (i32.const 1)))
+  (start $start)
+  (elem (i32.const 1) $one $two)
+  (elem $passive funcref (ref.func $two))
+  (elem declare func $one)
+  (data (i32.const 8) "\01\02")
+  (data $later "\03\04")
+  (func (export "init")
+    (memory.init $later (i32.const 10) (i32.const 0) (i32.const 2))
+    (table.init $passive (i32.const 3) (i32.const 0) (i32.const 1)))
+  (func (export "call") (param i32) (result i32)
+    (call_indirect (result i32) (local.get 0)))
+  (func (export "load") (param i32) (result i32)
+    (i32.load (local.get 0)))
+  (func (export "started") (result i32) (global.get $started)))
+"#,
+            &[],
+        )
+        .unwrap();
+        let mut invoke = |name, args: &[Value]| store.invoke_export(instance, name, args);
+        assert_eq!(invoke("started", &[]), Ok(vec![Value::I32(1)]));
+        assert_eq!(invoke("call", &[Value::I32(2)]), Ok(vec![Value::I32(2)]));
+        assert_eq!(
+            invoke("load", &[Value::I32(8)]),
+            Ok(vec![Value::I32(0x0201)])
+        );
+        assert_eq!(invoke("init", &[]), Ok(vec![]));
+        assert_eq!(
+            invoke("load", &[Value::I32(8)]),
+            Ok(vec![Value::I32(0x04030201)])
+        );
+        assert_eq!(invoke("call", &[Value::I32(3)]), Ok(vec![Value::I32(2)]));
+        // active and declarative segments are dropped after instantiation
+        let module = &store.instances[instance.address as usize];
+        let lengths: Vec<_> = module
+            .elements
+            .iter()
+            .map(|&a| store.elements[a as usize].len())
+            .collect();
+        assert_eq!(lengths, vec![0, 1, 0]);
+        assert_eq!(store.datas[module.datas[0] as usize], Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_imports() {
+        let mut store = Store::new();
+        let exporter = instantiate(
+            &mut store,
+            r#"
+(module
+  (memory (export "memory") 1)
+  (global (export "base") i32 (i32.const 16))
+  (table (export "table") 2 funcref)
+  (func (export "add") (param i32 i32) (result i32) (i32.add (local.get 0) (local.get 1))))
+"#,
+            &[],
+        )
+        .unwrap();
+        let imports: Vec<_> = ["add", "memory", "base", "table"]
+            .iter()
+            .map(|name| exporter.export(&store, name).unwrap())
+            .collect();
+        let importer = r#"
+(module
+  (import "m" "add" (func $add (param i32 i32) (result i32)))
+  (import "m" "memory" (memory 1))
+  (import "m" "base" (global $base i32))
+  (import "m" "table" (table 1 funcref))
+  (global $copy i32 (global.get $base))
+  (elem (i32.const 1) $add)
+  (data (global.get $base) "\2a")
+  (func (export "run") (result i32)
+    (call $add (i32.load8_u (global.get $base)) (global.get $copy))))
+"#;
+        let importer_instance = instantiate(&mut store, importer, &imports).unwrap();
+        assert_eq!(
+            store.invoke_export(importer_instance, "run", &[]),
+            Ok(vec![Value::I32(58)])
+        );
+        let Ok(Extern::Memory(memory)) = exporter.export(&store, "memory") else {
+            unreachable!()
+        };
+        assert_eq!(store.memories[memory as usize].data()[16], 0x2a);
+        let Ok(Extern::Table(table)) = exporter.export(&store, "table") else {
+            unreachable!()
+        };
+        let add = imports[0];
+        assert_eq!(
+            Extern::Function(match store.tables[table as usize].elements[1] {
+                Value::FuncRef(Some(address)) => address,
+                v => unreachable!("unexpected element {:?}", v),
+            }),
+            add
+        );
+
+        let error = instantiate(&mut store, importer, &imports[..2]).unwrap_err();
+        assert!(matches!(
+            error,
+            InstantiationError::ImportCountMismatch {
+                expected: 4,
+                actual: 2
+            }
+        ));
+        let swapped = [imports[1], imports[0], imports[2], imports[3]];
+        let error = instantiate(&mut store, importer, &swapped).unwrap_err();
+        assert!(
+            matches!(&error, InstantiationError::IncompatibleImport { name, .. } if name == "add")
+        );
+        let error = instantiate(
+            &mut store,
+            r#"(module (import "m" "memory" (memory 2)))"#,
+            &imports[1..2],
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            InstantiationError::IncompatibleImport { .. }
+        ));
+
+        // e.g. an address from another store
+        let foreign = Extern::Memory(store.memories.len() as u32);
+        let error = instantiate(
+            &mut store,
+            r#"(module (import "m" "memory" (memory 1)))"#,
+            &[foreign],
+        )
+        .unwrap_err();
+        let InstantiationError::IncompatibleImport { reason, .. } = error else {
+            unreachable!("unexpected error {:?}", error);
+        };
+        assert!(matches!(
+            *reason,
+            ImportMismatch::UnknownAddress { kind: "memory", .. }
+        ));
+    }
+
+    #[test]
+    fn test_instantiation_traps() {
+        let mut store = Store::new();
+        let cases = [
+            (
+                r#"(module (memory 1) (data (i32.const 65535) "ab"))"#,
+                Trap::MemoryOutOfBounds,
+            ),
+            (
+                r#"(module (table 1 funcref) (func) (elem (i32.const 1) 0))"#,
+                Trap::TableOutOfBounds,
+            ),
+            (
+                r#"(module (func unreachable) (start 0))"#,
+                Trap::Unreachable,
+            ),
+        ];
+        for (wat, expected) in cases {
+            match instantiate(&mut store, wat, &[]) {
+                Err(InstantiationError::Trap(trap)) => assert_eq!(trap, expected),
+                r => unreachable!("unexpected result for {}: {:?}", wat, r),
+            }
+        }
+        let error = instantiate(&mut store, "(module (func (result i32) i64.const 0))", &[]);
+        assert!(matches!(error, Err(InstantiationError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_allocation_limits() {
+        let mut store = Store::new();
+        let error = instantiate(&mut store, "(module (table 4294967295 funcref))", &[]);
+        assert!(matches!(
+            error,
+            Err(InstantiationError::Allocation(AllocationError::Table(
+                u32::MAX
+            )))
+        ));
+        let table_type = TableType {
+            ref_type: ReferenceType::FuncRef,
+            limits: Limits {
+                min: MAX_TABLE_SIZE + 1,
+                max: None,
+            },
+        };
+        assert_eq!(
+            store.define_table(table_type, Value::FuncRef(None)),
+            Err(AllocationError::Table(MAX_TABLE_SIZE + 1))
+        );
+        let memory_type = MemoryType {
+            limits: Limits {
+                min: 65537,
+                max: None,
+            },
+        };
+        assert_eq!(
+            store.define_memory(memory_type),
+            Err(AllocationError::Memory(65537))
+        );
+        assert!(store.tables.is_empty() && store.memories.is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::ast::types::{NumberType, ReferenceType, ValueType, VectorType};
+
+/// A runtime value. References hold the address of a function in the store
+/// or an embedder-defined number for external objects, or `None` for a null reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    V128(u128),
+    FuncRef(Option<u32>),
+    ExternRef(Option<u32>),
+}
+
+impl Value {
+    /// The zero value locals of type `t` start with.
+    pub fn default_of(t: ValueType) -> Value {
+        match t {
+            ValueType::Number(NumberType::I32) => Value::I32(0),
+            ValueType::Number(NumberType::I64) => Value::I64(0),
+            ValueType::Number(NumberType::F32) => Value::F32(0.0),
+            ValueType::Number(NumberType::F64) => Value::F64(0.0),
+            ValueType::Vector(VectorType::V128) => Value::V128(0),
+            ValueType::Reference(t) => Value::null(t),
+        }
+    }
+
+    pub fn null(t: ReferenceType) -> Value {
+        match t {
+            ReferenceType::FuncRef => Value::FuncRef(None),
+            ReferenceType::ExternRef => Value::ExternRef(None),
+        }
+    }
+
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::I32(_) => NumberType::I32.into(),
+            Value::I64(_) => NumberType::I64.into(),
+            Value::F32(_) => NumberType::F32.into(),
+            Value::F64(_) => NumberType::F64.into(),
+            Value::V128(_) => VectorType::V128.into(),
+            Value::FuncRef(_) => ReferenceType::FuncRef.into(),
+            Value::ExternRef(_) => ReferenceType::ExternRef.into(),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::FuncRef(None) | Value::ExternRef(None))
+    }
+}
+
+/// Rust types which stand for a wasm number type.
+pub trait WasmType: Sized {
+    fn value_type() -> ValueType;
+    fn from_value(value: Value) -> Option<Self>;
+    fn into_value(self) -> Value;
+}
+
+macro_rules! wasm_types {
+    ($($t:ty => $variant:ident),+ $(,)?) => {
+        $(
+            impl WasmType for $t {
+                fn value_type() -> ValueType {
+                    NumberType::$variant.into()
+                }
+
+                fn from_value(value: Value) -> Option<Self> {
+                    match value {
+                        Value::$variant(v) => Some(v),
+                        _ => None,
+                    }
+                }
+
+                fn into_value(self) -> Value {
+                    Value::$variant(self)
+                }
+            }
+
+            impl From<$t> for Value {
+                fn from(value: $t) -> Self {
+                    Value::$variant(value)
+                }
+            }
+        )+
+    };
+}
+
+wasm_types! {
+    i32 => I32,
+    i64 => I64,
+    f32 => F32,
+    f64 => F64,
+}
+
+/// Parameters or results of a typed function: a single [`WasmType`] or a tuple of them.
+pub trait WasmTypeList: Sized {
+    fn types() -> Vec<ValueType>;
+    fn from_values(values: &[Value]) -> Option<Self>;
+    fn into_values(self) -> Vec<Value>;
+}
+
+impl<T: WasmType> WasmTypeList for T {
+    fn types() -> Vec<ValueType> {
+        vec![T::value_type()]
+    }
+
+    fn from_values(values: &[Value]) -> Option<Self> {
+        match values {
+            [value] => T::from_value(*value),
+            _ => None,
+        }
+    }
+
+    fn into_values(self) -> Vec<Value> {
+        vec![self.into_value()]
+    }
+}
+
+macro_rules! wasm_type_lists {
+    ($(($($t:ident)*))+) => {
+        $(
+            #[allow(non_snake_case)]
+            impl<$($t: WasmType),*> WasmTypeList for ($($t,)*) {
+                fn types() -> Vec<ValueType> {
+                    vec![$($t::value_type()),*]
+                }
+
+                fn from_values(values: &[Value]) -> Option<Self> {
+                    match values {
+                        [$($t),*] => Some(($($t::from_value(*$t)?,)*)),
+                        _ => None,
+                    }
+                }
+
+                fn into_values(self) -> Vec<Value> {
+                    let ($($t,)*) = self;
+                    vec![$($t.into_value()),*]
+                }
+            }
+        )+
+    };
+}
+
+wasm_type_lists! {
+    ()
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+    (A B C D E F G)
+    (A B C D E F G H)
+}
@@ -0,0 +1,183 @@
+/// Modules for tests which compare different paths through the crate, such as
+/// the binary encoder and the native text parser against the `wat` crate.
+pub(crate) const MODULES: &[&str] = &[
+    r#"(module)"#,
+    r#"(module (func (param i32) (param i32) (result i32) (local f64) (local f64) local.get 0 local.get 1 i32.add))"#,
+    r#"(module (table 1 funcref) (func $f0) (elem (i32.const 0) func $f0))"#,
+    r#"(module (func $f0) (elem func $f0))"#,
+    r#"(module  (table $t0 1 funcref) (table $t1 1 funcref) (func $f0) (elem 1 (i32.const 0) func $f0))"#,
+    r#"(module (func $f0) (elem declare func $f0))"#,
+    r#"(module (table 1 funcref) (func $f0) (func) (elem (i32.const 0) funcref (ref.func 0)))"#,
+    r#"(module (func $f0) (elem funcref (ref.func 0)))"#,
+    r#"(module  (table $t0 1 funcref) (table $t1 1 funcref) (func $f0) (elem 1 (i32.const 0) funcref (ref.func 0)))"#,
+    r#"(module (func $f0) (elem declare funcref (ref.func 0)))"#,
+    r#"(module (global i32 (i32.const 11)))"#,
+    r#"(module (func (export "the_answer") (result i32) i32.const 42))"#,
+    r#"(module (func (param $l i32) (result i32) local.get $l))"#,
+    r#"(module (global i32 (i32.const 32)))"#,
+    r#"(module (import "console" "log" (func $log (param i32))))"#,
+    r#"(module (memory 100))"#,
+    r#"(module
+  (import "console" "log" (func $log (param i32)))
+  (import "host" "table" (table  1 10 funcref))
+  (import "host" "memory" (memory 1 2))
+  (global (import "host" "global") i64))
+"#,
+    r#"(module (func (result i32) (block (result i32) (loop (result i32) (i32.const 11)))))"#,
+    r#"(module (func) (start 0))"#,
+    r#"(module (table 1 10 funcref))"#,
+    r#"(module (type (func (param i32 i32) (result i64))))"#,
+    r#"
+(module
+  (func $add (param $lhs i32) (param $rhs i32) (result i32)
+    local.get $lhs
+    local.get $rhs
+    i32.add)
+  (export "add" (func $add))
+)
+"#,
+    r#"
+(module
+  (type $t (func (param i32) (result i32)))
+  (func (param i32) (result i32)
+    (block (result i32)
+      (loop (result i32)
+        (if (result i32) (local.get 0)
+          (then (i32.const 1))
+          (else (i32.const 11)))))
+    (block (type $t))
+    (if (local.get 0) (then))
+    (local.get 0)
+    i32.add)
+)
+"#,
+    r#"
+(module
+  (type $binop (func (param i32 i32) (result i32)))
+  (table 2 funcref)
+  (func $add (type $binop) local.get 0 local.get 1 i32.add)
+  (func (param i32) (result i32)
+    nop
+    (block $outer (result i32)
+      (block $inner
+        (br_table $inner $inner (local.get 0)))
+      (br_if $outer (i32.const 1) (local.get 0))
+      (call $add (i32.const 2) (i32.const 3))
+      (call_indirect (type $binop) (i32.const 4) (i32.const 5) (i32.const 0))
+      i32.add
+      return)))
+"#,
+    r#"(module (memory 1 10) (data (i32.const 0) "0") (data "1"))"#,
+    r#"(module (table $t0 1 funcref) (table $t1 1 funcref) (func $f0) (elem 1 (i32.const 0) func $f0))"#,
+    r#"(module (table $t0 1 funcref) (table $t1 1 funcref) (func $f0) (elem 1 (i32.const 0) funcref (ref.func 0)))"#,
+    r#"(module (import "env" "f" (func)) (func $broken (result i32) i64.const 0))"#,
+    r#"(module (import "env" "f" (func)) (func  (result i32) i64.const 0))"#,
+    r#"(module (global funcref ref.func 0) (func (param i32) (param i32) (result i32) local.get 0 local.get 1 i32.add))"#,
+    r#"(module (global i32 i32.const 0 i32.const 1 i32.add) (func (param i32) (param i32) (result i32) local.get 0 local.get 1 i32.add))"#,
+    r#"(module (import "host" "memory" (memory 1 10)) (import "host" "table" (table 10 11 funcref)) (import "host" "func" (func (param i32))) (import "host" "global" (global (mut f64))))"#,
+    r#"
+(module
+  (func $add (param $lhs i32) (param $rhs i64) (result i32)
+    local.get $lhs
+    local.get $rhs
+    i32.add)
+  (export "add" (func $add))
+)
+"#,
+    r#"(module (func (result i64) (block (result i64) (i32.const 0))))"#,
+    r#"(module (func br 1))"#,
+    r#"(module (type (func)) (func (call_indirect (type 0) (i32.const 0))) (table 0 externref))"#,
+    r#"(module (func (result funcref) ref.func 0))"#,
+    r#"(module (import "host" "log" (func $host (param i32))) (func $refrunf (result funcref) ref.func 0))"#,
+    r#"(module (func (result i32) (if (result i32) (i32.const 0) (then (i32.const 1)))))"#,
+    r#"(module (func (result i32) (i32.load (i32.const 0))))"#,
+    r#"(module (memory 1) (func (result i32) (i32.load16_u align=4 (i32.const 0))))"#,
+    r#"(module (memory 10 100))"#,
+    r#"(module (func (param f32) (result i64) (i64.trunc_f64_u (local.get 0))))"#,
+    r#"(module (func (param f64) (result i32) (i32.trunc_sat_f32_s (local.get 0))))"#,
+    r#"(module (start 0) (func (param i32) (param i32) (result i32) local.get 0 local.get 1 i32.add))"#,
+    r#"(module (start 1) (func (param i32) (param i32) (result i32) local.get 0 local.get 1 i32.add))"#,
+    r#"(module (table 1 funcref) (table 1 externref) (func (table.copy 0 1 (i32.const 0) (i32.const 0) (i32.const 0))))"#,
+    r#"(module (table 1 externref) (elem funcref) (func (table.init 0 0 (i32.const 0) (i32.const 0) (i32.const 0))))"#,
+    r#"(module (func (param externref) (result externref) (select (local.get 0) (local.get 0) (i32.const 0))))"#,
+    r#"(module (func (result i32) (select (i32.const 0) (i64.const 0) (i32.const 0))))"#,
+    r#"(module (memory 1) (data "x") (func (data.drop 0)))"#,
+    r#"
+(module
+  (memory 1)
+  (func (param i32) (result i64)
+    (i64.store8 offset=3 (local.get 0) (i64.const 255))
+    (f64.store align=4 (i32.const 8) (f64.const 1.5))
+    (i64.store (memory.grow (i32.const 1)) (i64.const 0))
+    (i64.add
+      (i64.load32_u (memory.size))
+      (i64.load align=8 (local.get 0)))))
+"#,
+    r#"
+(module
+  (func (param i32 i64 f32 f64) (result i32)
+    (i64.lt_u (i64.rotl (local.get 1) (i64.extend_i32_s (i32.popcnt (local.get 0))))
+              (i64.trunc_f64_s (f64.copysign (local.get 3) (f64.promote_f32 (local.get 2)))))
+    (i32.reinterpret_f32 (f32.sqrt (f32.demote_f64 (f64.convert_i32_u (local.get 0)))))
+    i32.xor))
+"#,
+    r#"
+(module
+  (func (param i32 externref) (result i64 externref)
+    (drop (f32.const 1))
+    (select (i64.const 1) (i64.const 2) (local.get 0))
+    (select (result externref) (local.get 1) (ref.null extern) (local.get 0)))
+  (func (result i32)
+    unreachable
+    select
+    (ref.is_null)))
+"#,
+    r#"
+(module
+  (func (param f32 f64) (result i32 i64)
+    (i32.extend8_s (i32.trunc_sat_f32_u (local.get 0)))
+    (i64.extend32_s (i64.trunc_sat_f64_s (local.get 1)))))
+"#,
+    r#"(module (start 0) (func))"#,
+    r#"
+(module (start 2)
+(import "f" "f" (func (param i32) (param i32) (result i32)))
+(func (param i32) (param i32) (result i32) local.get 0 local.get 1 i32.add)
+(func))
+"#,
+    r#"
+(module
+  (table $t0 1 funcref)
+  (table $t1 1 funcref)
+  (table $ext 0 externref)
+  (memory 1)
+  (elem $e funcref (ref.func $f))
+  (data $d "hello")
+  (func $f (param externref)
+    (table.init $t1 $e (i32.const 0) (i32.const 0) (i32.const 1))
+    (elem.drop $e)
+    (table.copy $t0 $t1 (i32.const 0) (i32.const 0) (i32.const 1))
+    (table.set $t0 (i32.const 0) (table.get $t1 (i32.const 0)))
+    (drop (table.grow $ext (local.get 0) (table.size $ext)))
+    (table.fill $ext (i32.const 0) (local.get 0) (i32.const 0))
+    (memory.init $d (i32.const 0) (i32.const 0) (i32.const 5))
+    (data.drop $d)
+    (memory.copy (i32.const 8) (i32.const 0) (i32.const 5))
+    (memory.fill (i32.const 0) (i32.const 0) (i32.const 16))))
+"#,
+    r#"(module (func (result i32) unreachable i32.add) (func (result i64) (block (br 0)) unreachable))"#,
+    r#"(module (import "host" "log" (func $host (param i32))) (elem declare funcref (ref.func 1)) (func $self (result funcref) ref.func 1))"#,
+    r#"(module (import "host" "log" (func $host (param i32))) (elem func 1) (func $self (result funcref) ref.func 1))"#,
+    r#"(module (export "self" (func 0)) (func (result funcref) ref.func 0))"#,
+    r#"(module (global funcref(ref.func 0)) (func $self (result funcref) ref.func 0))"#,
+    r#"
+(module
+  (memory 1)
+  (func (param i32) (result i32 v128)
+    (i32x4.extract_lane 3
+      (i32x4.add (v128.const i32x4 1 2 3 4) (i32x4.splat (local.get 0))))
+    (i8x16.shuffle 0 17 2 19 4 21 6 23 8 25 10 27 12 29 14 31
+      (v128.load offset=16 (i32.const 0))
+      (v128.load32_lane 1 (i32.const 0) (v128.const i64x2 0 0)))))
+"#,
+];
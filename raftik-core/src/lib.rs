@@ -1,3 +1,8 @@
 pub mod ast;
-mod binary;
+pub mod binary;
+pub mod builder;
+pub mod exec;
+#[cfg(test)]
+mod fixtures;
+pub mod text;
 pub mod validation;
@@ -0,0 +1,8 @@
+pub mod error;
+mod lexer;
+mod parser;
+mod printer;
+mod sexpr;
+
+pub use parser::{parse_wat, wat_to_bytes};
+pub use printer::print_module;
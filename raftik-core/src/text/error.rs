@@ -0,0 +1,106 @@
+use std::fmt;
+
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum WatErrorKind {
+    #[error("unexpected character {0:?}")]
+    UnexpectedChar(char),
+
+    #[error("unterminated string")]
+    UnterminatedString,
+
+    #[error("unterminated block comment")]
+    UnterminatedComment,
+
+    #[error("invalid escape sequence in string")]
+    InvalidEscape,
+
+    #[error("unmatched `)`")]
+    UnmatchedCloseParen,
+
+    #[error("unclosed `(`")]
+    UnclosedParen,
+
+    #[error("expected {0}")]
+    Expected(&'static str),
+
+    #[error("unexpected token")]
+    UnexpectedToken,
+
+    #[error("unknown instruction `{0}`")]
+    UnknownInstruction(String),
+
+    #[error("unknown identifier ${0}")]
+    UnknownId(String),
+
+    #[error("duplicate identifier ${0}")]
+    DuplicateId(String),
+
+    #[error("invalid or out-of-range number `{0}`")]
+    InvalidNumber(String),
+
+    #[error("alignment {0} is not a power of two")]
+    InvalidAlignment(u32),
+
+    #[error("invalid UTF-8 in name")]
+    InvalidUtf8,
+
+    #[error("unknown type {0}")]
+    UnknownType(u32),
+
+    #[error("inline function type does not match type {0}")]
+    TypeMismatch(u32),
+
+    #[error("import after a function, table, memory or global definition")]
+    ImportAfterDefinition,
+
+    #[error("label ${0} does not match the enclosing block")]
+    MismatchedLabel(String),
+
+    #[error("multiple start functions")]
+    MultipleStart,
+
+    #[error("lists are nested too deeply")]
+    NestingTooDeep,
+}
+
+/// Error returned by [`crate::text::parse_wat`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatError {
+    pub kind: WatErrorKind,
+    /// Byte offset in the source text where parsing failed.
+    pub offset: usize,
+    /// 1-based line of `offset`.
+    pub line: usize,
+    /// 1-based column of `offset`, counted in characters.
+    pub column: usize,
+}
+
+impl WatError {
+    /// An error at `offset` whose line and column are filled in by [`WatError::locate`].
+    pub(crate) fn new(kind: WatErrorKind, offset: usize) -> Self {
+        Self {
+            kind,
+            offset,
+            line: 0,
+            column: 0,
+        }
+    }
+
+    pub(crate) fn locate(mut self, source: &str) -> Self {
+        let before = &source[..self.offset.min(source.len())];
+        self.line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        self.column = before[line_start..].chars().count() + 1;
+        self
+    }
+}
+
+impl fmt::Display for WatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error at {}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for WatError {}
@@ -0,0 +1,186 @@
+use super::error::{WatError, WatErrorKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Token<'a> {
+    LParen,
+    RParen,
+    /// Keywords, numbers and other reserved words, e.g. `i32.add`, `0x10` or `offset=4`.
+    Keyword(&'a str),
+    /// A `$name`, without the `$`.
+    Id(&'a str),
+    /// String contents with escapes resolved; strings are not required to be UTF-8.
+    String(Vec<u8>),
+}
+
+/// Splits `text` into tokens paired with their byte offsets, skipping whitespace and comments.
+pub(super) fn tokenize(text: &str) -> Result<Vec<(usize, Token<'_>)>, WatError> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let start = pos;
+        match bytes[pos] {
+            b' ' | b'\t' | b'\n' | b'\r' => pos += 1,
+            b';' if bytes.get(pos + 1) == Some(&b';') => {
+                pos = text[pos..].find('\n').map_or(bytes.len(), |i| pos + i);
+            }
+            b'(' if bytes.get(pos + 1) == Some(&b';') => pos = block_comment(bytes, pos)?,
+            b'(' => {
+                tokens.push((start, Token::LParen));
+                pos += 1;
+            }
+            b')' => {
+                tokens.push((start, Token::RParen));
+                pos += 1;
+            }
+            b'"' => {
+                let (end, string) = string(text, pos)?;
+                tokens.push((start, Token::String(string)));
+                pos = end;
+            }
+            b if is_id_char(b) => {
+                while pos < bytes.len() && is_id_char(bytes[pos]) {
+                    pos += 1;
+                }
+                let word = &text[start..pos];
+                match word.strip_prefix('$') {
+                    Some("") => {
+                        return Err(WatError::new(WatErrorKind::Expected("identifier"), start));
+                    }
+                    Some(id) => tokens.push((start, Token::Id(id))),
+                    None => tokens.push((start, Token::Keyword(word))),
+                }
+            }
+            _ => {
+                let c = text[pos..].chars().next().unwrap();
+                return Err(WatError::new(WatErrorKind::UnexpectedChar(c), start));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_id_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-./:<=>?@\\^_`|~".contains(&b)
+}
+
+/// Skips a possibly nested `(; ... ;)` comment starting at `start`, returning the position after it.
+fn block_comment(bytes: &[u8], start: usize) -> Result<usize, WatError> {
+    let mut depth = 0;
+    let mut pos = start;
+    while pos + 1 < bytes.len() {
+        match &bytes[pos..pos + 2] {
+            b"(;" => {
+                depth += 1;
+                pos += 2;
+            }
+            b";)" => {
+                depth -= 1;
+                pos += 2;
+                if depth == 0 {
+                    return Ok(pos);
+                }
+            }
+            _ => pos += 1,
+        }
+    }
+    Err(WatError::new(WatErrorKind::UnterminatedComment, start))
+}
+
+/// Reads the string starting at the `"` at `start`, returning the position after the closing quote.
+fn string(text: &str, start: usize) -> Result<(usize, Vec<u8>), WatError> {
+    let mut out = Vec::new();
+    let mut pos = start + 1;
+    while let Some(c) = text[pos..].chars().next() {
+        let at = pos;
+        pos += c.len_utf8();
+        match c {
+            '"' => return Ok((pos, out)),
+            '\\' => {
+                let invalid = || WatError::new(WatErrorKind::InvalidEscape, at);
+                let escape = text[pos..].chars().next().ok_or_else(invalid)?;
+                pos += escape.len_utf8();
+                match escape {
+                    't' => out.push(b'\t'),
+                    'n' => out.push(b'\n'),
+                    'r' => out.push(b'\r'),
+                    '"' => out.push(b'"'),
+                    '\'' => out.push(b'\''),
+                    '\\' => out.push(b'\\'),
+                    'u' => {
+                        let (hex, _) = text[pos..]
+                            .strip_prefix('{')
+                            .and_then(|rest| rest.split_once('}'))
+                            .ok_or_else(invalid)?;
+                        let code = u32::from_str_radix(&hex.replace('_', ""), 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(invalid)?;
+                        pos += hex.len() + 2;
+                        let mut buf = [0; 4];
+                        out.extend_from_slice(code.encode_utf8(&mut buf).as_bytes());
+                    }
+                    hi if hi.is_ascii_hexdigit() => {
+                        let lo = text[pos..]
+                            .chars()
+                            .next()
+                            .filter(char::is_ascii_hexdigit)
+                            .ok_or_else(invalid)?;
+                        pos += 1;
+                        let byte = (hi.to_digit(16).unwrap() << 4) | lo.to_digit(16).unwrap();
+                        out.push(byte as u8);
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            c if (c as u32) < 0x20 || c == '\u{7f}' => {
+                return Err(WatError::new(WatErrorKind::UnexpectedChar(c), at));
+            }
+            c => {
+                let mut buf = [0; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    Err(WatError::new(WatErrorKind::UnterminatedString, start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize(
+            "(module ;; comment\n (; nested (; block ;) comment ;) $id i32.load offset=4 \"a\\n\\00\\u{e9}\")",
+        )
+        .unwrap();
+        let tokens: Vec<_> = tokens.into_iter().map(|(_, t)| t).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::LParen,
+                Token::Keyword("module"),
+                Token::Id("id"),
+                Token::Keyword("i32.load"),
+                Token::Keyword("offset=4"),
+                Token::String(b"a\n\0\xc3\xa9".to_vec()),
+                Token::RParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_errors() {
+        let cases = [
+            ("(module \"abc", WatErrorKind::UnterminatedString, 8),
+            ("(; (; ;)", WatErrorKind::UnterminatedComment, 0),
+            ("\"\\q\"", WatErrorKind::InvalidEscape, 1),
+            ("(func [", WatErrorKind::UnexpectedChar('['), 6),
+        ];
+        for (text, kind, offset) in cases {
+            let error = tokenize(text).unwrap_err();
+            assert_eq!((error.kind, error.offset), (kind, offset), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,1126 @@
+mod instructions;
+mod number;
+
+use std::collections::HashMap;
+
+use instructions::ExpressionParser;
+use number::parse_u32;
+
+use super::{
+    error::{WatError, WatErrorKind},
+    lexer::{Token, tokenize},
+    sexpr::{SExpr, parse_sexprs},
+};
+use crate::{
+    ast::{
+        ModuleOwned,
+        instructions::{BlockType, Opcode, RawExpression},
+        section::{Element, ElementItems, ElementKind, ExportDesc, ImportDesc},
+        types::{
+            FunctionType, GlobalType, Limits, MemoryType, Mutability, NumberType, ReferenceType,
+            TableType, ValueType, VectorType,
+        },
+    },
+    builder::ModuleBuilder,
+};
+
+type WatResult<T> = Result<T, WatError>;
+
+/// Parses a module in the WebAssembly text format, resolving `$id`s to indices.
+/// No `name` custom section is generated for the ids.
+pub fn parse_wat(text: &str) -> Result<ModuleOwned, WatError> {
+    parse(text).map_err(|e| e.locate(text))
+}
+
+/// Parses a module in the WebAssembly text format and encodes it in the binary format.
+pub fn wat_to_bytes(text: &str) -> Result<Vec<u8>, WatError> {
+    parse_wat(text).map(|module| module.to_bytes())
+}
+
+fn parse(text: &str) -> WatResult<ModuleOwned> {
+    let sexprs = parse_sexprs(tokenize(text)?)?;
+    let fields = module_fields(&sexprs, text.len());
+    let mut module = TextModule::default();
+    module.declare(fields.clone())?;
+    module.define(fields)?;
+    Ok(module.builder.build())
+}
+
+/// The fields of `(module $id? field*)`, or of a bare sequence of fields.
+fn module_fields<'s, 'a>(sexprs: &'s [SExpr<'a>], text_end: usize) -> Cursor<'s, 'a> {
+    if let [SExpr::List { items, end, .. }] = sexprs {
+        let mut cursor = Cursor::new(items, *end);
+        if cursor.keyword_if("module") {
+            cursor.id();
+            return cursor;
+        }
+    }
+    Cursor::new(sexprs, text_end)
+}
+
+/// Reads the items of a list from left to right.
+#[derive(Debug, Clone)]
+struct Cursor<'s, 'a> {
+    items: &'s [SExpr<'a>],
+    /// Offset of the closing parenthesis, used for errors once all items are read.
+    end: usize,
+}
+
+impl<'s, 'a> Cursor<'s, 'a> {
+    fn new(items: &'s [SExpr<'a>], end: usize) -> Self {
+        Self { items, end }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Offset of the next item, or of the closing parenthesis.
+    fn offset(&self) -> usize {
+        self.items.first().map_or(self.end, SExpr::offset)
+    }
+
+    fn error(&self, kind: WatErrorKind) -> WatError {
+        WatError::new(kind, self.offset())
+    }
+
+    fn advance(&mut self) {
+        self.items = &self.items[1..];
+    }
+
+    fn peek_token(&self) -> Option<&'s Token<'a>> {
+        match self.items.first() {
+            Some(SExpr::Atom { token, .. }) => Some(token),
+            _ => None,
+        }
+    }
+
+    fn peek_keyword(&self) -> Option<&'a str> {
+        match self.peek_token() {
+            Some(Token::Keyword(keyword)) => Some(keyword),
+            _ => None,
+        }
+    }
+
+    fn peek_number(&self) -> bool {
+        self.peek_keyword().is_some_and(is_number)
+    }
+
+    fn keyword(&mut self) -> Option<&'a str> {
+        let keyword = self.peek_keyword()?;
+        self.advance();
+        Some(keyword)
+    }
+
+    fn expect_keyword(&mut self, what: &'static str) -> WatResult<&'a str> {
+        self.keyword()
+            .ok_or_else(|| self.error(WatErrorKind::Expected(what)))
+    }
+
+    /// Consumes the next item if it is `keyword`.
+    fn keyword_if(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword() == Some(keyword);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn id(&mut self) -> Option<&'a str> {
+        match self.peek_token() {
+            Some(Token::Id(id)) => {
+                self.advance();
+                Some(id)
+            }
+            _ => None,
+        }
+    }
+
+    fn string(&mut self) -> Option<&'s [u8]> {
+        match self.peek_token() {
+            Some(Token::String(bytes)) => {
+                self.advance();
+                Some(bytes)
+            }
+            _ => None,
+        }
+    }
+
+    /// A string which must be valid UTF-8, as used for import and export names.
+    fn name(&mut self) -> WatResult<String> {
+        let offset = self.offset();
+        let bytes = self
+            .string()
+            .ok_or_else(|| self.error(WatErrorKind::Expected("string")))?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| WatError::new(WatErrorKind::InvalidUtf8, offset))
+    }
+
+    /// Concatenates the remaining items, which must all be strings.
+    fn strings(&mut self) -> WatResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        while !self.is_empty() {
+            let string = self
+                .string()
+                .ok_or_else(|| self.error(WatErrorKind::Expected("string")))?;
+            bytes.extend_from_slice(string);
+        }
+        Ok(bytes)
+    }
+
+    fn number<T>(&mut self, parse: impl Fn(&str) -> Option<T>) -> WatResult<T> {
+        let offset = self.offset();
+        let text = self.expect_keyword("number")?;
+        parse(text)
+            .ok_or_else(|| WatError::new(WatErrorKind::InvalidNumber(text.to_string()), offset))
+    }
+
+    fn u32(&mut self) -> WatResult<u32> {
+        self.number(parse_u32)
+    }
+
+    /// Consumes an index if the next item is a `$id` or an integer.
+    fn index(&mut self) -> WatResult<Option<Index<'a>>> {
+        let offset = self.offset();
+        if let Some(id) = self.id() {
+            return Ok(Some(Index::Id(id, offset)));
+        }
+        if self.peek_number() {
+            return self.u32().map(|n| Some(Index::Num(n)));
+        }
+        Ok(None)
+    }
+
+    /// The keyword at the start of the next item, if that item is a list.
+    fn peek_list_head(&self) -> Option<&'a str> {
+        match self.items.first() {
+            Some(SExpr::List { items, .. }) => match items.first() {
+                Some(SExpr::Atom {
+                    token: Token::Keyword(keyword),
+                    ..
+                }) => Some(keyword),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Consumes the next item if it is a list starting with `head`,
+    /// returning a cursor positioned after `head`.
+    fn list(&mut self, head: &str) -> Option<Cursor<'s, 'a>> {
+        if self.peek_list_head() != Some(head) {
+            return None;
+        }
+        let mut list = self.any_list()?;
+        list.advance();
+        Some(list)
+    }
+
+    fn any_list(&mut self) -> Option<Cursor<'s, 'a>> {
+        match self.items.first() {
+            Some(SExpr::List { items, end, .. }) => {
+                self.advance();
+                Some(Cursor::new(items, *end))
+            }
+            _ => None,
+        }
+    }
+
+    /// Fails unless all items have been read.
+    fn finish(&self) -> WatResult<()> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self.error(WatErrorKind::UnexpectedToken))
+        }
+    }
+}
+
+fn is_number(keyword: &str) -> bool {
+    keyword.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// A reference to an item, either numeric or by `$id` together with the id's offset.
+#[derive(Debug, Clone, Copy)]
+enum Index<'a> {
+    Num(u32),
+    Id(&'a str, usize),
+}
+
+/// The `$id`s of one index space.
+#[derive(Debug, Default)]
+struct Space<'a> {
+    ids: HashMap<&'a str, u32>,
+    len: u32,
+}
+
+impl<'a> Space<'a> {
+    fn push(&mut self, id: Option<&'a str>, offset: usize) -> WatResult<u32> {
+        let index = self.len;
+        if let Some(id) = id
+            && self.ids.insert(id, index).is_some()
+        {
+            return Err(WatError::new(
+                WatErrorKind::DuplicateId(id.to_string()),
+                offset,
+            ));
+        }
+        self.len += 1;
+        Ok(index)
+    }
+
+    fn resolve(&self, index: Index<'a>) -> WatResult<u32> {
+        match index {
+            Index::Num(n) => Ok(n),
+            Index::Id(id, offset) => self
+                .ids
+                .get(id)
+                .copied()
+                .ok_or_else(|| WatError::new(WatErrorKind::UnknownId(id.to_string()), offset)),
+        }
+    }
+
+    fn parse(&self, cursor: &mut Cursor<'_, 'a>) -> WatResult<u32> {
+        let index = cursor
+            .index()?
+            .ok_or_else(|| cursor.error(WatErrorKind::Expected("index")))?;
+        self.resolve(index)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Ids<'a> {
+    types: Space<'a>,
+    functions: Space<'a>,
+    tables: Space<'a>,
+    memories: Space<'a>,
+    globals: Space<'a>,
+    elements: Space<'a>,
+    data: Space<'a>,
+}
+
+impl<'a> Ids<'a> {
+    fn space(&self, kind: ItemKind) -> &Space<'a> {
+        match kind {
+            ItemKind::Function => &self.functions,
+            ItemKind::Table => &self.tables,
+            ItemKind::Memory => &self.memories,
+            ItemKind::Global => &self.globals,
+        }
+    }
+
+    fn space_mut(&mut self, kind: ItemKind) -> &mut Space<'a> {
+        match kind {
+            ItemKind::Function => &mut self.functions,
+            ItemKind::Table => &mut self.tables,
+            ItemKind::Memory => &mut self.memories,
+            ItemKind::Global => &mut self.globals,
+        }
+    }
+}
+
+/// The kinds of items which can be imported and exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemKind {
+    Function,
+    Table,
+    Memory,
+    Global,
+}
+
+impl ItemKind {
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "func" => Some(ItemKind::Function),
+            "table" => Some(ItemKind::Table),
+            "memory" => Some(ItemKind::Memory),
+            "global" => Some(ItemKind::Global),
+            _ => None,
+        }
+    }
+
+    fn export_desc(self, index: u32) -> ExportDesc {
+        match self {
+            ItemKind::Function => ExportDesc::FunctionIndex(index),
+            ItemKind::Table => ExportDesc::TableIndex(index),
+            ItemKind::Memory => ExportDesc::MemoryIndex(index),
+            ItemKind::Global => ExportDesc::GlobalIndex(index),
+        }
+    }
+}
+
+/// Ids of function parameters or locals, with the offset of each id.
+type LocalIds<'a> = Vec<Option<(&'a str, usize)>>;
+
+/// The module being lowered. Fields are read twice: [`TextModule::declare`] collects
+/// the `$id`s and explicit types, so that [`TextModule::define`] can resolve forward references.
+#[derive(Debug, Default)]
+struct TextModule<'a> {
+    ids: Ids<'a>,
+    /// Explicit types come first; types of inline type uses are added as they are found.
+    builder: ModuleBuilder,
+    has_start: bool,
+    /// Set by the first definition of a function, table, memory or global;
+    /// imports are not allowed afterwards.
+    defined: bool,
+}
+
+/// Consumes the next module field, returning its contents after the leading keyword.
+fn next_field<'s, 'a>(
+    fields: &mut Cursor<'s, 'a>,
+) -> WatResult<Option<(Cursor<'s, 'a>, &'a str, usize)>> {
+    if fields.is_empty() {
+        return Ok(None);
+    }
+    let offset = fields.offset();
+    let mut field = fields
+        .any_list()
+        .ok_or_else(|| fields.error(WatErrorKind::Expected("module field")))?;
+    let keyword = field.expect_keyword("module field")?;
+    Ok(Some((field, keyword, offset)))
+}
+
+impl<'a> TextModule<'a> {
+    fn declare(&mut self, mut fields: Cursor<'_, 'a>) -> WatResult<()> {
+        while let Some((mut field, keyword, offset)) = next_field(&mut fields)? {
+            let id_offset = field.offset();
+            match keyword {
+                "type" => {
+                    let id = field.id();
+                    self.ids.types.push(id, id_offset)?;
+                    let mut func = field
+                        .list("func")
+                        .ok_or_else(|| field.error(WatErrorKind::Expected("(func ...)")))?;
+                    let (ty, _) = signature(&mut func)?;
+                    func.finish()?;
+                    field.finish()?;
+                    self.builder
+                        .push_type(ty.unwrap_or_else(empty_function_type));
+                }
+                "import" => {
+                    field.string();
+                    field.string();
+                    if let Some(mut desc) = field.any_list()
+                        && let Some(kind) = desc.keyword().and_then(ItemKind::from_keyword)
+                    {
+                        let id_offset = desc.offset();
+                        let id = desc.id();
+                        self.ids.space_mut(kind).push(id, id_offset)?;
+                    }
+                }
+                "func" | "table" | "memory" | "global" => {
+                    let kind = ItemKind::from_keyword(keyword).unwrap();
+                    let id = field.id();
+                    self.ids.space_mut(kind).push(id, id_offset)?;
+                    while field.list("export").is_some() {}
+                    // inline segments take the next segment index
+                    if kind == ItemKind::Table
+                        && field.keyword().and_then(reference_type).is_some()
+                        && field.peek_list_head() == Some("elem")
+                    {
+                        self.ids.elements.push(None, id_offset)?;
+                    }
+                    if kind == ItemKind::Memory && field.list("data").is_some() {
+                        self.ids.data.push(None, id_offset)?;
+                    }
+                }
+                "elem" => {
+                    let id = field.id();
+                    self.ids.elements.push(id, id_offset)?;
+                }
+                "data" => {
+                    let id = field.id();
+                    self.ids.data.push(id, id_offset)?;
+                }
+                "export" | "start" => (),
+                _ => {
+                    return Err(WatError::new(
+                        WatErrorKind::Expected("module field"),
+                        offset,
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn define(&mut self, mut fields: Cursor<'_, 'a>) -> WatResult<()> {
+        while let Some((mut field, keyword, offset)) = next_field(&mut fields)? {
+            match keyword {
+                // types are complete after `declare`
+                "type" => (),
+                "import" => {
+                    let module = field.name()?;
+                    let name = field.name()?;
+                    let expected = field.error(WatErrorKind::Expected("import description"));
+                    let mut desc = field.any_list().ok_or_else(|| expected.clone())?;
+                    let kind = desc
+                        .keyword()
+                        .and_then(ItemKind::from_keyword)
+                        .ok_or(expected)?;
+                    desc.id();
+                    let desc = self.import_desc(kind, &mut desc)?;
+                    field.finish()?;
+                    self.add_import(module, name, desc, offset)?;
+                }
+                "func" => self.function(field, offset)?,
+                "table" => self.table(field, offset)?,
+                "memory" => self.memory(field, offset)?,
+                "global" => self.global(field, offset)?,
+                "export" => {
+                    let name = field.name()?;
+                    let expected = field.error(WatErrorKind::Expected("export description"));
+                    let mut desc = field.any_list().ok_or_else(|| expected.clone())?;
+                    let kind = desc
+                        .keyword()
+                        .and_then(ItemKind::from_keyword)
+                        .ok_or(expected)?;
+                    let index = self.ids.space(kind).parse(&mut desc)?;
+                    desc.finish()?;
+                    field.finish()?;
+                    self.builder.export(&name, kind.export_desc(index));
+                }
+                "start" => {
+                    let index = self.ids.functions.parse(&mut field)?;
+                    field.finish()?;
+                    if self.has_start {
+                        return Err(WatError::new(WatErrorKind::MultipleStart, offset));
+                    }
+                    self.has_start = true;
+                    self.builder.set_start(index);
+                }
+                "elem" => self.element(field)?,
+                "data" => self.data(field)?,
+                _ => unreachable!("unknown fields are rejected by `declare`"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of items of `kind` so far, which is the index of the next one.
+    fn count(&self, kind: ItemKind) -> u32 {
+        match kind {
+            ItemKind::Function => self.builder.function_count(),
+            ItemKind::Table => self.builder.table_count(),
+            ItemKind::Memory => self.builder.memory_count(),
+            ItemKind::Global => self.builder.global_count(),
+        }
+    }
+
+    fn add_import(
+        &mut self,
+        module: String,
+        name: String,
+        desc: ImportDesc,
+        offset: usize,
+    ) -> WatResult<()> {
+        if self.defined {
+            return Err(WatError::new(WatErrorKind::ImportAfterDefinition, offset));
+        }
+        self.builder.add_import(&module, &name, desc);
+        Ok(())
+    }
+
+    fn import_desc(
+        &mut self,
+        kind: ItemKind,
+        cursor: &mut Cursor<'_, 'a>,
+    ) -> WatResult<ImportDesc> {
+        let desc = match kind {
+            ItemKind::Function => ImportDesc::TypeIndex(self.type_use(cursor)?.0),
+            ItemKind::Table => ImportDesc::Table(table_type(cursor)?),
+            ItemKind::Memory => ImportDesc::Memory(memory_type(cursor)?),
+            ItemKind::Global => ImportDesc::Global(global_type(cursor)?),
+        };
+        cursor.finish()?;
+        Ok(desc)
+    }
+
+    /// Handles the `(export "name")*` and `(import "module" "name")?` abbreviations
+    /// which may follow the id of a function, table, memory or global.
+    /// Returns true if the item was imported, in which case the field has been read completely.
+    fn inline_import_export(
+        &mut self,
+        kind: ItemKind,
+        field: &mut Cursor<'_, 'a>,
+        offset: usize,
+    ) -> WatResult<bool> {
+        let index = self.count(kind);
+        while let Some(mut export) = field.list("export") {
+            let name = export.name()?;
+            export.finish()?;
+            self.builder.export(&name, kind.export_desc(index));
+        }
+        let Some(mut import) = field.list("import") else {
+            self.defined = true;
+            return Ok(false);
+        };
+        let module = import.name()?;
+        let name = import.name()?;
+        import.finish()?;
+        let desc = self.import_desc(kind, field)?;
+        self.add_import(module, name, desc, offset)?;
+        Ok(true)
+    }
+
+    fn function(&mut self, mut field: Cursor<'_, 'a>, offset: usize) -> WatResult<()> {
+        field.id();
+        if self.inline_import_export(ItemKind::Function, &mut field, offset)? {
+            return Ok(());
+        }
+        let (type_index, mut local_ids) = self.type_use(&mut field)?;
+        let mut locals = Vec::new();
+        while let Some(mut local) = field.list("local") {
+            let id_offset = local.offset();
+            if let Some(id) = local.id() {
+                locals.push(value_type(&mut local)?);
+                local_ids.push(Some((id, id_offset)));
+                local.finish()?;
+            } else {
+                while !local.is_empty() {
+                    locals.push(value_type(&mut local)?);
+                    local_ids.push(None);
+                }
+            }
+        }
+        let mut local_indices = HashMap::new();
+        for (index, (id, offset)) in local_ids
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, id)| Some((index as u32, id?)))
+        {
+            if local_indices.insert(id, index).is_some() {
+                return Err(WatError::new(
+                    WatErrorKind::DuplicateId(id.to_string()),
+                    offset,
+                ));
+            }
+        }
+        let opcodes = ExpressionParser::new(self, local_indices).parse(field)?;
+        self.builder
+            .add_function_with_type_index(type_index, &locals, &opcodes);
+        Ok(())
+    }
+
+    fn table(&mut self, mut field: Cursor<'_, 'a>, offset: usize) -> WatResult<()> {
+        let index = self.count(ItemKind::Table);
+        field.id();
+        if self.inline_import_export(ItemKind::Table, &mut field, offset)? {
+            return Ok(());
+        }
+        // `reftype (elem ...)` defines a table exactly as large as its elements
+        let Some(ref_type) = field.peek_keyword().and_then(reference_type) else {
+            self.builder.add_table(table_type(&mut field)?);
+            return field.finish();
+        };
+        field.advance();
+        let mut elem = field
+            .list("elem")
+            .ok_or_else(|| field.error(WatErrorKind::Expected("(elem ...)")))?;
+        field.finish()?;
+        let items = if elem.peek_list_head().is_some() {
+            self.element_expressions(&mut elem, ref_type)?
+        } else {
+            self.function_indices(&mut elem, ref_type)?
+        };
+        let len = match &items {
+            ElementItems::Functions(indices) => indices.len(),
+            ElementItems::Expressions(_, expressions) => expressions.len(),
+        } as u32;
+        self.builder.add_table(TableType {
+            ref_type,
+            limits: Limits {
+                min: len,
+                max: Some(len),
+            },
+        });
+        self.builder.add_element(Element {
+            kind: ElementKind::Active {
+                table_index: Some(index),
+                offset_expression: RawExpression::from_opcodes(&[Opcode::I32Const(0)]),
+            },
+            items,
+        });
+        Ok(())
+    }
+
+    fn memory(&mut self, mut field: Cursor<'_, 'a>, offset: usize) -> WatResult<()> {
+        let index = self.count(ItemKind::Memory);
+        field.id();
+        if self.inline_import_export(ItemKind::Memory, &mut field, offset)? {
+            return Ok(());
+        }
+        // `(data ...)` defines a memory exactly as large as its data
+        let Some(mut data) = field.list("data") else {
+            self.builder.add_memory(memory_type(&mut field)?);
+            return field.finish();
+        };
+        field.finish()?;
+        let bytes = data.strings()?;
+        let pages = bytes.len().div_ceil(1 << 16) as u32;
+        self.builder.add_memory(MemoryType {
+            limits: Limits {
+                min: pages,
+                max: Some(pages),
+            },
+        });
+        self.builder
+            .add_active_data(index, &[Opcode::I32Const(0)], bytes);
+        Ok(())
+    }
+
+    fn global(&mut self, mut field: Cursor<'_, 'a>, offset: usize) -> WatResult<()> {
+        field.id();
+        if self.inline_import_export(ItemKind::Global, &mut field, offset)? {
+            return Ok(());
+        }
+        let global_type = global_type(&mut field)?;
+        let init = self.const_expression(field)?;
+        self.builder.add_global(global_type, &init);
+        Ok(())
+    }
+
+    fn element(&mut self, mut field: Cursor<'_, 'a>) -> WatResult<()> {
+        field.id();
+        // without a table use, a bare list of function indices is accepted as in older text
+        let mut table_omitted = false;
+        let kind = if field.keyword_if("declare") {
+            ElementKind::Declarative
+        } else if field.peek_number() || field.peek_list_head().is_some() {
+            let table_index = if field.peek_number() {
+                table_omitted = true;
+                Some(field.u32()?)
+            } else if let Some(mut table) = field.list("table") {
+                let index = self.ids.tables.parse(&mut table)?;
+                table.finish()?;
+                Some(index)
+            } else {
+                table_omitted = true;
+                None
+            };
+            let offset = self.abbreviated_expression(&mut field, "offset")?;
+            ElementKind::Active {
+                table_index,
+                offset_expression: RawExpression::from_opcodes(&offset),
+            }
+        } else {
+            ElementKind::Passive
+        };
+        let items = if field.keyword_if("func") {
+            self.function_indices(&mut field, ReferenceType::FuncRef)?
+        } else if let Some(ref_type) = field.peek_keyword().and_then(reference_type) {
+            field.advance();
+            self.element_expressions(&mut field, ref_type)?
+        } else if table_omitted {
+            self.function_indices(&mut field, ReferenceType::FuncRef)?
+        } else {
+            return Err(field.error(WatErrorKind::Expected("element list")));
+        };
+        self.builder.add_element(Element { kind, items });
+        Ok(())
+    }
+
+    /// Reads function indices up to the end of `cursor`; other types than funcref
+    /// can only hold them as `ref.func` expressions.
+    fn function_indices(
+        &mut self,
+        cursor: &mut Cursor<'_, 'a>,
+        ref_type: ReferenceType,
+    ) -> WatResult<ElementItems<'static>> {
+        let mut indices = Vec::new();
+        while !cursor.is_empty() {
+            indices.push(self.ids.functions.parse(cursor)?);
+        }
+        Ok(match ref_type {
+            ReferenceType::FuncRef => ElementItems::Functions(indices),
+            ReferenceType::ExternRef => ElementItems::Expressions(
+                ref_type,
+                indices
+                    .into_iter()
+                    .map(|i| RawExpression::from_opcodes(&[Opcode::RefFunc(i)]))
+                    .collect(),
+            ),
+        })
+    }
+
+    fn element_expressions(
+        &mut self,
+        cursor: &mut Cursor<'_, 'a>,
+        ref_type: ReferenceType,
+    ) -> WatResult<ElementItems<'static>> {
+        let mut expressions = Vec::new();
+        while !cursor.is_empty() {
+            let item = self.abbreviated_expression(cursor, "item")?;
+            expressions.push(RawExpression::from_opcodes(&item));
+        }
+        Ok(ElementItems::Expressions(ref_type, expressions))
+    }
+
+    fn data(&mut self, mut field: Cursor<'_, 'a>) -> WatResult<()> {
+        field.id();
+        if field.is_empty() || matches!(field.peek_token(), Some(Token::String(_))) {
+            self.builder.add_passive_data(field.strings()?);
+            return Ok(());
+        }
+        let memory_index = if let Some(mut memory) = field.list("memory") {
+            let index = self.ids.memories.parse(&mut memory)?;
+            memory.finish()?;
+            index
+        } else if let Some(index) = field.index()? {
+            self.ids.memories.resolve(index)?
+        } else {
+            0
+        };
+        let offset = self.abbreviated_expression(&mut field, "offset")?;
+        self.builder
+            .add_active_data(memory_index, &offset, field.strings()?);
+        Ok(())
+    }
+
+    /// `(keyword instr*)`, or a single folded instruction standing for it.
+    fn abbreviated_expression(
+        &mut self,
+        cursor: &mut Cursor<'_, 'a>,
+        keyword: &str,
+    ) -> WatResult<Vec<Opcode>> {
+        if let Some(list) = cursor.list(keyword) {
+            return self.const_expression(list);
+        }
+        let list = cursor
+            .any_list()
+            .ok_or_else(|| cursor.error(WatErrorKind::Expected("expression")))?;
+        ExpressionParser::new(self, HashMap::new()).parse_folded(list)
+    }
+
+    fn const_expression(&mut self, cursor: Cursor<'_, 'a>) -> WatResult<Vec<Opcode>> {
+        ExpressionParser::new(self, HashMap::new()).parse(cursor)
+    }
+
+    /// Resolves `(type x)? (param ...)* (result ...)*` to a type index, adding the inline
+    /// signature to the types if there is no explicit index.
+    /// Also returns the ids of the parameters.
+    fn type_use(&mut self, cursor: &mut Cursor<'_, 'a>) -> WatResult<(u32, LocalIds<'a>)> {
+        let explicit = match cursor.list("type") {
+            Some(mut ty) => {
+                let offset = ty.offset();
+                let index = self.ids.types.parse(&mut ty)?;
+                ty.finish()?;
+                Some((index, offset))
+            }
+            None => None,
+        };
+        let (inline, param_ids) = signature(cursor)?;
+        let Some((index, offset)) = explicit else {
+            let ty = inline.unwrap_or_else(empty_function_type);
+            return Ok((self.builder.add_type(ty), param_ids));
+        };
+        let ty = self
+            .builder
+            .get_type(index)
+            .ok_or_else(|| WatError::new(WatErrorKind::UnknownType(index), offset))?;
+        match inline {
+            Some(inline) if inline != *ty => {
+                Err(WatError::new(WatErrorKind::TypeMismatch(index), offset))
+            }
+            Some(_) => Ok((index, param_ids)),
+            None => Ok((index, vec![None; ty.params.len()])),
+        }
+    }
+
+    /// Block types without parameters and with at most one result are encoded directly,
+    /// others through the type section.
+    fn block_type(&mut self, cursor: &mut Cursor<'_, 'a>) -> WatResult<BlockType> {
+        if cursor.peek_list_head() == Some("type") {
+            return Ok(BlockType::TypeIndex(self.type_use(cursor)?.0));
+        }
+        Ok(match signature(cursor)?.0 {
+            None => BlockType::Empty,
+            Some(ty) if ty.params.is_empty() && ty.results.len() <= 1 => ty
+                .results
+                .first()
+                .map_or(BlockType::Empty, |t| BlockType::Value(*t)),
+            Some(ty) => BlockType::TypeIndex(self.builder.add_type(ty)),
+        })
+    }
+}
+
+fn empty_function_type() -> FunctionType {
+    FunctionType {
+        params: Vec::new(),
+        results: Vec::new(),
+    }
+}
+
+/// `(param ...)* (result ...)*`, or `None` if there are neither; also returns the parameter ids.
+fn signature<'a>(cursor: &mut Cursor<'_, 'a>) -> WatResult<(Option<FunctionType>, LocalIds<'a>)> {
+    let mut present = false;
+    let mut params = Vec::new();
+    let mut ids = Vec::new();
+    let mut results = Vec::new();
+    while let Some(mut param) = cursor.list("param") {
+        present = true;
+        let offset = param.offset();
+        if let Some(id) = param.id() {
+            params.push(value_type(&mut param)?);
+            ids.push(Some((id, offset)));
+            param.finish()?;
+        } else {
+            while !param.is_empty() {
+                params.push(value_type(&mut param)?);
+                ids.push(None);
+            }
+        }
+    }
+    while let Some(mut result) = cursor.list("result") {
+        present = true;
+        while !result.is_empty() {
+            results.push(value_type(&mut result)?);
+        }
+    }
+    Ok((present.then_some(FunctionType { params, results }), ids))
+}
+
+fn value_type(cursor: &mut Cursor) -> WatResult<ValueType> {
+    let value_type = match cursor.peek_keyword() {
+        Some("i32") => NumberType::I32.into(),
+        Some("i64") => NumberType::I64.into(),
+        Some("f32") => NumberType::F32.into(),
+        Some("f64") => NumberType::F64.into(),
+        Some("v128") => VectorType::V128.into(),
+        Some(keyword) => match reference_type(keyword) {
+            Some(ref_type) => ref_type.into(),
+            None => return Err(cursor.error(WatErrorKind::Expected("value type"))),
+        },
+        None => return Err(cursor.error(WatErrorKind::Expected("value type"))),
+    };
+    cursor.advance();
+    Ok(value_type)
+}
+
+fn reference_type(keyword: &str) -> Option<ReferenceType> {
+    match keyword {
+        "funcref" => Some(ReferenceType::FuncRef),
+        "externref" => Some(ReferenceType::ExternRef),
+        _ => None,
+    }
+}
+
+fn limits(cursor: &mut Cursor) -> WatResult<Limits> {
+    let min = cursor.u32()?;
+    let max = if cursor.peek_number() {
+        Some(cursor.u32()?)
+    } else {
+        None
+    };
+    Ok(Limits { min, max })
+}
+
+fn table_type(cursor: &mut Cursor) -> WatResult<TableType> {
+    let limits = limits(cursor)?;
+    let ref_type = cursor
+        .peek_keyword()
+        .and_then(reference_type)
+        .ok_or_else(|| cursor.error(WatErrorKind::Expected("reference type")))?;
+    cursor.advance();
+    Ok(TableType { ref_type, limits })
+}
+
+fn memory_type(cursor: &mut Cursor) -> WatResult<MemoryType> {
+    Ok(MemoryType {
+        limits: limits(cursor)?,
+    })
+}
+
+fn global_type(cursor: &mut Cursor) -> WatResult<GlobalType> {
+    match cursor.list("mut") {
+        Some(mut var) => {
+            let val_type = value_type(&mut var)?;
+            var.finish()?;
+            Ok(GlobalType {
+                val_type,
+                mutability: Mutability::Var,
+            })
+        }
+        None => Ok(GlobalType {
+            val_type: value_type(cursor)?,
+            mutability: Mutability::Const,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{ModuleParsed, section::SectionID};
+
+    /// The sections must match those assembled by the `wat` crate, except for its name section.
+    fn assert_matches_wat(text: &str) {
+        let wasm = wat::parse_str(text).unwrap();
+        let expected = ModuleParsed::from_slice(&wasm).unwrap();
+        let expected: Vec<_> = expected
+            .sections
+            .into_iter()
+            .filter(|s| s.id() != SectionID::Custom)
+            .collect();
+        let module = parse_wat(text).unwrap_or_else(|e| panic!("{}\n{}", e, text));
+        assert_eq!(module.sections, expected, "{}", text);
+    }
+
+    #[test]
+    fn test_parse_abbreviations() {
+        assert_matches_wat(
+            r#"
+(module
+  (type $unused (func (result i64)))
+  (func $f (export "a") (export "b") (import "env" "f") (param i32))
+  (global $g (import "env" "g") (mut i32))
+  (table $t funcref (elem $h $f))
+  (memory $m (export "mem") (data "hello, " "world\u{1f600}"))
+  (func $h (param $x i32) (result i32) (local $y i64) (local f32 f32)
+    (if $label (result i32) (local.get $x)
+      (then (br $label (i32.const 1)))
+      (else (call $f (local.get $x)) (i32.const 2)))
+    block $b (param i32) (result i32 i32)
+      i32.const 3
+    end $b
+    drop
+    global.set $g
+    (memory.init $seg (i32.const 0) (i32.const 0) (i32.const 0))
+    (data.drop 0)
+    (drop (v128.const i8x16 -1 255 0 1 2 3 4 5 6 7 8 9 10 11 12 0x7f))
+    (drop (v128.const f64x2 0x1p-1 -inf))
+    (i32.store offset=4 align=2 (i32.const 0) (i32.const 1))
+    (i32.load16_u $m offset=0x10 (i32.const 0)))
+  (elem declare func $h)
+  (elem (table $t) (offset (i32.const 1)) funcref (item ref.func $h) (ref.null func))
+  (data $seg "\00\01\ff")
+  (start $f))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_parse_modules() {
+        for wat in crate::fixtures::MODULES {
+            assert_matches_wat(wat);
+        }
+    }
+
+    #[test]
+    fn test_parse_nesting_limit() {
+        // the function and each block are lists
+        let nested = |depth: usize| {
+            format!(
+                "(func {}nop{})",
+                "(block ".repeat(depth - 1),
+                ")".repeat(depth - 1)
+            )
+        };
+        assert!(parse_wat(&nested(200)).is_ok());
+        let error = parse_wat(&nested(201)).unwrap_err();
+        assert_eq!(
+            (error.kind, error.column),
+            (WatErrorKind::NestingTooDeep, 1400)
+        );
+        let operands = format!(
+            "(func (result i32) {}i32.const 0{})",
+            "(i32.eqz ".repeat(100_000),
+            ")".repeat(100_000)
+        );
+        assert_eq!(
+            parse_wat(&operands).unwrap_err().kind,
+            WatErrorKind::NestingTooDeep
+        );
+    }
+
+    #[test]
+    fn test_wat_to_bytes() {
+        let text = r#"(module (func (export "answer") (result i32) i32.const 42))"#;
+        assert_eq!(wat_to_bytes(text).unwrap(), wat::parse_str(text).unwrap());
+        assert_eq!(
+            wat_to_bytes("(func) (func)").unwrap(),
+            wat::parse_str("(module (func) (func))").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            (
+                "(module\n  (func\n    call $nope))",
+                WatErrorKind::UnknownId("nope".to_string()),
+                3,
+                10,
+            ),
+            (
+                "(func $f) (func $f)",
+                WatErrorKind::DuplicateId("f".to_string()),
+                1,
+                17,
+            ),
+            (
+                "(func i32.frobnicate)",
+                WatErrorKind::UnknownInstruction("i32.frobnicate".to_string()),
+                1,
+                7,
+            ),
+            (
+                "(type (func)) (func (type 0) (param i32))",
+                WatErrorKind::TypeMismatch(0),
+                1,
+                27,
+            ),
+            ("(func (type 1))", WatErrorKind::UnknownType(1), 1, 13),
+            (
+                "(func) (import \"a\" \"b\" (func))",
+                WatErrorKind::ImportAfterDefinition,
+                1,
+                8,
+            ),
+            (
+                "(func block $a end $b)",
+                WatErrorKind::MismatchedLabel("b".to_string()),
+                1,
+                20,
+            ),
+            ("(func block)", WatErrorKind::Expected("end"), 1, 12),
+            (
+                "(func i32.const 0x1_0000_0000)",
+                WatErrorKind::InvalidNumber("0x1_0000_0000".to_string()),
+                1,
+                17,
+            ),
+            (
+                "(memory 1) (func i32.load align=3)",
+                WatErrorKind::InvalidAlignment(3),
+                1,
+                27,
+            ),
+            ("(start 0) (start 0)", WatErrorKind::MultipleStart, 1, 11),
+            (
+                "(module (tabel 1 funcref))",
+                WatErrorKind::Expected("module field"),
+                1,
+                9,
+            ),
+            ("(func))", WatErrorKind::UnmatchedCloseParen, 1, 7),
+            (
+                "(export \"\\ff\" (func 0))",
+                WatErrorKind::InvalidUtf8,
+                1,
+                9,
+            ),
+        ];
+        for (text, kind, line, column) in cases {
+            let error = parse_wat(text).unwrap_err();
+            assert_eq!(
+                (error.kind, error.line, error.column),
+                (kind, line, column),
+                "{}",
+                text
+            );
+        }
+        let error = parse_wat("(module\n  (func\n    call $nope))").unwrap_err();
+        assert_eq!(error.to_string(), "error at 3:10: unknown identifier $nope");
+    }
+}
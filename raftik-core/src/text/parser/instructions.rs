@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+
+use super::{
+    Cursor, Index, Space, TextModule, WatResult,
+    number::{parse_f32, parse_f64, parse_i8, parse_i16, parse_i32, parse_i64, parse_u32},
+    value_type,
+};
+use crate::{
+    ast::{
+        instructions::{
+            MemArg, MemoryOp, NumericOp, Opcode, VectorLaneOp, VectorMemoryLaneOp, VectorMemoryOp,
+            VectorOp,
+        },
+        types::ReferenceType,
+    },
+    text::error::{WatError, WatErrorKind},
+};
+
+/// Lowers plain and folded instructions to a flat sequence of opcodes.
+pub(super) struct ExpressionParser<'m, 'a> {
+    module: &'m mut TextModule<'a>,
+    locals: HashMap<&'a str, u32>,
+    /// Labels of the enclosing blocks, innermost last.
+    labels: Vec<Option<&'a str>>,
+    opcodes: Vec<Opcode>,
+}
+
+impl<'m, 'a> ExpressionParser<'m, 'a> {
+    pub fn new(module: &'m mut TextModule<'a>, locals: HashMap<&'a str, u32>) -> Self {
+        Self {
+            module,
+            locals,
+            labels: Vec::new(),
+            opcodes: Vec::new(),
+        }
+    }
+
+    /// Reads instructions up to the end of `cursor`; the closing `end` is not included.
+    pub fn parse(mut self, mut cursor: Cursor<'_, 'a>) -> WatResult<Vec<Opcode>> {
+        self.instructions(&mut cursor)?;
+        if !self.labels.is_empty() {
+            return Err(cursor.error(WatErrorKind::Expected("end")));
+        }
+        Ok(self.opcodes)
+    }
+
+    /// Reads the single folded instruction whose list is `list`.
+    pub fn parse_folded(mut self, list: Cursor<'_, 'a>) -> WatResult<Vec<Opcode>> {
+        self.folded(list)?;
+        Ok(self.opcodes)
+    }
+
+    fn instructions(&mut self, cursor: &mut Cursor<'_, 'a>) -> WatResult<()> {
+        while !cursor.is_empty() {
+            if let Some(list) = cursor.any_list() {
+                self.folded(list)?;
+            } else {
+                let offset = cursor.offset();
+                let name = cursor.expect_keyword("instruction")?;
+                self.plain(name, offset, cursor)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn folded(&mut self, mut list: Cursor<'_, 'a>) -> WatResult<()> {
+        let offset = list.offset();
+        let name = list.expect_keyword("instruction")?;
+        match name {
+            "block" | "loop" => {
+                self.block_start(name, &mut list)?;
+                self.instructions(&mut list)?;
+                self.labels.pop();
+                self.opcodes.push(Opcode::End);
+            }
+            "if" => {
+                let label = list.id();
+                let block_type = self.module.block_type(&mut list)?;
+                // the condition is given as folded instructions before `(then ...)`
+                while list.peek_list_head() != Some("then") {
+                    let condition = list
+                        .any_list()
+                        .ok_or_else(|| list.error(WatErrorKind::Expected("(then ...)")))?;
+                    self.folded(condition)?;
+                }
+                let mut then = list.list("then").unwrap();
+                self.opcodes.push(Opcode::If(block_type));
+                self.labels.push(label);
+                self.instructions(&mut then)?;
+                if let Some(mut otherwise) = list.list("else") {
+                    self.opcodes.push(Opcode::Else);
+                    self.instructions(&mut otherwise)?;
+                }
+                list.finish()?;
+                self.labels.pop();
+                self.opcodes.push(Opcode::End);
+            }
+            "else" | "end" => return Err(WatError::new(WatErrorKind::UnexpectedToken, offset)),
+            _ => {
+                // operands follow the immediates, and are evaluated before the instruction
+                self.plain(name, offset, &mut list)?;
+                let opcode = self.opcodes.pop().unwrap();
+                while !list.is_empty() {
+                    let operand = list
+                        .any_list()
+                        .ok_or_else(|| list.error(WatErrorKind::UnexpectedToken))?;
+                    self.folded(operand)?;
+                }
+                self.opcodes.push(opcode);
+            }
+        }
+        Ok(())
+    }
+
+    fn block_start(&mut self, name: &str, cursor: &mut Cursor<'_, 'a>) -> WatResult<()> {
+        let label = cursor.id();
+        let block_type = self.module.block_type(cursor)?;
+        self.opcodes.push(match name {
+            "block" => Opcode::Block(block_type),
+            "loop" => Opcode::Loop(block_type),
+            _ => Opcode::If(block_type),
+        });
+        self.labels.push(label);
+        Ok(())
+    }
+
+    /// Reads the immediates of the instruction `name` and pushes its opcode.
+    fn plain(
+        &mut self,
+        name: &'a str,
+        offset: usize,
+        cursor: &mut Cursor<'_, 'a>,
+    ) -> WatResult<()> {
+        let opcode = match name {
+            "unreachable" => Opcode::Unreachable,
+            "nop" => Opcode::Nop,
+            "block" | "loop" | "if" => return self.block_start(name, cursor),
+            "else" => {
+                self.closing_label(cursor)?;
+                Opcode::Else
+            }
+            "end" => {
+                self.closing_label(cursor)?;
+                if self.labels.pop().is_none() {
+                    return Err(WatError::new(WatErrorKind::UnexpectedToken, offset));
+                }
+                Opcode::End
+            }
+            "br" => Opcode::Br(self.label(cursor)?),
+            "br_if" => Opcode::BrIf(self.label(cursor)?),
+            "br_table" => {
+                let mut labels = vec![self.label(cursor)?];
+                while let Some(index) = cursor.index()? {
+                    labels.push(self.resolve_label(index)?);
+                }
+                let default = labels.pop().unwrap();
+                Opcode::BrTable(labels, default)
+            }
+            "return" => Opcode::Return,
+            "call" => Opcode::Call(self.module.ids.functions.parse(cursor)?),
+            "call_indirect" => {
+                let table = optional_index(cursor, &self.module.ids.tables)?;
+                let (type_index, _) = self.module.type_use(cursor)?;
+                Opcode::CallIndirect(type_index, table)
+            }
+            "drop" => Opcode::Drop,
+            "select" => {
+                let mut present = false;
+                let mut types = Vec::new();
+                while let Some(mut result) = cursor.list("result") {
+                    present = true;
+                    while !result.is_empty() {
+                        types.push(value_type(&mut result)?);
+                    }
+                }
+                if present {
+                    Opcode::SelectTyped(types)
+                } else {
+                    Opcode::Select
+                }
+            }
+            "local.get" => Opcode::LocalGet(self.local(cursor)?),
+            "local.set" => Opcode::LocalSet(self.local(cursor)?),
+            "local.tee" => Opcode::LocalTee(self.local(cursor)?),
+            "global.get" => Opcode::GlobalGet(self.module.ids.globals.parse(cursor)?),
+            "global.set" => Opcode::GlobalSet(self.module.ids.globals.parse(cursor)?),
+            "i32.const" => Opcode::I32Const(cursor.number(parse_i32)?),
+            "i64.const" => Opcode::I64Const(cursor.number(parse_i64)?),
+            "f32.const" => Opcode::F32Const(cursor.number(parse_f32)?),
+            "f64.const" => Opcode::F64Const(cursor.number(parse_f64)?),
+            "ref.null" => Opcode::RefNull(match cursor.keyword() {
+                Some("func") => ReferenceType::FuncRef,
+                Some("extern") => ReferenceType::ExternRef,
+                _ => return Err(cursor.error(WatErrorKind::Expected("heap type"))),
+            }),
+            "ref.is_null" => Opcode::RefIsNull,
+            "ref.func" => Opcode::RefFunc(self.module.ids.functions.parse(cursor)?),
+            "table.get" => Opcode::TableGet(optional_index(cursor, &self.module.ids.tables)?),
+            "table.set" => Opcode::TableSet(optional_index(cursor, &self.module.ids.tables)?),
+            "table.grow" => Opcode::TableGrow(optional_index(cursor, &self.module.ids.tables)?),
+            "table.size" => Opcode::TableSize(optional_index(cursor, &self.module.ids.tables)?),
+            "table.fill" => Opcode::TableFill(optional_index(cursor, &self.module.ids.tables)?),
+            "table.init" => {
+                let (table, element) = self.segment_and_target(cursor)?;
+                let ids = &self.module.ids;
+                let table = table.map_or(Ok(0), |table| ids.tables.resolve(table))?;
+                Opcode::TableInit(ids.elements.resolve(element)?, table)
+            }
+            "elem.drop" => Opcode::ElemDrop(self.module.ids.elements.parse(cursor)?),
+            "table.copy" => {
+                let (destination, source) = index_pair(cursor, &self.module.ids.tables)?;
+                Opcode::TableCopy(destination, source)
+            }
+            "memory.size" => Opcode::MemorySize(optional_index(cursor, &self.module.ids.memories)?),
+            "memory.grow" => Opcode::MemoryGrow(optional_index(cursor, &self.module.ids.memories)?),
+            "memory.fill" => Opcode::MemoryFill(optional_index(cursor, &self.module.ids.memories)?),
+            "memory.init" => {
+                let (memory, data) = self.segment_and_target(cursor)?;
+                let ids = &self.module.ids;
+                let memory = memory.map_or(Ok(0), |memory| ids.memories.resolve(memory))?;
+                Opcode::MemoryInit(ids.data.resolve(data)?, memory)
+            }
+            "data.drop" => Opcode::DataDrop(self.module.ids.data.parse(cursor)?),
+            "memory.copy" => {
+                let (destination, source) = index_pair(cursor, &self.module.ids.memories)?;
+                Opcode::MemoryCopy(destination, source)
+            }
+            "v128.const" => Opcode::V128Const(v128_const(cursor)?),
+            "i8x16.shuffle" => {
+                let mut lanes = [0; 16];
+                for lane in &mut lanes {
+                    *lane = lane_index(cursor)?;
+                }
+                Opcode::I8x16Shuffle(lanes)
+            }
+            _ => {
+                if let Some(op) = NumericOp::from_name(name) {
+                    Opcode::Numeric(op)
+                } else if let Some(op) = MemoryOp::from_name(name) {
+                    let memory_index = optional_index(cursor, &self.module.ids.memories)?;
+                    Opcode::Memory(op, mem_arg(cursor, op.natural_alignment(), memory_index)?)
+                } else if let Some(op) = VectorOp::from_name(name) {
+                    Opcode::Vector(op)
+                } else if let Some(op) = VectorLaneOp::from_name(name) {
+                    Opcode::VectorLane(op, lane_index(cursor)?)
+                } else if let Some(op) = VectorMemoryOp::from_name(name) {
+                    let memory_index = optional_index(cursor, &self.module.ids.memories)?;
+                    Opcode::VectorMemory(op, mem_arg(cursor, op.natural_alignment(), memory_index)?)
+                } else if let Some(op) = VectorMemoryLaneOp::from_name(name) {
+                    self.vector_memory_lane(op, cursor)?
+                } else {
+                    return Err(WatError::new(
+                        WatErrorKind::UnknownInstruction(name.to_string()),
+                        offset,
+                    ));
+                }
+            }
+        };
+        self.opcodes.push(opcode);
+        Ok(())
+    }
+
+    /// `memidx? memarg laneidx`, where a single number before the memarg is the lane.
+    fn vector_memory_lane(
+        &mut self,
+        op: VectorMemoryLaneOp,
+        cursor: &mut Cursor<'_, 'a>,
+    ) -> WatResult<Opcode> {
+        let first_offset = cursor.offset();
+        let first = cursor.index()?;
+        let memarg_offset = cursor.offset();
+        let mut memarg = mem_arg(cursor, op.natural_alignment(), 0)?;
+        let lane = if cursor.peek_number() {
+            if let Some(memory) = first {
+                memarg.memory_index = self.module.ids.memories.resolve(memory)?;
+            }
+            lane_index(cursor)?
+        } else {
+            match first {
+                Some(Index::Num(lane)) if memarg_offset == cursor.offset() => u8::try_from(lane)
+                    .map_err(|_| {
+                        WatError::new(WatErrorKind::InvalidNumber(lane.to_string()), first_offset)
+                    })?,
+                _ => return Err(cursor.error(WatErrorKind::Expected("lane index"))),
+            }
+        };
+        Ok(Opcode::VectorMemoryLane(op, memarg, lane))
+    }
+
+    /// Checks the optional label after `else` or `end` against the innermost block.
+    fn closing_label(&self, cursor: &mut Cursor<'_, 'a>) -> WatResult<()> {
+        let offset = cursor.offset();
+        if let Some(id) = cursor.id()
+            && self.labels.last() != Some(&Some(id))
+        {
+            return Err(WatError::new(
+                WatErrorKind::MismatchedLabel(id.to_string()),
+                offset,
+            ));
+        }
+        Ok(())
+    }
+
+    fn label(&self, cursor: &mut Cursor<'_, 'a>) -> WatResult<u32> {
+        let index = cursor
+            .index()?
+            .ok_or_else(|| cursor.error(WatErrorKind::Expected("label")))?;
+        self.resolve_label(index)
+    }
+
+    /// Labels are referred to by their depth, counting outwards from the innermost block.
+    fn resolve_label(&self, index: Index<'a>) -> WatResult<u32> {
+        match index {
+            Index::Num(depth) => Ok(depth),
+            Index::Id(id, offset) => self
+                .labels
+                .iter()
+                .rev()
+                .position(|label| *label == Some(id))
+                .map(|depth| depth as u32)
+                .ok_or_else(|| WatError::new(WatErrorKind::UnknownId(id.to_string()), offset)),
+        }
+    }
+
+    fn local(&self, cursor: &mut Cursor<'_, 'a>) -> WatResult<u32> {
+        match cursor
+            .index()?
+            .ok_or_else(|| cursor.error(WatErrorKind::Expected("local")))?
+        {
+            Index::Num(index) => Ok(index),
+            Index::Id(id, offset) => self
+                .locals
+                .get(id)
+                .copied()
+                .ok_or_else(|| WatError::new(WatErrorKind::UnknownId(id.to_string()), offset)),
+        }
+    }
+
+    /// `target? segment` of `table.init` and `memory.init`.
+    fn segment_and_target(
+        &self,
+        cursor: &mut Cursor<'_, 'a>,
+    ) -> WatResult<(Option<Index<'a>>, Index<'a>)> {
+        let first = cursor
+            .index()?
+            .ok_or_else(|| cursor.error(WatErrorKind::Expected("index")))?;
+        Ok(match cursor.index()? {
+            Some(segment) => (Some(first), segment),
+            None => (None, first),
+        })
+    }
+}
+
+/// An index which defaults to 0 when left out.
+fn optional_index<'a>(cursor: &mut Cursor<'_, 'a>, space: &Space<'a>) -> WatResult<u32> {
+    match cursor.index()? {
+        Some(index) => space.resolve(index),
+        None => Ok(0),
+    }
+}
+
+/// Either no index, or both the destination and the source index.
+fn index_pair<'a>(cursor: &mut Cursor<'_, 'a>, space: &Space<'a>) -> WatResult<(u32, u32)> {
+    match cursor.index()? {
+        Some(destination) => Ok((space.resolve(destination)?, space.parse(cursor)?)),
+        None => Ok((0, 0)),
+    }
+}
+
+/// `offset=n? align=n?`, with the alignment stored as an exponent.
+fn mem_arg(cursor: &mut Cursor, natural_alignment: u32, memory_index: u32) -> WatResult<MemArg> {
+    let mut memarg = MemArg {
+        align: natural_alignment,
+        offset: 0,
+        memory_index,
+    };
+    if let Some(offset) = cursor
+        .peek_keyword()
+        .and_then(|k| k.strip_prefix("offset="))
+    {
+        memarg.offset = value_after_equals(cursor, offset)?;
+    }
+    if let Some(align) = cursor.peek_keyword().and_then(|k| k.strip_prefix("align=")) {
+        let at = cursor.offset();
+        let align = value_after_equals(cursor, align)?;
+        if !align.is_power_of_two() {
+            return Err(WatError::new(WatErrorKind::InvalidAlignment(align), at));
+        }
+        memarg.align = align.trailing_zeros();
+    }
+    Ok(memarg)
+}
+
+fn value_after_equals(cursor: &mut Cursor, text: &str) -> WatResult<u32> {
+    let value = parse_u32(text)
+        .ok_or_else(|| cursor.error(WatErrorKind::InvalidNumber(text.to_string())))?;
+    cursor.advance();
+    Ok(value)
+}
+
+fn lane_index(cursor: &mut Cursor) -> WatResult<u8> {
+    cursor.number(|text| parse_u32(text).and_then(|lane| u8::try_from(lane).ok()))
+}
+
+/// A shape followed by its lanes, stored in little-endian order.
+fn v128_const(cursor: &mut Cursor) -> WatResult<[u8; 16]> {
+    let offset = cursor.offset();
+    let shape = cursor.expect_keyword("vector shape")?;
+    let mut bytes = Vec::with_capacity(16);
+    match shape {
+        "i8x16" => {
+            for _ in 0..16 {
+                bytes.extend(cursor.number(parse_i8)?.to_le_bytes());
+            }
+        }
+        "i16x8" => {
+            for _ in 0..8 {
+                bytes.extend(cursor.number(parse_i16)?.to_le_bytes());
+            }
+        }
+        "i32x4" => {
+            for _ in 0..4 {
+                bytes.extend(cursor.number(parse_i32)?.to_le_bytes());
+            }
+        }
+        "i64x2" => {
+            for _ in 0..2 {
+                bytes.extend(cursor.number(parse_i64)?.to_le_bytes());
+            }
+        }
+        "f32x4" => {
+            for _ in 0..4 {
+                bytes.extend(cursor.number(parse_f32)?.to_bits().to_le_bytes());
+            }
+        }
+        "f64x2" => {
+            for _ in 0..2 {
+                bytes.extend(cursor.number(parse_f64)?.to_bits().to_le_bytes());
+            }
+        }
+        _ => {
+            return Err(WatError::new(
+                WatErrorKind::Expected("vector shape"),
+                offset,
+            ));
+        }
+    }
+    Ok(bytes.try_into().unwrap())
+}
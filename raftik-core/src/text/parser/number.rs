@@ -0,0 +1,322 @@
+//! Integer and float literals of the text format.
+//! Each function returns `None` if `text` is malformed or out of range.
+
+/// Removes `_` separators, which are only allowed between two digits.
+fn digits(text: &str, hex: bool) -> Option<String> {
+    let is_digit = |c: char| {
+        if hex {
+            c.is_ascii_hexdigit()
+        } else {
+            c.is_ascii_digit()
+        }
+    };
+    let mut previous_digit = false;
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '_' {
+            if !previous_digit || !chars.peek().is_some_and(|&next| is_digit(next)) {
+                return None;
+            }
+        } else if is_digit(c) {
+            out.push(c);
+        } else {
+            return None;
+        }
+        previous_digit = c != '_';
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+fn split_sign(text: &str) -> (bool, &str) {
+    match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    }
+}
+
+fn unsigned(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(&digits(hex, true)?, 16).ok(),
+        None => digits(text, false)?.parse().ok(),
+    }
+}
+
+pub(super) fn parse_u32(text: &str) -> Option<u32> {
+    unsigned(text)?.try_into().ok()
+}
+
+/// An integer of `bits` bits, written in either its signed or its unsigned range.
+fn narrow(text: &str, bits: u32) -> Option<i64> {
+    let (negative, magnitude) = split_sign(text);
+    let magnitude = unsigned(magnitude)?;
+    if negative {
+        (magnitude <= 1 << (bits - 1)).then(|| -(magnitude as i64))
+    } else {
+        (magnitude < 1 << bits).then_some(magnitude as i64)
+    }
+}
+
+pub(super) fn parse_i8(text: &str) -> Option<i8> {
+    narrow(text, 8).map(|v| v as i8)
+}
+
+pub(super) fn parse_i16(text: &str) -> Option<i16> {
+    narrow(text, 16).map(|v| v as i16)
+}
+
+/// Integers may be written in either the signed or the unsigned range of 32 bits.
+pub(super) fn parse_i32(text: &str) -> Option<i32> {
+    match split_sign(text) {
+        (true, magnitude) => {
+            let magnitude = unsigned(magnitude)?;
+            (magnitude <= 1 << 31).then(|| (magnitude as i64).wrapping_neg() as i32)
+        }
+        (false, magnitude) => u32::try_from(unsigned(magnitude)?).ok().map(|v| v as i32),
+    }
+}
+
+/// Integers may be written in either the signed or the unsigned range of 64 bits.
+pub(super) fn parse_i64(text: &str) -> Option<i64> {
+    match split_sign(text) {
+        (true, magnitude) => {
+            let magnitude = unsigned(magnitude)?;
+            (magnitude <= 1 << 63).then(|| magnitude.wrapping_neg() as i64)
+        }
+        (false, magnitude) => unsigned(magnitude).map(|v| v as i64),
+    }
+}
+
+/// Bit layout of an IEEE 754 binary format.
+#[derive(Clone, Copy)]
+struct FloatFormat {
+    mantissa_bits: u32,
+    exponent_bits: u32,
+}
+
+const F32: FloatFormat = FloatFormat {
+    mantissa_bits: 23,
+    exponent_bits: 8,
+};
+
+const F64: FloatFormat = FloatFormat {
+    mantissa_bits: 52,
+    exponent_bits: 11,
+};
+
+impl FloatFormat {
+    fn sign_bit(&self) -> u64 {
+        1 << (self.mantissa_bits + self.exponent_bits)
+    }
+
+    fn exponent_mask(&self) -> u64 {
+        ((1 << self.exponent_bits) - 1) << self.mantissa_bits
+    }
+
+    fn bias(&self) -> i64 {
+        (1 << (self.exponent_bits - 1)) - 1
+    }
+}
+
+pub(super) fn parse_f32(text: &str) -> Option<f32> {
+    float_bits(text, F32, |decimal| {
+        decimal
+            .parse::<f32>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .map(|v| u64::from(v.to_bits()))
+    })
+    .map(|bits| f32::from_bits(bits as u32))
+}
+
+pub(super) fn parse_f64(text: &str) -> Option<f64> {
+    float_bits(text, F64, |decimal| {
+        decimal
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .map(f64::to_bits)
+    })
+    .map(f64::from_bits)
+}
+
+/// Parses any float literal to its bits; unsigned decimal literals are converted by `decimal`,
+/// which receives them with separators removed.
+fn float_bits(
+    text: &str,
+    format: FloatFormat,
+    decimal: impl Fn(&str) -> Option<u64>,
+) -> Option<u64> {
+    let (negative, magnitude) = split_sign(text);
+    let sign = if negative { format.sign_bit() } else { 0 };
+    let bits = if magnitude == "inf" {
+        format.exponent_mask()
+    } else if magnitude == "nan" {
+        format.exponent_mask() | 1 << (format.mantissa_bits - 1)
+    } else if let Some(payload) = magnitude.strip_prefix("nan:0x") {
+        let payload = u64::from_str_radix(&digits(payload, true)?, 16).ok()?;
+        if payload == 0 || payload >> format.mantissa_bits != 0 {
+            return None;
+        }
+        format.exponent_mask() | payload
+    } else if let Some(hex) = magnitude.strip_prefix("0x") {
+        hex_float(hex, format)?
+    } else {
+        let (mantissa, exponent) = match magnitude.find(['e', 'E']) {
+            Some(i) => (&magnitude[..i], Some(&magnitude[i + 1..])),
+            None => (magnitude, None),
+        };
+        let mut normalized = match mantissa.split_once('.') {
+            Some((int, "")) => digits(int, false)?,
+            Some((int, frac)) => format!("{}.{}", digits(int, false)?, digits(frac, false)?),
+            None => digits(mantissa, false)?,
+        };
+        if let Some(exponent) = exponent {
+            let (negative, exponent) = split_sign(exponent);
+            let sign = if negative { "-" } else { "" };
+            normalized = format!("{}e{}{}", normalized, sign, digits(exponent, false)?);
+        }
+        decimal(&normalized)?
+    };
+    Some(sign | bits)
+}
+
+/// Converts a hexadecimal float, without sign and `0x`, rounding to nearest even.
+fn hex_float(text: &str, format: FloatFormat) -> Option<u64> {
+    let (mantissa, exponent) = match text.find(['p', 'P']) {
+        Some(i) => (&text[..i], Some(&text[i + 1..])),
+        None => (text, None),
+    };
+    let (int, frac) = match mantissa.split_once('.') {
+        Some((int, "")) => (digits(int, true)?, String::new()),
+        Some((int, frac)) => (digits(int, true)?, digits(frac, true)?),
+        None => (digits(mantissa, true)?, String::new()),
+    };
+
+    // significand * 2^exponent, with `sticky` recording nonzero digits which did not fit
+    let mut significand: u64 = 0;
+    let mut exponent: i64 = match exponent {
+        Some(exponent) => {
+            let (negative, exponent) = split_sign(exponent);
+            let value = digits(exponent, false)?.bytes().fold(0i64, |acc, d| {
+                acc.saturating_mul(10).saturating_add(i64::from(d - b'0'))
+            });
+            if negative { -value } else { value }
+        }
+        None => 0,
+    };
+    let mut sticky = false;
+    for (i, c) in int.chars().chain(frac.chars()).enumerate() {
+        let digit = u64::from(c.to_digit(16).unwrap());
+        let fractional = i >= int.len();
+        if significand >> 60 == 0 {
+            significand = significand << 4 | digit;
+            if fractional {
+                exponent = exponent.saturating_sub(4);
+            }
+        } else {
+            sticky |= digit != 0;
+            if !fractional {
+                exponent = exponent.saturating_add(4);
+            }
+        }
+    }
+    if significand == 0 {
+        return Some(0);
+    }
+
+    let msb = 63 - i64::from(significand.leading_zeros());
+    let mut value_exponent = msb.saturating_add(exponent);
+    let min_exponent = 1 - format.bias();
+    let precision =
+        i64::from(format.mantissa_bits) + 1 - min_exponent.saturating_sub(value_exponent).max(0);
+    let shift = msb + 1 - precision;
+    let mut rounded = if shift <= 0 {
+        significand << -shift
+    } else if shift > msb + 1 {
+        0
+    } else {
+        round_shift(significand, shift as u32, sticky)
+    };
+
+    if value_exponent < min_exponent {
+        // subnormal: a carry into the implicit bit correctly yields the smallest normal number
+        return Some(rounded);
+    }
+    if rounded >> (format.mantissa_bits + 1) != 0 {
+        rounded >>= 1;
+        value_exponent += 1;
+    }
+    if value_exponent > format.bias() {
+        return None;
+    }
+    let biased = (value_exponent + format.bias()) as u64;
+    Some(biased << format.mantissa_bits | (rounded & ((1 << format.mantissa_bits) - 1)))
+}
+
+/// `value >> shift`, rounded to nearest even; `sticky` marks nonzero bits below `value`.
+fn round_shift(value: u64, shift: u32, sticky: bool) -> u64 {
+    let value = u128::from(value);
+    let dropped = value & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    let mut result = (value >> shift) as u64;
+    if dropped > half || (dropped == half && (sticky || result & 1 == 1)) {
+        result += 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(parse_u32("0x1_0"), Some(16));
+        assert_eq!(parse_u32("4294967296"), None);
+        assert_eq!(parse_u32("1__0"), None);
+        assert_eq!(parse_u32("-1"), None);
+        assert_eq!(parse_i8("255"), Some(-1));
+        assert_eq!(parse_i8("-129"), None);
+        assert_eq!(parse_i16("-0x8000"), Some(i16::MIN));
+        assert_eq!(parse_i32("-2147483648"), Some(i32::MIN));
+        assert_eq!(parse_i32("0xffffffff"), Some(-1));
+        assert_eq!(parse_i32("-2147483649"), None);
+        assert_eq!(parse_i64("-0x8000000000000000"), Some(i64::MIN));
+        assert_eq!(parse_i64("18446744073709551615"), Some(-1));
+        assert_eq!(parse_i64("18446744073709551616"), None);
+    }
+
+    #[test]
+    fn test_floats() {
+        assert_eq!(parse_f32("-0x1.8p+1"), Some(-3.0));
+        assert_eq!(parse_f64("1_000.5e-1"), Some(100.05));
+        assert_eq!(parse_f64("1."), Some(1.0));
+        assert_eq!(parse_f64("0x1p-1074"), Some(f64::from_bits(1)));
+        assert_eq!(parse_f64("0x1p-1075"), Some(0.0));
+        assert_eq!(parse_f64("0x1.8p-1075"), Some(f64::from_bits(1)));
+        assert_eq!(parse_f64("0x1.fffffffffffff8p1023"), None);
+        assert_eq!(parse_f32("0x1.fffffep127"), Some(f32::MAX));
+        assert_eq!(parse_f32("0x1.ffffffp127"), None);
+        assert_eq!(parse_f32("0x1.000001p0"), Some(1.0));
+        assert_eq!(parse_f32("0x1.0000011p0"), Some(1.0 + f32::EPSILON));
+        assert_eq!(
+            parse_f32("0x123456789abcdef0123p0"),
+            Some(0x123456789abcdef0123u128 as f32)
+        );
+        assert_eq!(parse_f32("1e39"), None);
+        assert_eq!(parse_f64("-inf"), Some(f64::NEG_INFINITY));
+        assert_eq!(
+            parse_f32("nan:0x200000").map(f32::to_bits),
+            Some(0x7fa0_0000)
+        );
+        assert_eq!(
+            parse_f64("-nan").map(f64::to_bits),
+            Some(0xfff8_0000_0000_0000)
+        );
+        assert_eq!(parse_f32("nan:0x800000"), None);
+        assert_eq!(parse_f64("1e"), None);
+        assert_eq!(parse_f64(".5"), None);
+    }
+}
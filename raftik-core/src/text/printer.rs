@@ -0,0 +1,867 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use nom::combinator::iterator;
+
+use crate::{
+    ast::{
+        ModuleParsed, Section,
+        custom::NameMap,
+        instructions::{BlockType, MemArg, Opcode, RawExpression},
+        section::{
+            DataMode, DataSegment, Element, ElementItems, ElementKind, Export, ExportDesc,
+            FunctionBody, Global, Import, ImportDesc,
+        },
+        types::{
+            FunctionType, GlobalType, Limits, MemoryType, Mutability, NumberType, ReferenceType,
+            TableType, ValueType, VectorType,
+        },
+    },
+    binary::{error::ParseError, parser::instructions::parse_instruction},
+};
+
+/// Renders `module` in the WebAssembly text format.
+/// Function and local names are taken from the `name` custom section when present.
+pub fn print_module(module: &ModuleParsed) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        indent: 0,
+        names: Names::from_module(module),
+    };
+    printer.module(module);
+    printer.out
+}
+
+/// Function and local names which are usable as `$id`s.
+#[derive(Default)]
+struct Names {
+    module: Option<String>,
+    functions: HashMap<u32, String>,
+    locals: HashMap<u32, HashMap<u32, String>>,
+}
+
+impl Names {
+    fn from_module(module: &ModuleParsed) -> Self {
+        let Some(names) = module.name_section() else {
+            return Names::default();
+        };
+        Names {
+            module: names.module.filter(|name| is_id(name)),
+            functions: unique_ids(names.functions),
+            locals: names
+                .locals
+                .into_iter()
+                .map(|(function, map)| (function, unique_ids(map)))
+                .collect(),
+        }
+    }
+
+    fn function(&self, index: u32) -> String {
+        match self.functions.get(&index) {
+            Some(name) => format!("${}", name),
+            None => index.to_string(),
+        }
+    }
+}
+
+/// Keeps only names which can be printed as an `$id` and are not used twice.
+fn unique_ids(map: NameMap) -> HashMap<u32, String> {
+    let mut seen = HashSet::new();
+    let mut duplicated = HashSet::new();
+    for name in map.values() {
+        if !seen.insert(name.clone()) {
+            duplicated.insert(name.clone());
+        }
+    }
+    map.into_iter()
+        .filter(|(_, name)| is_id(name) && !duplicated.contains(name))
+        .collect()
+}
+
+fn is_id(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-./:<=>?@\\^_`|~".contains(&b))
+}
+
+struct Printer {
+    out: String,
+    indent: usize,
+    names: Names,
+}
+
+impl Printer {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn module(&mut self, module: &ModuleParsed) {
+        match &self.names.module {
+            Some(name) => self.line(&format!("(module ${}", name)),
+            None => self.line("(module"),
+        }
+        self.indent += 1;
+        let mut function_index = 0;
+        let mut table_index = 0;
+        let mut memory_index = 0;
+        let mut global_index = 0;
+        let mut element_index = 0;
+        let mut data_index = 0;
+        let types: Vec<&FunctionType> = module
+            .sections
+            .iter()
+            .filter_map(|s| match s {
+                Section::Type(t) => Some(&t.types),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        let function_types: Vec<u32> = module
+            .sections
+            .iter()
+            .filter_map(|s| match s {
+                Section::Function(f) => Some(&f.type_indices),
+                _ => None,
+            })
+            .flatten()
+            .copied()
+            .collect();
+        let bodies: Vec<Result<Cow<FunctionBody>, ParseError>> = module
+            .sections
+            .iter()
+            .flat_map(|s| match s {
+                Section::Code(c) => c.code.iter().map(|b| Ok(Cow::Borrowed(b))).collect(),
+                Section::LazyCode(c) => c.iter().map(|b| b.map(Cow::Owned)).collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+        let mut bodies = bodies.into_iter();
+
+        for section in &module.sections {
+            match section {
+                Section::Type(s) => {
+                    for (i, t) in s.types.iter().enumerate() {
+                        self.line(&format!("(type (;{};) (func{}))", i, signature(t)));
+                    }
+                }
+                Section::Import(s) => {
+                    for import in &s.imports {
+                        let index = match import.desc {
+                            ImportDesc::TypeIndex(_) => &mut function_index,
+                            ImportDesc::Table(_) => &mut table_index,
+                            ImportDesc::Memory(_) => &mut memory_index,
+                            ImportDesc::Global(_) => &mut global_index,
+                        };
+                        let text = self.import(import, *index);
+                        *index += 1;
+                        self.line(&text);
+                    }
+                }
+                // bodies are printed together with the code section
+                Section::Function(_) => (),
+                Section::Table(s) => {
+                    for t in &s.tables {
+                        self.line(&format!("(table (;{};) {})", table_index, table_type(t)));
+                        table_index += 1;
+                    }
+                }
+                Section::Memory(s) => {
+                    for m in &s.memories {
+                        self.line(&format!("(memory (;{};) {})", memory_index, memory_type(m)));
+                        memory_index += 1;
+                    }
+                }
+                Section::Global(s) => {
+                    for g in &s.globals {
+                        let text = self.global(g, global_index);
+                        global_index += 1;
+                        self.line(&text);
+                    }
+                }
+                Section::Export(s) => {
+                    for e in &s.exports {
+                        let text = self.export(e);
+                        self.line(&text);
+                    }
+                }
+                Section::Start(s) => {
+                    let text = format!("(start {})", self.names.function(s.start_function_index));
+                    self.line(&text);
+                }
+                Section::Element(s) => {
+                    for e in &s.elements {
+                        let text = self.element(e, element_index);
+                        element_index += 1;
+                        self.line(&text);
+                    }
+                }
+                Section::Code(_) | Section::LazyCode(_) => {
+                    for type_index in &function_types {
+                        let Some(body) = bodies.next() else {
+                            break;
+                        };
+                        match body {
+                            Ok(body) => {
+                                let ty = types.get(*type_index as usize).copied();
+                                self.function(function_index, *type_index, ty, &body);
+                            }
+                            Err(e) => self.line(&format!(
+                                ";; function {} is malformed: {}",
+                                function_index, e
+                            )),
+                        }
+                        function_index += 1;
+                    }
+                }
+                Section::Data(s) => {
+                    for d in &s.segments {
+                        let text = self.data(d, data_index);
+                        data_index += 1;
+                        self.line(&text);
+                    }
+                }
+                Section::DataCount(_) => (),
+                // names are already shown as `$id`s
+                Section::Custom(c) if c.name == "name" => (),
+                Section::Custom(c) => {
+                    self.line(&format!(";; custom section {}", string(c.name.as_bytes())));
+                }
+            }
+        }
+        self.indent -= 1;
+        self.line(")");
+    }
+
+    fn import(&self, import: &Import, index: u32) -> String {
+        let desc = match &import.desc {
+            ImportDesc::TypeIndex(t) => {
+                format!("(func {}(;{};) (type {}))", self.id_prefix(index), index, t)
+            }
+            ImportDesc::Table(t) => format!("(table (;{};) {})", index, table_type(t)),
+            ImportDesc::Memory(m) => format!("(memory (;{};) {})", index, memory_type(m)),
+            ImportDesc::Global(g) => format!("(global (;{};) {})", index, global_type(g)),
+        };
+        format!(
+            "(import {} {} {})",
+            string(import.module.as_bytes()),
+            string(import.name.as_bytes()),
+            desc
+        )
+    }
+
+    fn id_prefix(&self, function_index: u32) -> String {
+        match self.names.functions.get(&function_index) {
+            Some(name) => format!("${} ", name),
+            None => String::new(),
+        }
+    }
+
+    fn global(&self, global: &Global, index: u32) -> String {
+        format!(
+            "(global (;{};) {} {})",
+            index,
+            global_type(&global.global_type),
+            self.const_expression(&global.expression)
+        )
+    }
+
+    fn export(&self, export: &Export) -> String {
+        let desc = match export.desc {
+            ExportDesc::FunctionIndex(i) => format!("(func {})", self.names.function(i)),
+            ExportDesc::TableIndex(i) => format!("(table {})", i),
+            ExportDesc::MemoryIndex(i) => format!("(memory {})", i),
+            ExportDesc::GlobalIndex(i) => format!("(global {})", i),
+        };
+        format!("(export {} {})", string(export.name.as_bytes()), desc)
+    }
+
+    fn element(&self, element: &Element, index: u32) -> String {
+        let mut text = format!("(elem (;{};)", index);
+        match &element.kind {
+            ElementKind::Active {
+                table_index,
+                offset_expression,
+            } => {
+                if let Some(table_index) = table_index {
+                    write!(text, " (table {})", table_index).unwrap();
+                }
+                write!(text, " {}", self.offset(offset_expression)).unwrap();
+            }
+            ElementKind::Passive => (),
+            ElementKind::Declarative => text.push_str(" declare"),
+        }
+        match &element.items {
+            ElementItems::Functions(indices) => {
+                text.push_str(" func");
+                for i in indices {
+                    write!(text, " {}", self.names.function(*i)).unwrap();
+                }
+            }
+            ElementItems::Expressions(ref_type, expressions) => {
+                write!(text, " {}", reference_type(*ref_type)).unwrap();
+                for e in expressions {
+                    write!(text, " (item {})", self.instructions_inline(e)).unwrap();
+                }
+            }
+        }
+        text.push(')');
+        text
+    }
+
+    fn data(&self, segment: &DataSegment, index: u32) -> String {
+        let mut text = format!("(data (;{};)", index);
+        if let DataMode::Active {
+            memory_index,
+            offset_expression,
+        } = &segment.mode
+        {
+            if let Some(memory_index) = memory_index {
+                write!(text, " (memory {})", memory_index).unwrap();
+            }
+            write!(text, " {}", self.offset(offset_expression)).unwrap();
+        }
+        write!(text, " {})", string(&segment.data)).unwrap();
+        text
+    }
+
+    /// A single constant instruction is folded, otherwise `(offset ...)` is used.
+    fn offset(&self, expression: &RawExpression) -> String {
+        match decode(expression).as_slice() {
+            [op] => format!("({})", self.instruction(op, None)),
+            _ => format!("(offset {})", self.instructions_inline(expression)),
+        }
+    }
+
+    fn const_expression(&self, expression: &RawExpression) -> String {
+        match decode(expression).as_slice() {
+            [op] => format!("({})", self.instruction(op, None)),
+            _ => self.instructions_inline(expression),
+        }
+    }
+
+    fn instructions_inline(&self, expression: &RawExpression) -> String {
+        decode(expression)
+            .iter()
+            .map(|op| self.instruction(op, None))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn function(
+        &mut self,
+        index: u32,
+        type_index: u32,
+        ty: Option<&FunctionType>,
+        body: &FunctionBody,
+    ) {
+        let local_names = self.names.locals.remove(&index).unwrap_or_default();
+        let local_id = |i: u32| match local_names.get(&i) {
+            Some(name) => format!("${} ", name),
+            None => String::new(),
+        };
+        let mut header = format!(
+            "(func {}(;{};) (type {})",
+            self.id_prefix(index),
+            index,
+            type_index
+        );
+        let mut local_index = 0;
+        if let Some(ty) = ty {
+            for param in &ty.params {
+                write!(
+                    header,
+                    " (param {}{})",
+                    local_id(local_index),
+                    value_type(*param)
+                )
+                .unwrap();
+                local_index += 1;
+            }
+            if !ty.results.is_empty() {
+                write!(header, " (result {})", value_types(&ty.results)).unwrap();
+            }
+        }
+        self.line(&header);
+        self.indent += 1;
+        // unvalidated bodies may have unmatched `end`s or unclosed blocks
+        let body_indent = self.indent;
+        for locals in &body.locals {
+            for _ in 0..locals.count {
+                let text = format!(
+                    "(local {}{})",
+                    local_id(local_index),
+                    value_type(locals.value_type)
+                );
+                self.line(&text);
+                local_index += 1;
+            }
+        }
+        let mut it = iterator(&body.expression.instructions[..], parse_instruction);
+        for op in &mut it {
+            if matches!(op, Opcode::Else | Opcode::End) {
+                self.indent = (self.indent - 1).max(body_indent);
+            }
+            let text = self.instruction(&op, Some(&local_names));
+            self.line(&text);
+            if matches!(
+                op,
+                Opcode::Block(_) | Opcode::Loop(_) | Opcode::If(_) | Opcode::Else
+            ) {
+                self.indent += 1;
+            }
+        }
+        if it.finish().is_err() {
+            self.line(";; malformed instruction");
+        }
+        self.indent = body_indent - 1;
+        self.line(")");
+    }
+
+    fn instruction(&self, op: &Opcode, locals: Option<&HashMap<u32, String>>) -> String {
+        let local = |i: &u32| match locals.and_then(|l| l.get(i)) {
+            Some(name) => format!("${}", name),
+            None => i.to_string(),
+        };
+        match op {
+            Opcode::Unreachable => "unreachable".to_string(),
+            Opcode::Nop => "nop".to_string(),
+            Opcode::Block(t) => format!("block{}", block_type(t)),
+            Opcode::Loop(t) => format!("loop{}", block_type(t)),
+            Opcode::If(t) => format!("if{}", block_type(t)),
+            Opcode::Else => "else".to_string(),
+            Opcode::End => "end".to_string(),
+            Opcode::Br(l) => format!("br {}", l),
+            Opcode::BrIf(l) => format!("br_if {}", l),
+            Opcode::BrTable(labels, default) => {
+                let mut text = "br_table".to_string();
+                for l in labels.iter().chain([default]) {
+                    write!(text, " {}", l).unwrap();
+                }
+                text
+            }
+            Opcode::Return => "return".to_string(),
+            Opcode::Call(f) => format!("call {}", self.names.function(*f)),
+            Opcode::CallIndirect(ty, table) => format!("call_indirect {} (type {})", table, ty),
+            Opcode::Drop => "drop".to_string(),
+            Opcode::Select => "select".to_string(),
+            Opcode::SelectTyped(types) => format!("select (result {})", value_types(types)),
+            Opcode::LocalGet(i) => format!("local.get {}", local(i)),
+            Opcode::LocalSet(i) => format!("local.set {}", local(i)),
+            Opcode::LocalTee(i) => format!("local.tee {}", local(i)),
+            Opcode::GlobalGet(i) => format!("global.get {}", i),
+            Opcode::GlobalSet(i) => format!("global.set {}", i),
+            Opcode::I32Const(v) => format!("i32.const {}", v),
+            Opcode::I64Const(v) => format!("i64.const {}", v),
+            Opcode::F32Const(v) => format!("f32.const {}", f32_text(*v)),
+            Opcode::F64Const(v) => format!("f64.const {}", f64_text(*v)),
+            Opcode::RefNull(t) => format!("ref.null {}", heap_type(*t)),
+            Opcode::RefIsNull => "ref.is_null".to_string(),
+            Opcode::RefFunc(f) => format!("ref.func {}", self.names.function(*f)),
+            Opcode::TableGet(t) => format!("table.get {}", t),
+            Opcode::TableSet(t) => format!("table.set {}", t),
+            Opcode::TableInit(elem, table) => format!("table.init {} {}", table, elem),
+            Opcode::ElemDrop(e) => format!("elem.drop {}", e),
+            Opcode::TableCopy(dst, src) => format!("table.copy {} {}", dst, src),
+            Opcode::TableGrow(t) => format!("table.grow {}", t),
+            Opcode::TableSize(t) => format!("table.size {}", t),
+            Opcode::TableFill(t) => format!("table.fill {}", t),
+            Opcode::Memory(op, memarg) => {
+                format!("{}{}", op.name(), mem_arg(memarg, op.natural_alignment()))
+            }
+            Opcode::MemorySize(m) => format!("memory.size {}", m),
+            Opcode::MemoryGrow(m) => format!("memory.grow {}", m),
+            Opcode::MemoryInit(data, memory) => format!("memory.init {} {}", memory, data),
+            Opcode::DataDrop(d) => format!("data.drop {}", d),
+            Opcode::MemoryCopy(dst, src) => format!("memory.copy {} {}", dst, src),
+            Opcode::MemoryFill(m) => format!("memory.fill {}", m),
+            Opcode::Numeric(op) => op.name().to_string(),
+            Opcode::V128Const(bytes) => {
+                let mut text = "v128.const i32x4".to_string();
+                for lane in bytes.chunks_exact(4) {
+                    let lane = u32::from_le_bytes(lane.try_into().unwrap());
+                    write!(text, " {:#010x}", lane).unwrap();
+                }
+                text
+            }
+            Opcode::I8x16Shuffle(lanes) => {
+                let mut text = "i8x16.shuffle".to_string();
+                for lane in lanes {
+                    write!(text, " {}", lane).unwrap();
+                }
+                text
+            }
+            Opcode::VectorLane(op, lane) => format!("{} {}", op.name(), lane),
+            Opcode::VectorMemory(op, memarg) => {
+                format!("{}{}", op.name(), mem_arg(memarg, op.natural_alignment()))
+            }
+            Opcode::VectorMemoryLane(op, memarg, lane) => format!(
+                "{}{} {}",
+                op.name(),
+                mem_arg(memarg, op.natural_alignment()),
+                lane
+            ),
+            Opcode::Vector(op) => op.name().to_string(),
+        }
+    }
+}
+
+fn decode(expression: &RawExpression) -> Vec<Opcode> {
+    iterator(&expression.instructions[..], parse_instruction).collect()
+}
+
+fn signature(ty: &FunctionType) -> String {
+    let mut text = String::new();
+    if !ty.params.is_empty() {
+        write!(text, " (param {})", value_types(&ty.params)).unwrap();
+    }
+    if !ty.results.is_empty() {
+        write!(text, " (result {})", value_types(&ty.results)).unwrap();
+    }
+    text
+}
+
+fn block_type(t: &BlockType) -> String {
+    match t {
+        BlockType::Empty => String::new(),
+        BlockType::Value(v) => format!(" (result {})", value_type(*v)),
+        BlockType::TypeIndex(i) => format!(" (type {})", i),
+    }
+}
+
+/// Memory index (if not 0), then `offset=` and `align=` when they differ from the defaults.
+fn mem_arg(memarg: &MemArg, natural_alignment: u32) -> String {
+    let mut text = String::new();
+    if memarg.memory_index != 0 {
+        write!(text, " {}", memarg.memory_index).unwrap();
+    }
+    if memarg.offset != 0 {
+        write!(text, " offset={}", memarg.offset).unwrap();
+    }
+    if memarg.align != natural_alignment {
+        write!(text, " align={}", 1u64 << memarg.align.min(63)).unwrap();
+    }
+    text
+}
+
+fn value_type(t: ValueType) -> &'static str {
+    match t {
+        ValueType::Number(NumberType::I32) => "i32",
+        ValueType::Number(NumberType::I64) => "i64",
+        ValueType::Number(NumberType::F32) => "f32",
+        ValueType::Number(NumberType::F64) => "f64",
+        ValueType::Vector(VectorType::V128) => "v128",
+        ValueType::Reference(r) => reference_type(r),
+    }
+}
+
+fn value_types(types: &[ValueType]) -> String {
+    types
+        .iter()
+        .map(|t| value_type(*t))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn reference_type(t: ReferenceType) -> &'static str {
+    match t {
+        ReferenceType::FuncRef => "funcref",
+        ReferenceType::ExternRef => "externref",
+    }
+}
+
+fn heap_type(t: ReferenceType) -> &'static str {
+    match t {
+        ReferenceType::FuncRef => "func",
+        ReferenceType::ExternRef => "extern",
+    }
+}
+
+fn limits(limits: &Limits) -> String {
+    match limits.max {
+        Some(max) => format!("{} {}", limits.min, max),
+        None => limits.min.to_string(),
+    }
+}
+
+fn table_type(t: &TableType) -> String {
+    format!("{} {}", limits(&t.limits), reference_type(t.ref_type))
+}
+
+fn memory_type(m: &MemoryType) -> String {
+    limits(&m.limits)
+}
+
+fn global_type(g: &GlobalType) -> String {
+    match g.mutability {
+        Mutability::Const => value_type(g.val_type).to_string(),
+        Mutability::Var => format!("(mut {})", value_type(g.val_type)),
+    }
+}
+
+/// Quotes `bytes` as a WAT string, escaping anything but printable ASCII.
+fn string(bytes: &[u8]) -> String {
+    let mut text = String::from("\"");
+    for &b in bytes {
+        match b {
+            b'"' => text.push_str("\\\""),
+            b'\\' => text.push_str("\\\\"),
+            0x20..=0x7e => text.push(b as char),
+            _ => write!(text, "\\{:02x}", b).unwrap(),
+        }
+    }
+    text.push('"');
+    text
+}
+
+fn f32_text(v: f32) -> String {
+    float_text(v.is_nan(), v.is_sign_negative(), v.is_infinite(), || {
+        (u64::from(v.to_bits() & 0x7f_ffff), 0x40_0000)
+    })
+    .unwrap_or_else(|| v.to_string())
+}
+
+fn f64_text(v: f64) -> String {
+    float_text(v.is_nan(), v.is_sign_negative(), v.is_infinite(), || {
+        (v.to_bits() & 0xf_ffff_ffff_ffff, 0x8_0000_0000_0000)
+    })
+    .unwrap_or_else(|| v.to_string())
+}
+
+/// Text for the values which have no decimal form: infinities and NaNs with their payload.
+fn float_text(
+    nan: bool,
+    negative: bool,
+    infinite: bool,
+    payload: impl Fn() -> (u64, u64),
+) -> Option<String> {
+    let sign = if negative { "-" } else { "" };
+    if infinite {
+        return Some(format!("{}inf", sign));
+    }
+    if !nan {
+        return None;
+    }
+    let (payload, canonical) = payload();
+    if payload == canonical {
+        Some(format!("{}nan", sign))
+    } else {
+        Some(format!("{}nan:{:#x}", sign, payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::parse_wat;
+
+    fn without_custom_sections<'a>(module: &'a ModuleParsed) -> Vec<&'a Section<'a>> {
+        module
+            .sections
+            .iter()
+            .filter(|s| !matches!(s, Section::Custom(_)))
+            .collect()
+    }
+
+    /// The printed text must assemble back to the same module.
+    fn assert_round_trip(wat: &str) {
+        let wasm = wat::parse_str(wat).unwrap();
+        let module = ModuleParsed::from_slice(&wasm).unwrap();
+        let printed = print_module(&module);
+        let reassembled = wat::parse_str(&printed)
+            .unwrap_or_else(|e| panic!("printed text does not assemble: {}\n{}", e, printed));
+        let reparsed = ModuleParsed::from_slice(&reassembled).unwrap();
+        assert_eq!(
+            without_custom_sections(&reparsed),
+            without_custom_sections(&module),
+            "{}",
+            printed
+        );
+        let native = parse_wat(&printed)
+            .unwrap_or_else(|e| panic!("printed text does not parse: {}\n{}", e, printed));
+        assert_eq!(
+            without_custom_sections(&native),
+            without_custom_sections(&module),
+            "{}",
+            printed
+        );
+    }
+
+    #[test]
+    fn test_print_module() {
+        let wasm = wat::parse_str(
+            r#"
+(module $m
+  (import "env" "log" (func $log (param i32)))
+  (memory 1)
+  (func $add (export "add") (param $a i32) (param $b i32) (result i32) (local $tmp f64)
+    local.get $a
+    if (result i32)
+      local.get $b
+    else
+      i32.const -3
+    end
+    call $log
+    i32.const 1)
+  (data (i32.const 8) "hi\00\"\\\ff"))
+"#,
+        )
+        .unwrap();
+        let module = ModuleParsed::from_slice(&wasm).unwrap();
+        assert_eq!(
+            print_module(&module),
+            r#"(module $m
+  (type (;0;) (func (param i32)))
+  (type (;1;) (func (param i32 i32) (result i32)))
+  (import "env" "log" (func $log (;0;) (type 0)))
+  (memory (;0;) 1)
+  (export "add" (func $add))
+  (func $add (;1;) (type 1) (param $a i32) (param $b i32) (result i32)
+    (local $tmp f64)
+    local.get $a
+    if (result i32)
+      local.get $b
+    else
+      i32.const -3
+    end
+    call $log
+    i32.const 1
+  )
+  (data (;0;) (i32.const 8) "hi\00\"\\\ff")
+)
+"#
+        );
+    }
+
+    #[test]
+    fn test_print_round_trip() {
+        let fixtures = [
+            "(module)",
+            r#"(module
+  (import "console" "log" (func $log (param i32)))
+  (import "host" "table" (table 1 10 funcref))
+  (import "host" "memory" (memory 1 2))
+  (global (import "host" "global") i64)
+  (func (export "f") (param i64 f32 f64) (result i32)
+    (local i32 v128 externref)
+    block $outer (result i32)
+      loop $inner
+        local.get 0
+        i64.eqz
+        br_if 1
+        i32.const 1
+        br_table 0 1 1
+      end
+      i32.const 0
+    end
+    (select (result i32) (i32.const 1) (i32.const 2) (i32.const 0))
+    drop
+    (call_indirect (param i32) (i32.const 7) (i32.const 0))
+    f32.const -0x1.8p+1
+    f32.const nan:0x200000
+    f64.const 1e300
+    f64.const -nan
+    drop drop drop drop
+    i64.const -9223372036854775808
+    i64.extend32_s
+    drop
+    i32.const 0
+    i64.load32_u offset=12 align=2
+    drop
+    memory.size
+    memory.grow
+    drop
+    ref.null extern
+    ref.is_null
+    drop))"#,
+            r#"(module
+  (table $t 2 funcref)
+  (table 1 externref)
+  (memory 1)
+  (func $a)
+  (func $b (param v128) (result v128)
+    local.get 0
+    v128.const i32x4 1 2 3 4
+    i32x4.add
+    i8x16.shuffle 0 17 2 19 4 21 6 23 8 25 10 27 12 29 14 31
+    i32.const 0
+    v128.load offset=16
+    i32x4.extract_lane 3
+    i32.const 0
+    v128.load32_lane offset=4 1
+    f64x2.promote_low_f32x4
+    i32.const 0
+    v128.load8_splat align=1
+    drop
+    drop
+    drop
+    drop)
+  (func $c (param i32)
+    (memory.init 1 (i32.const 0) (i32.const 0) (i32.const 1))
+    (data.drop 0)
+    (memory.copy (i32.const 0) (i32.const 1) (i32.const 2))
+    (memory.fill (i32.const 0) (i32.const 1) (i32.const 2))
+    (table.init $t 1 (i32.const 0) (i32.const 0) (i32.const 1))
+    (elem.drop 1)
+    (table.copy (i32.const 0) (i32.const 1) (i32.const 1))
+    (drop (table.grow 1 (ref.null extern) (i32.const 1)))
+    (drop (table.size 1))
+    (table.fill 1 (i32.const 0) (ref.null extern) (i32.const 1))
+    (table.set 0 (i32.const 0) (ref.func $a))
+    (drop (table.get 0 (i32.const 0)))
+    (ref.func $b)
+    drop)
+  (global $g (mut i32) (i32.const 0))
+  (global i32 (global.get $g))
+  (export "t" (table $t))
+  (export "m" (memory 0))
+  (export "g" (global $g))
+  (start $a)
+  (elem (i32.const 0) $a $b)
+  (elem func $c)
+  (elem declare func $a)
+  (elem (table $t) (offset (i32.const 1)) funcref (ref.func $a))
+  (data "passive")
+  (data (i32.const 4) "\01\02 active"))"#,
+        ];
+        for fixture in fixtures {
+            assert_round_trip(fixture);
+        }
+    }
+
+    #[test]
+    fn test_print_unbalanced_blocks() {
+        use crate::{ast::types::FunctionType, builder::ModuleBuilder};
+
+        let mut builder = ModuleBuilder::new();
+        let void = FunctionType {
+            params: vec![],
+            results: vec![],
+        };
+        builder.add_function(void.clone(), &[], &[Opcode::End, Opcode::End]);
+        builder.add_function(void, &[], &[Opcode::Block(BlockType::Empty), Opcode::Nop]);
+        let module = builder.build();
+        assert_eq!(
+            print_module(&module),
+            r#"(module
+  (type (;0;) (func))
+  (func (;0;) (type 0)
+    end
+    end
+  )
+  (func (;1;) (type 0)
+    block
+      nop
+  )
+)
+"#
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use super::{
+    error::{WatError, WatErrorKind},
+    lexer::Token,
+};
+
+/// How deeply lists may nest, which bounds the recursion of the parser over them.
+const MAX_DEPTH: usize = 200;
+
+/// A token or a parenthesized list of S-expressions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum SExpr<'a> {
+    Atom {
+        token: Token<'a>,
+        offset: usize,
+    },
+    List {
+        items: Vec<SExpr<'a>>,
+        /// Offset of the opening parenthesis.
+        offset: usize,
+        /// Offset of the closing parenthesis.
+        end: usize,
+    },
+}
+
+impl SExpr<'_> {
+    pub fn offset(&self) -> usize {
+        match self {
+            SExpr::Atom { offset, .. } | SExpr::List { offset, .. } => *offset,
+        }
+    }
+}
+
+/// Groups `tokens` into a sequence of S-expressions.
+pub(super) fn parse_sexprs(tokens: Vec<(usize, Token<'_>)>) -> Result<Vec<SExpr<'_>>, WatError> {
+    // the innermost list is last; the bottom entry collects the top-level expressions
+    let mut stack: Vec<(usize, Vec<SExpr>)> = vec![(0, Vec::new())];
+    for (offset, token) in tokens {
+        match token {
+            Token::LParen => {
+                // the bottom entry of the stack is not a list
+                if stack.len() > MAX_DEPTH {
+                    return Err(WatError::new(WatErrorKind::NestingTooDeep, offset));
+                }
+                stack.push((offset, Vec::new()));
+            }
+            Token::RParen => {
+                if stack.len() == 1 {
+                    return Err(WatError::new(WatErrorKind::UnmatchedCloseParen, offset));
+                }
+                let (start, items) = stack.pop().unwrap();
+                stack.last_mut().unwrap().1.push(SExpr::List {
+                    items,
+                    offset: start,
+                    end: offset,
+                });
+            }
+            token => stack
+                .last_mut()
+                .unwrap()
+                .1
+                .push(SExpr::Atom { token, offset }),
+        }
+    }
+    if stack.len() > 1 {
+        let (start, _) = stack.pop().unwrap();
+        return Err(WatError::new(WatErrorKind::UnclosedParen, start));
+    }
+    Ok(stack.pop().unwrap().1)
+}
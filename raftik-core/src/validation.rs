@@ -64,7 +64,9 @@ struct Context<'a> {
     pub globals: Vec<ItemDesc<&'a GlobalType>>,
     pub locals: Vec<ValueType>,
     pub refs: HashSet<u32>,
+    pub elements: Vec<ReferenceType>,
     pub data_segments: Vec<()>,
+    pub data_count: Option<u32>,
     pub instructions_should_be_constant: bool,
 }
 
@@ -78,7 +80,9 @@ impl<'a> Context<'a> {
             globals: self.globals.imported(),
             locals: self.locals.clone(),
             refs: self.refs.clone(),
+            elements: self.elements.clone(),
             data_segments: self.data_segments.clone(),
+            data_count: self.data_count,
             instructions_should_be_constant: self.instructions_should_be_constant,
         }
     }
@@ -155,6 +159,7 @@ fn initialize_context<'a>(module: &'a ModuleParsed<'a>) -> Result<Context<'a>, V
                 for (i, e) in element_section.elements.iter().enumerate() {
                     match &e.items {
                         crate::ast::section::ElementItems::Functions(items) => {
+                            context.elements.push(ReferenceType::FuncRef);
                             for i in items {
                                 context.refs.insert(*i);
                             }
@@ -163,6 +168,7 @@ fn initialize_context<'a>(module: &'a ModuleParsed<'a>) -> Result<Context<'a>, V
                             reference_type,
                             raw_expressions,
                         ) => {
+                            context.elements.push(*reference_type);
                             if *reference_type == ReferenceType::FuncRef {
                                 for (j, exp) in raw_expressions.iter().enumerate() {
                                     instruction::collect_funcref_in_expression(
@@ -179,11 +185,13 @@ fn initialize_context<'a>(module: &'a ModuleParsed<'a>) -> Result<Context<'a>, V
                     }
                 }
             }
-            Section::Code(_) => (),
+            Section::Code(_) | Section::LazyCode(_) => (),
             Section::Data(data_section) => {
                 context.data_segments = vec![(); data_section.segments.len()];
             }
-            Section::DataCount(_) => (),
+            Section::DataCount(data_count_section) => {
+                context.data_count = Some(data_count_section.count);
+            }
             Section::Custom(_) => (),
         }
     }
@@ -219,8 +227,20 @@ pub fn validate_module(module: &ModuleParsed) -> Result<(), ValidationError> {
                 c_prime.instructions_should_be_constant = true;
                 section::validate_element_section(element_section, &mut c_prime)?
             }
-            Section::Code(code_section) => {
-                section::validate_code_section(code_section, &mut context)?
+            Section::Code(code_section) => section::validate_code_section(
+                code_section,
+                &mut context,
+                &module.name_section().unwrap_or_default(),
+            )?,
+            Section::LazyCode(lazy_code_section) => {
+                let code_section = lazy_code_section
+                    .to_code_section()
+                    .map_err(ValidationError::MalformedFunctionBody)?;
+                section::validate_code_section(
+                    &code_section,
+                    &mut context,
+                    &module.name_section().unwrap_or_default(),
+                )?
             }
             Section::Data(data_section) => {
                 let mut c_prime = context.prime();
@@ -280,6 +300,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_error_names_function() {
+        let cases = [
+            ("$broken", "at code section #0 ($broken)"),
+            ("", "at code section #0 (function #1)"),
+        ];
+        for (id, expected) in cases {
+            let wat = format!(
+                "(module (import \"env\" \"f\" (func)) (func {} (result i32) i64.const 0))",
+                id
+            );
+            with_wat(wat, |module| match validate_module(&module) {
+                Err(ValidationError::InstructionValidationError { desc, .. }) => {
+                    assert_eq!(desc, expected)
+                }
+                r => unreachable!("unexpected result: {:?}", r),
+            });
+        }
+    }
+
     #[test]
     fn test_invalid_add_module() {
         with_wat(
@@ -309,6 +349,394 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_block_module() {
+        with_wat(
+            "
+(module
+  (type $t (func (param i32) (result i32)))
+  (func (param i32) (result i32)
+    (block (result i32)
+      (loop (result i32)
+        (if (result i32) (local.get 0)
+          (then (i32.const 1))
+          (else (i32.const 11)))))
+    (block (type $t))
+    (if (local.get 0) (then))
+    (local.get 0)
+    i32.add)
+)
+",
+            |module| {
+                let r = validate_module(&module);
+                assert!(r.is_ok(), "{:#?}", r);
+            },
+        );
+    }
+
+    #[test]
+    fn test_invalid_if_without_else() {
+        with_wat(
+            "(module (func (result i32) (if (result i32) (i32.const 0) (then (i32.const 1)))))",
+            |module| {
+                let r = validate_module(&module);
+                if let Err(ValidationError::InstructionValidationError { error: e, .. }) = r {
+                    assert!(matches!(e, VInstError::IfWithoutElseTypeMismatch));
+                } else {
+                    unreachable!("result not expected: {:#?}", r);
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_invalid_block_result() {
+        with_wat(
+            "(module (func (result i64) (block (result i64) (i32.const 0))))",
+            |module| {
+                let r = validate_module(&module);
+                assert!(r.is_err(), "{:#?}", r);
+            },
+        );
+    }
+
+    #[test]
+    fn test_control_instructions() {
+        with_wat(
+            r#"
+(module
+  (type $binop (func (param i32 i32) (result i32)))
+  (table 2 funcref)
+  (func $add (type $binop) local.get 0 local.get 1 i32.add)
+  (func (param i32) (result i32)
+    nop
+    (block $outer (result i32)
+      (block $inner
+        (br_table $inner $inner (local.get 0)))
+      (br_if $outer (i32.const 1) (local.get 0))
+      (call $add (i32.const 2) (i32.const 3))
+      (call_indirect (type $binop) (i32.const 4) (i32.const 5) (i32.const 0))
+      i32.add
+      return)))
+"#,
+            |module| {
+                let r = validate_module(&module);
+                assert!(r.is_ok(), "{:#?}", r);
+            },
+        );
+    }
+
+    #[test]
+    fn test_unreachable_makes_stack_polymorphic() {
+        with_wat(
+            "(module (func (result i32) unreachable i32.add) (func (result i64) (block (br 0)) unreachable))",
+            |module| {
+                let r = validate_module(&module);
+                assert!(r.is_ok(), "{:#?}", r);
+            },
+        );
+    }
+
+    #[test]
+    fn test_invalid_br_label() {
+        with_wat("(module (func br 1))", |module| {
+            let r = validate_module(&module);
+            if let Err(ValidationError::InstructionValidationError { error: e, .. }) = r {
+                assert!(matches!(e, VInstError::NoLabelAtIndex(1)));
+            } else {
+                unreachable!("result not expected: {:#?}", r);
+            }
+        });
+    }
+
+    #[test]
+    fn test_invalid_call_indirect_without_table() {
+        with_wat(
+            "(module (type (func)) (func (call_indirect (type 0) (i32.const 0))) (table 0 externref))",
+            |module| {
+                let r = validate_module(&module);
+                if let Err(ValidationError::InstructionValidationError { error: e, .. }) = r {
+                    assert!(matches!(e, VInstError::TableShouldBeFuncRef(0)));
+                } else {
+                    unreachable!("result not expected: {:#?}", r);
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_numeric_instructions() {
+        with_wat(
+            r#"
+(module
+  (func (param i32 i64 f32 f64) (result i32)
+    (i64.lt_u (i64.rotl (local.get 1) (i64.extend_i32_s (i32.popcnt (local.get 0))))
+              (i64.trunc_f64_s (f64.copysign (local.get 3) (f64.promote_f32 (local.get 2)))))
+    (i32.reinterpret_f32 (f32.sqrt (f32.demote_f64 (f64.convert_i32_u (local.get 0)))))
+    i32.xor))
+"#,
+            |module| {
+                let r = validate_module(&module);
+                assert!(r.is_ok(), "{:#?}", r);
+            },
+        );
+    }
+
+    #[test]
+    fn test_invalid_numeric_conversion() {
+        with_wat(
+            "(module (func (param f32) (result i64) (i64.trunc_f64_u (local.get 0))))",
+            |module| match validate_module(&module) {
+                Err(ValidationError::InstructionValidationError {
+                    error: VInstError::PopValueTypeMismatch { expected, actual },
+                    ..
+                }) => {
+                    assert_eq!(ValueType::Number(NumberType::F64), expected);
+                    assert_eq!(ValueType::Number(NumberType::F32), actual);
+                }
+                r => unreachable!("result not expected: {:#?}", r),
+            },
+        );
+    }
+
+    #[test]
+    fn test_memory_instructions() {
+        with_wat(
+            r#"
+(module
+  (memory 1)
+  (func (param i32) (result i64)
+    (i64.store8 offset=3 (local.get 0) (i64.const 255))
+    (f64.store align=4 (i32.const 8) (f64.const 1.5))
+    (i64.store (memory.grow (i32.const 1)) (i64.const 0))
+    (i64.add
+      (i64.load32_u (memory.size))
+      (i64.load align=8 (local.get 0)))))
+"#,
+            |module| {
+                let r = validate_module(&module);
+                assert!(r.is_ok(), "{:#?}", r);
+            },
+        );
+    }
+
+    #[test]
+    fn test_invalid_memory_instructions() {
+        type Expected = fn(&VInstError) -> bool;
+        let cases: [(&str, Expected); 2] = [
+            (
+                "(module (func (result i32) (i32.load (i32.const 0))))",
+                |e| matches!(e, VInstError::NoMemoryAtIndex(0)),
+            ),
+            (
+                "(module (memory 1) (func (result i32) (i32.load16_u align=4 (i32.const 0))))",
+                |e| {
+                    matches!(
+                        e,
+                        VInstError::AlignmentTooLarge {
+                            align: 2,
+                            natural: 1
+                        }
+                    )
+                },
+            ),
+        ];
+        for (wat, expected) in cases {
+            with_wat(wat, |module| match validate_module(&module) {
+                Err(ValidationError::InstructionValidationError { error, .. }) => {
+                    assert!(expected(&error), "{}: {:?}", wat, error);
+                }
+                r => unreachable!("{}: result not expected: {:#?}", wat, r),
+            });
+        }
+    }
+
+    #[test]
+    fn test_parametric_instructions() {
+        with_wat(
+            r#"
+(module
+  (func (param i32 externref) (result i64 externref)
+    (drop (f32.const 1))
+    (select (i64.const 1) (i64.const 2) (local.get 0))
+    (select (result externref) (local.get 1) (ref.null extern) (local.get 0)))
+  (func (result i32)
+    unreachable
+    select
+    (ref.is_null)))
+"#,
+            |module| {
+                let r = validate_module(&module);
+                assert!(r.is_ok(), "{:#?}", r);
+            },
+        );
+    }
+
+    #[test]
+    fn test_invalid_untyped_select() {
+        let wats = [
+            "(module (func (param externref) (result externref) (select (local.get 0) (local.get 0) (i32.const 0))))",
+            "(module (func (result i32) (select (i32.const 0) (i64.const 0) (i32.const 0))))",
+        ];
+        for wat in wats.iter() {
+            with_wat(wat, |module| match validate_module(&module) {
+                Err(ValidationError::InstructionValidationError { error, .. }) => {
+                    assert!(
+                        matches!(error, VInstError::SelectOperandsInvalid(..)),
+                        "{}: {:?}",
+                        wat,
+                        error
+                    );
+                }
+                r => unreachable!("{}: result not expected: {:#?}", wat, r),
+            });
+        }
+    }
+
+    #[test]
+    fn test_table_and_bulk_memory_instructions() {
+        with_wat(
+            r#"
+(module
+  (table $t0 1 funcref)
+  (table $t1 1 funcref)
+  (table $ext 0 externref)
+  (memory 1)
+  (elem $e funcref (ref.func $f))
+  (data $d "hello")
+  (func $f (param externref)
+    (table.init $t1 $e (i32.const 0) (i32.const 0) (i32.const 1))
+    (elem.drop $e)
+    (table.copy $t0 $t1 (i32.const 0) (i32.const 0) (i32.const 1))
+    (table.set $t0 (i32.const 0) (table.get $t1 (i32.const 0)))
+    (drop (table.grow $ext (local.get 0) (table.size $ext)))
+    (table.fill $ext (i32.const 0) (local.get 0) (i32.const 0))
+    (memory.init $d (i32.const 0) (i32.const 0) (i32.const 5))
+    (data.drop $d)
+    (memory.copy (i32.const 8) (i32.const 0) (i32.const 5))
+    (memory.fill (i32.const 0) (i32.const 0) (i32.const 16))))
+"#,
+            |module| {
+                let r = validate_module(&module);
+                assert!(r.is_ok(), "{:#?}", r);
+            },
+        );
+    }
+
+    #[test]
+    fn test_invalid_table_instructions() {
+        let wats = [
+            "(module (table 1 funcref) (table 1 externref) (func (table.copy 0 1 (i32.const 0) (i32.const 0) (i32.const 0))))",
+            "(module (table 1 externref) (elem funcref) (func (table.init 0 0 (i32.const 0) (i32.const 0) (i32.const 0))))",
+        ];
+        for wat in wats.iter() {
+            with_wat(wat, |module| match validate_module(&module) {
+                Err(ValidationError::InstructionValidationError { error, .. }) => {
+                    assert!(
+                        matches!(error, VInstError::ReferenceTypeMismatch { .. }),
+                        "{}: {:?}",
+                        wat,
+                        error
+                    );
+                }
+                r => unreachable!("{}: result not expected: {:#?}", wat, r),
+            });
+        }
+    }
+
+    #[test]
+    fn test_memory_init_requires_data_count() {
+        with_wat(
+            r#"(module (memory 1) (data "x") (func (data.drop 0)))"#,
+            |mut module| {
+                assert!(validate_module(&module).is_ok());
+                module.sections.retain(|s| s.id() != SectionID::DataCount);
+                match validate_module(&module) {
+                    Err(ValidationError::InstructionValidationError { error, .. }) => {
+                        assert!(matches!(error, VInstError::DataCountSectionRequired))
+                    }
+                    r => unreachable!("result not expected: {:#?}", r),
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_saturating_truncation_and_sign_extension() {
+        with_wat(
+            r#"
+(module
+  (func (param f32 f64) (result i32 i64)
+    (i32.extend8_s (i32.trunc_sat_f32_u (local.get 0)))
+    (i64.extend32_s (i64.trunc_sat_f64_s (local.get 1)))))
+"#,
+            |module| {
+                let r = validate_module(&module);
+                assert!(r.is_ok(), "{:#?}", r);
+            },
+        );
+    }
+
+    #[test]
+    fn test_invalid_saturating_truncation() {
+        with_wat(
+            "(module (func (param f64) (result i32) (i32.trunc_sat_f32_s (local.get 0))))",
+            |module| match validate_module(&module) {
+                Err(ValidationError::InstructionValidationError {
+                    error: VInstError::PopValueTypeMismatch { expected, actual },
+                    ..
+                }) => {
+                    assert_eq!(ValueType::Number(NumberType::F32), expected);
+                    assert_eq!(ValueType::Number(NumberType::F64), actual);
+                }
+                r => unreachable!("result not expected: {:#?}", r),
+            },
+        );
+    }
+
+    #[test]
+    fn test_vector_instructions() {
+        with_wat(
+            r#"
+(module
+  (memory 1)
+  (func (param i32) (result i32 v128)
+    (i32x4.extract_lane 3
+      (i32x4.add (v128.const i32x4 1 2 3 4) (i32x4.splat (local.get 0))))
+    (i8x16.shuffle 0 17 2 19 4 21 6 23 8 25 10 27 12 29 14 31
+      (v128.load offset=16 (i32.const 0))
+      (v128.load32_lane 1 (i32.const 0) (v128.const i64x2 0 0)))))
+"#,
+            |module| {
+                let r = validate_module(&module);
+                assert!(r.is_ok(), "{:#?}", r);
+            },
+        );
+    }
+
+    #[test]
+    fn test_invalid_vector_lane() {
+        let mut wasm = wat::parse_str(
+            "(module (func (result i64) (i64x2.extract_lane 1 (v128.const i64x2 0 0))))",
+        )
+        .unwrap();
+        // patch the lane immediate, which directly precedes the final `end`
+        let lane = wasm.len() - 2;
+        assert_eq!(wasm[lane], 1);
+        wasm[lane] = 2;
+        let module = ModuleParsed::from_slice(&wasm).unwrap();
+        match validate_module(&module) {
+            Err(ValidationError::InstructionValidationError {
+                error: VInstError::LaneIndexOutOfRange { lane, lanes },
+                ..
+            }) => {
+                assert_eq!(2, lane);
+                assert_eq!(2, lanes);
+            }
+            r => unreachable!("result not expected: {:#?}", r),
+        }
+    }
+
     #[test]
     fn test_table_module() {
         with_wat("(module (table 1 10 funcref))", |module| {
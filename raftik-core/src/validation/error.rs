@@ -19,7 +19,7 @@ pub enum ValidationError {
         code_bodies: usize,
     },
 
-    #[error("instruction validation error")]
+    #[error("instruction validation error {desc}: {error}")]
     InstructionValidationError {
         desc: String,
         error: VInstError,
@@ -58,6 +58,9 @@ pub enum ValidationError {
 
     #[error("data count section {count} differs from data segment size {segment_size}")]
     DataCountSectionDiffers { count: usize, segment_size: usize },
+
+    #[error("malformed function body: {0}")]
+    MalformedFunctionBody(crate::binary::error::ParseError),
 }
 
 #[derive(Error, Debug)]
@@ -83,6 +86,39 @@ pub enum VInstError {
     #[error("no global found at index {0}")]
     NoGlobalAtIndex(u32),
 
+    #[error("no type found at index {0}")]
+    NoTypeAtIndex(u32),
+
+    #[error("no table found at index {0}")]
+    NoTableAtIndex(u32),
+
+    #[error("no memory found at index {0}")]
+    NoMemoryAtIndex(u32),
+
+    #[error("alignment 2^{align} exceeds natural alignment 2^{natural}")]
+    AlignmentTooLarge { align: u32, natural: u32 },
+
+    #[error("no data segment found at index {0}")]
+    NoDataSegmentAtIndex(u32),
+
+    #[error("data count section is required to refer to data segments")]
+    DataCountSectionRequired,
+
+    #[error("no element segment found at index {0}")]
+    NoElementAtIndex(u32),
+
+    #[error("reference type mismatch: expected {expected:?}, actual {actual:?}")]
+    ReferenceTypeMismatch {
+        expected: crate::ast::types::ReferenceType,
+        actual: crate::ast::types::ReferenceType,
+    },
+
+    #[error("lane index {lane} out of range for {lanes} lanes")]
+    LaneIndexOutOfRange { lane: u8, lanes: u32 },
+
+    #[error("no label found at index {0}")]
+    NoLabelAtIndex(u32),
+
     #[error("no function found at index {0}")]
     NoFunctionAtIndex(u32),
 
@@ -90,11 +126,32 @@ pub enum VInstError {
     NotIncludedInRefs(u32),
 
     #[error("opcode should be constant: {0:?}")]
-    OpcodeShouldBeConstant(crate::ast::instructions::Opcode),
+    OpcodeShouldBeConstant(Box<crate::ast::instructions::Opcode>),
 
     #[error("opcode should be constants: referring not constant; global get {0}")]
     GlobalGetShouldBeConstant(u32),
 
     #[error("stack value should be reference type, actual: {0:?}")]
     StackValueShouldBeRefType(crate::validation::instruction::StackValue),
+
+    #[error("else without matching if")]
+    ElseWithoutIf,
+
+    #[error("if without else must have identical start and end types")]
+    IfWithoutElseTypeMismatch,
+
+    #[error("br_table label {0} has different arity from default label")]
+    BrTableArityMismatch(u32),
+
+    #[error("select operands should be both numeric or both vector of the same type: {0:?}, {1:?}")]
+    SelectOperandsInvalid(
+        crate::validation::instruction::StackValue,
+        crate::validation::instruction::StackValue,
+    ),
+
+    #[error("typed select should have exactly one type, actual: {0}")]
+    SelectTypedArity(usize),
+
+    #[error("table {0} should have funcref element type")]
+    TableShouldBeFuncRef(u32),
 }
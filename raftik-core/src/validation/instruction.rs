@@ -9,8 +9,10 @@ use super::{
 };
 use crate::{
     ast::{
-        instructions::{Opcode, RawExpression},
-        types::{FunctionType, NumberType, ReferenceType, ValueType},
+        instructions::{BlockType, MemArg, Opcode, RawExpression},
+        types::{
+            FunctionType, MemoryType, NumberType, ReferenceType, TableType, ValueType, VectorType,
+        },
     },
     binary::parser::instructions::parse_instruction,
 };
@@ -34,6 +36,25 @@ impl StackValue {
     fn f64() -> Self {
         NumberType::F64.into()
     }
+    // Unknown may stand for any type, so it counts as both numeric and vector
+    fn is_num(&self) -> bool {
+        matches!(
+            self,
+            StackValue::Unknown | StackValue::Value(ValueType::Number(_))
+        )
+    }
+    fn is_vec(&self) -> bool {
+        matches!(
+            self,
+            StackValue::Unknown | StackValue::Value(ValueType::Vector(_))
+        )
+    }
+    fn is_ref(&self) -> bool {
+        matches!(
+            self,
+            StackValue::Unknown | StackValue::Value(ValueType::Reference(_))
+        )
+    }
 }
 
 impl From<NumberType> for StackValue {
@@ -48,20 +69,42 @@ impl From<ValueType> for StackValue {
     }
 }
 
+fn stack_values(types: &[ValueType]) -> Vec<StackValue> {
+    types.iter().map(|t| StackValue::Value(*t)).collect()
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    #[default]
+    Block,
+    Loop,
+    If,
+    Else,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct ControlFrame {
-    #[allow(dead_code)]
+    pub kind: FrameKind,
     pub start_types: Vec<ValueType>,
     pub end_types: Vec<ValueType>,
     pub height_of_value_stack: usize,
     pub unreachable: bool,
 }
 
+impl ControlFrame {
+    /// Types a branch to this frame has to provide.
+    fn label_types(&self) -> &[ValueType] {
+        match self.kind {
+            FrameKind::Loop => &self.start_types,
+            _ => &self.end_types,
+        }
+    }
+}
+
 trait ValueStack {
     fn push_val(&mut self, value: StackValue);
     fn pop_val(&mut self) -> Result<StackValue, VInstError>;
     fn pop_expect_val(&mut self, expected: StackValue) -> Result<StackValue, VInstError>;
-    #[allow(dead_code)]
     fn push_vals(&mut self, values: &[StackValue]);
     fn pop_vals(&mut self, expected_values: &[StackValue]) -> Result<Vec<StackValue>, VInstError>;
 
@@ -69,9 +112,15 @@ trait ValueStack {
 }
 
 trait ControlStack {
-    fn push_ctrl(&mut self, frame: ControlFrame);
+    fn push_ctrl(
+        &mut self,
+        kind: FrameKind,
+        start_types: Vec<ValueType>,
+        end_types: Vec<ValueType>,
+    );
     fn pop_ctrl(&mut self) -> Result<ControlFrame, VInstError>;
-    #[allow(dead_code)]
+    fn get_ctrl(&self, label: u32) -> Result<&ControlFrame, VInstError>;
+    fn get_outermost_ctrl(&self) -> Result<&ControlFrame, VInstError>;
     fn unreachable(&mut self);
     fn get_clone_of_control_stack(&self) -> Vec<ControlFrame>;
 }
@@ -102,6 +151,66 @@ fn get_func<'a>(i: u32, ctx: &'a Context) -> Result<&'a FunctionType, VInstError
         .ok_or(VInstError::NoFunctionAtIndex(i))
 }
 
+fn get_table<'a>(i: u32, ctx: &'a Context) -> Result<&'a TableType, VInstError> {
+    ctx.tables
+        .get(i as usize)
+        .cloned()
+        .ok_or(VInstError::NoTableAtIndex(i))
+}
+
+fn get_memory<'a>(i: u32, ctx: &'a Context) -> Result<&'a MemoryType, VInstError> {
+    ctx.memories
+        .get(i as usize)
+        .cloned()
+        .ok_or(VInstError::NoMemoryAtIndex(i))
+}
+
+fn get_element(i: u32, ctx: &Context) -> Result<ReferenceType, VInstError> {
+    ctx.elements
+        .get(i as usize)
+        .cloned()
+        .ok_or(VInstError::NoElementAtIndex(i))
+}
+
+fn check_data_segment(i: u32, ctx: &Context) -> Result<(), VInstError> {
+    if ctx.data_count.is_none() {
+        return Err(VInstError::DataCountSectionRequired);
+    }
+    ctx.data_segments
+        .get(i as usize)
+        .ok_or(VInstError::NoDataSegmentAtIndex(i))?;
+    Ok(())
+}
+
+fn check_ref_type(expected: ReferenceType, actual: ReferenceType) -> Result<(), VInstError> {
+    if expected != actual {
+        Err(VInstError::ReferenceTypeMismatch { expected, actual })
+    } else {
+        Ok(())
+    }
+}
+
+fn get_type<'a>(i: u32, ctx: &'a Context) -> Result<&'a FunctionType, VInstError> {
+    ctx.types
+        .get(i as usize)
+        .cloned()
+        .ok_or(VInstError::NoTypeAtIndex(i))
+}
+
+fn get_block_type(t: &BlockType, ctx: &Context) -> Result<FunctionType, VInstError> {
+    match t {
+        BlockType::Empty => Ok(FunctionType {
+            params: vec![],
+            results: vec![],
+        }),
+        BlockType::Value(v) => Ok(FunctionType {
+            params: vec![],
+            results: vec![*v],
+        }),
+        BlockType::TypeIndex(i) => get_type(*i, ctx).cloned(),
+    }
+}
+
 fn check_refs(i: u32, ctx: &Context) -> Result<(), VInstError> {
     if !ctx.refs.contains(&i) {
         Err(VInstError::NotIncludedInRefs(i))
@@ -110,6 +219,129 @@ fn check_refs(i: u32, ctx: &Context) -> Result<(), VInstError> {
     }
 }
 
+fn validate_opcode_control(
+    opcode: &Opcode,
+    stack: &mut (impl ValueStack + ControlStack),
+    ctx: &Context,
+) -> Result<(), VInstError> {
+    match opcode {
+        Opcode::Unreachable => stack.unreachable(),
+        Opcode::Nop => (),
+        Opcode::Block(t) | Opcode::Loop(t) => {
+            let t = get_block_type(t, ctx)?;
+            stack.pop_vals(&stack_values(&t.params))?;
+            let kind = if let Opcode::Loop(_) = opcode {
+                FrameKind::Loop
+            } else {
+                FrameKind::Block
+            };
+            stack.push_ctrl(kind, t.params, t.results);
+        }
+        Opcode::If(t) => {
+            let t = get_block_type(t, ctx)?;
+            stack.pop_expect_val(StackValue::i32())?;
+            stack.pop_vals(&stack_values(&t.params))?;
+            stack.push_ctrl(FrameKind::If, t.params, t.results);
+        }
+        Opcode::Else => {
+            let frame = stack.pop_ctrl()?;
+            if frame.kind != FrameKind::If {
+                return Err(VInstError::ElseWithoutIf);
+            }
+            stack.push_ctrl(FrameKind::Else, frame.start_types, frame.end_types);
+        }
+        Opcode::End => {
+            let frame = stack.pop_ctrl()?;
+            // `if` without `else` behaves as if the missing branch passed its inputs through
+            if frame.kind == FrameKind::If && frame.start_types != frame.end_types {
+                return Err(VInstError::IfWithoutElseTypeMismatch);
+            }
+            stack.push_vals(&stack_values(&frame.end_types));
+        }
+        Opcode::Br(l) => {
+            let label_types = stack_values(stack.get_ctrl(*l)?.label_types());
+            stack.pop_vals(&label_types)?;
+            stack.unreachable();
+        }
+        Opcode::BrIf(l) => {
+            stack.pop_expect_val(StackValue::i32())?;
+            let label_types = stack_values(stack.get_ctrl(*l)?.label_types());
+            let values = stack.pop_vals(&label_types)?;
+            stack.push_vals(&values);
+        }
+        Opcode::BrTable(labels, default) => {
+            stack.pop_expect_val(StackValue::i32())?;
+            let default_types = stack_values(stack.get_ctrl(*default)?.label_types());
+            for l in labels {
+                let label_types = stack_values(stack.get_ctrl(*l)?.label_types());
+                if label_types.len() != default_types.len() {
+                    return Err(VInstError::BrTableArityMismatch(*l));
+                }
+                let values = stack.pop_vals(&label_types)?;
+                stack.push_vals(&values);
+            }
+            stack.pop_vals(&default_types)?;
+            stack.unreachable();
+        }
+        Opcode::Return => {
+            let return_types = stack_values(&stack.get_outermost_ctrl()?.end_types);
+            stack.pop_vals(&return_types)?;
+            stack.unreachable();
+        }
+        Opcode::Call(i) => {
+            let t = get_func(*i, ctx)?;
+            stack.pop_vals(&stack_values(&t.params))?;
+            stack.push_vals(&stack_values(&t.results));
+        }
+        Opcode::CallIndirect(type_index, table_index) => {
+            let table = get_table(*table_index, ctx)?;
+            if table.ref_type != ReferenceType::FuncRef {
+                return Err(VInstError::TableShouldBeFuncRef(*table_index));
+            }
+            stack.pop_expect_val(StackValue::i32())?;
+            let t = get_type(*type_index, ctx)?;
+            stack.pop_vals(&stack_values(&t.params))?;
+            stack.push_vals(&stack_values(&t.results));
+        }
+        _ => unreachable!("opcode in control category not processed {:?}", opcode),
+    }
+    Ok(())
+}
+
+fn validate_opcode_parametric(
+    opcode: &Opcode,
+    stack: &mut (impl ValueStack + ControlStack),
+    _ctx: &Context,
+) -> Result<(), VInstError> {
+    match opcode {
+        Opcode::Drop => {
+            stack.pop_val()?;
+        }
+        Opcode::Select => {
+            stack.pop_expect_val(StackValue::i32())?;
+            let t1 = stack.pop_val()?;
+            let t2 = stack.pop_val()?;
+            let same_kind = (t1.is_num() && t2.is_num()) || (t1.is_vec() && t2.is_vec());
+            let same_type = t1 == t2 || t1 == StackValue::Unknown || t2 == StackValue::Unknown;
+            if !same_kind || !same_type {
+                return Err(VInstError::SelectOperandsInvalid(t1, t2));
+            }
+            stack.push_val(if t1 == StackValue::Unknown { t2 } else { t1 });
+        }
+        Opcode::SelectTyped(types) => {
+            let [t] = types[..] else {
+                return Err(VInstError::SelectTypedArity(types.len()));
+            };
+            stack.pop_expect_val(StackValue::i32())?;
+            stack.pop_expect_val(t.into())?;
+            stack.pop_expect_val(t.into())?;
+            stack.push_val(t.into());
+        }
+        _ => unreachable!("opcode in parametric category not processed {:?}", opcode),
+    }
+    Ok(())
+}
+
 fn validate_opcode_variable(
     opcode: &Opcode,
     stack: &mut (impl ValueStack + ControlStack),
@@ -138,17 +370,167 @@ fn validate_opcode_variable(
     Ok(())
 }
 
-fn validate_opcode_numeric(
+fn validate_opcode_table(
     opcode: &Opcode,
     stack: &mut (impl ValueStack + ControlStack),
-    _ctx: &mut Context,
+    ctx: &Context,
 ) -> Result<(), VInstError> {
     match opcode {
-        Opcode::I32Add => {
+        Opcode::TableGet(i) => {
+            let t = ValueType::Reference(get_table(*i, ctx)?.ref_type);
             stack.pop_expect_val(StackValue::i32())?;
+            stack.push_val(t.into());
+        }
+        Opcode::TableSet(i) => {
+            let t = ValueType::Reference(get_table(*i, ctx)?.ref_type);
+            stack.pop_expect_val(t.into())?;
             stack.pop_expect_val(StackValue::i32())?;
+        }
+        Opcode::TableInit(elem_index, table_index) => {
+            let table = get_table(*table_index, ctx)?;
+            let elem = get_element(*elem_index, ctx)?;
+            check_ref_type(table.ref_type, elem)?;
+            stack.pop_vals(&[StackValue::i32(), StackValue::i32(), StackValue::i32()])?;
+        }
+        Opcode::ElemDrop(i) => {
+            get_element(*i, ctx)?;
+        }
+        Opcode::TableCopy(dst, src) => {
+            let dst = get_table(*dst, ctx)?;
+            let src = get_table(*src, ctx)?;
+            check_ref_type(dst.ref_type, src.ref_type)?;
+            stack.pop_vals(&[StackValue::i32(), StackValue::i32(), StackValue::i32()])?;
+        }
+        Opcode::TableGrow(i) => {
+            let t = ValueType::Reference(get_table(*i, ctx)?.ref_type);
+            stack.pop_vals(&[t.into(), StackValue::i32()])?;
+            stack.push_val(StackValue::i32());
+        }
+        Opcode::TableSize(i) => {
+            get_table(*i, ctx)?;
             stack.push_val(StackValue::i32());
         }
+        Opcode::TableFill(i) => {
+            let t = ValueType::Reference(get_table(*i, ctx)?.ref_type);
+            stack.pop_vals(&[StackValue::i32(), t.into(), StackValue::i32()])?;
+        }
+        _ => unreachable!("opcode in table category not processed {:?}", opcode),
+    }
+    Ok(())
+}
+
+fn validate_opcode_memory(
+    opcode: &Opcode,
+    stack: &mut (impl ValueStack + ControlStack),
+    ctx: &Context,
+) -> Result<(), VInstError> {
+    match opcode {
+        Opcode::Memory(op, memarg) => {
+            check_memarg(memarg, op.natural_alignment(), ctx)?;
+            let params: Vec<StackValue> = op.params().iter().map(|&t| t.into()).collect();
+            let results: Vec<StackValue> = op.results().iter().map(|&t| t.into()).collect();
+            stack.pop_vals(&params)?;
+            stack.push_vals(&results);
+        }
+        Opcode::MemorySize(i) => {
+            get_memory(*i, ctx)?;
+            stack.push_val(StackValue::i32());
+        }
+        Opcode::MemoryGrow(i) => {
+            get_memory(*i, ctx)?;
+            stack.pop_expect_val(StackValue::i32())?;
+            stack.push_val(StackValue::i32());
+        }
+        Opcode::MemoryInit(data_index, memory_index) => {
+            get_memory(*memory_index, ctx)?;
+            check_data_segment(*data_index, ctx)?;
+            stack.pop_vals(&[StackValue::i32(), StackValue::i32(), StackValue::i32()])?;
+        }
+        Opcode::DataDrop(i) => {
+            check_data_segment(*i, ctx)?;
+        }
+        Opcode::MemoryCopy(dst, src) => {
+            get_memory(*dst, ctx)?;
+            get_memory(*src, ctx)?;
+            stack.pop_vals(&[StackValue::i32(), StackValue::i32(), StackValue::i32()])?;
+        }
+        Opcode::MemoryFill(i) => {
+            get_memory(*i, ctx)?;
+            stack.pop_vals(&[StackValue::i32(), StackValue::i32(), StackValue::i32()])?;
+        }
+        _ => unreachable!("opcode in memory category not processed {:?}", opcode),
+    }
+    Ok(())
+}
+
+fn check_memarg(memarg: &MemArg, natural_alignment: u32, ctx: &Context) -> Result<(), VInstError> {
+    get_memory(memarg.memory_index, ctx)?;
+    if memarg.align > natural_alignment {
+        return Err(VInstError::AlignmentTooLarge {
+            align: memarg.align,
+            natural: natural_alignment,
+        });
+    }
+    Ok(())
+}
+
+fn check_lane(lane: u8, lanes: u32) -> Result<(), VInstError> {
+    if u32::from(lane) >= lanes {
+        Err(VInstError::LaneIndexOutOfRange { lane, lanes })
+    } else {
+        Ok(())
+    }
+}
+
+fn validate_opcode_vector(
+    opcode: &Opcode,
+    stack: &mut (impl ValueStack + ControlStack),
+    ctx: &Context,
+) -> Result<(), VInstError> {
+    let (params, results) = match opcode {
+        Opcode::V128Const(_) => (&[][..], &[VectorType::V128.into()][..]),
+        Opcode::I8x16Shuffle(lanes) => {
+            for lane in lanes {
+                check_lane(*lane, 32)?;
+            }
+            let v128 = ValueType::from(VectorType::V128);
+            stack.pop_vals(&[v128.into(), v128.into()])?;
+            stack.push_val(v128.into());
+            return Ok(());
+        }
+        Opcode::VectorLane(op, lane) => {
+            check_lane(*lane, op.lanes())?;
+            (op.params(), op.results())
+        }
+        Opcode::VectorMemory(op, memarg) => {
+            check_memarg(memarg, op.natural_alignment(), ctx)?;
+            (op.params(), op.results())
+        }
+        Opcode::VectorMemoryLane(op, memarg, lane) => {
+            check_memarg(memarg, op.natural_alignment(), ctx)?;
+            check_lane(*lane, op.lanes())?;
+            (op.params(), op.results())
+        }
+        Opcode::Vector(op) => (op.params(), op.results()),
+        _ => unreachable!("opcode in vector category not processed {:?}", opcode),
+    };
+    stack.pop_vals(&stack_values(params))?;
+    stack.push_vals(&stack_values(results));
+    Ok(())
+}
+
+fn validate_opcode_numeric(
+    opcode: &Opcode,
+    stack: &mut (impl ValueStack + ControlStack),
+    _ctx: &mut Context,
+) -> Result<(), VInstError> {
+    match opcode {
+        Opcode::Numeric(op) => {
+            let params: Vec<StackValue> = op.params().iter().map(|&t| t.into()).collect();
+            let results: Vec<StackValue> = op.results().iter().map(|&t| t.into()).collect();
+            stack.pop_vals(&params)?;
+            stack.push_vals(&results);
+        }
         _ => unreachable!("opcode in numeric category not processed {:?}", opcode),
     }
     Ok(())
@@ -181,9 +563,9 @@ fn validate_opcode_reference(
         Opcode::RefNull(t) => stack.push_val(ValueType::Reference(*t).into()),
         Opcode::RefIsNull => {
             let v = stack.pop_val()?;
-            let StackValue::Value(ValueType::Reference(_)) = v else {
+            if !v.is_ref() {
                 return Err(VInstError::StackValueShouldBeRefType(v));
-            };
+            }
             stack.push_val(StackValue::i32());
         }
         Opcode::RefFunc(i) => {
@@ -202,23 +584,38 @@ fn validate_opcode(
     ctx: &mut Context,
 ) -> Result<(), VInstError> {
     match opcode.category() {
+        crate::ast::instructions::OpcodeCategory::Control => {
+            validate_opcode_control(opcode, stack, ctx)?
+        }
+        crate::ast::instructions::OpcodeCategory::Parametric => {
+            validate_opcode_parametric(opcode, stack, ctx)?
+        }
         crate::ast::instructions::OpcodeCategory::Variable => {
             validate_opcode_variable(opcode, stack, ctx)?
         }
         crate::ast::instructions::OpcodeCategory::Reference => {
             validate_opcode_reference(opcode, stack, ctx)?
         }
+        crate::ast::instructions::OpcodeCategory::Table => {
+            validate_opcode_table(opcode, stack, ctx)?
+        }
+        crate::ast::instructions::OpcodeCategory::Memory => {
+            validate_opcode_memory(opcode, stack, ctx)?
+        }
         crate::ast::instructions::OpcodeCategory::NumericConst => {
             validate_opcode_numeric_const(opcode, stack, ctx)?
         }
         crate::ast::instructions::OpcodeCategory::Numeric => {
             validate_opcode_numeric(opcode, stack, ctx)?
         }
+        crate::ast::instructions::OpcodeCategory::Vector => {
+            validate_opcode_vector(opcode, stack, ctx)?
+        }
     }
 
     if ctx.instructions_should_be_constant {
         if !opcode.is_constant() {
-            return Err(VInstError::OpcodeShouldBeConstant(*opcode));
+            return Err(VInstError::OpcodeShouldBeConstant(Box::new(opcode.clone())));
         }
         if let Opcode::GlobalGet(i) = opcode {
             let g = *ctx.globals[*i as usize].t();
@@ -239,7 +636,7 @@ fn validate_instructions(
 ) -> Result<(), VInstError> {
     let mut it = iterator(instructions, parse_instruction);
     for opcode in &mut it {
-        progress.push(opcode);
+        progress.push(opcode.clone());
         validate_opcode(&opcode, stack, ctx)?;
     }
     it.finish()
@@ -258,13 +655,9 @@ pub fn validate_raw_expression(
     let mut progress = Vec::new();
 
     // push outermost control frame (regarding as a block)
-    stack.push_ctrl(ControlFrame {
-        start_types: vec![],
-        end_types: t.results.to_vec(),
-        ..Default::default()
-    });
+    stack.push_ctrl(FrameKind::Block, vec![], t.results.to_vec());
 
-    validate_instructions(expr.instructions, &mut stack, ctx, &mut progress).map_err(|e| {
+    validate_instructions(&expr.instructions, &mut stack, ctx, &mut progress).map_err(|e| {
         ValidationError::InstructionValidationError {
             desc: desc_on_error,
             error: e,
@@ -280,7 +673,7 @@ pub fn collect_funcref_in_expression(
     r: &mut HashSet<u32>,
     desc_on_error: String,
 ) -> Result<(), ValidationError> {
-    let mut it = iterator(expr.instructions, parse_instruction);
+    let mut it = iterator(&expr.instructions[..], parse_instruction);
     for opcode in &mut it {
         if let Opcode::RefFunc(i) = opcode {
             r.insert(i);
@@ -1,5 +1,5 @@
-use super::{ControlFrame, ControlStack, StackValue, ValueStack};
-use crate::validation::error::VInstError;
+use super::{ControlFrame, ControlStack, FrameKind, StackValue, ValueStack, stack_values};
+use crate::{ast::types::ValueType, validation::error::VInstError};
 
 macro_rules! controls_last {
     ($controls: expr) => {{ $controls.last().ok_or(VInstError::ControlStackUnderflow) }};
@@ -54,11 +54,14 @@ impl ValueStack for TheStack {
     }
 
     fn pop_vals(&mut self, expected_values: &[StackValue]) -> Result<Vec<StackValue>, VInstError> {
-        expected_values
+        let mut values = expected_values
             .iter()
             .rev()
             .map(|v| self.pop_expect_val(*v))
-            .collect()
+            .collect::<Result<Vec<_>, _>>()?;
+        // return values in the order they were on the stack
+        values.reverse();
+        Ok(values)
     }
 
     fn get_clone_of_value_stack(&self) -> Vec<StackValue> {
@@ -67,20 +70,26 @@ impl ValueStack for TheStack {
 }
 
 impl ControlStack for TheStack {
-    fn push_ctrl(&mut self, frame: ControlFrame) {
-        self.controls.push(frame);
+    fn push_ctrl(
+        &mut self,
+        kind: FrameKind,
+        start_types: Vec<ValueType>,
+        end_types: Vec<ValueType>,
+    ) {
+        let start_values = stack_values(&start_types);
+        self.controls.push(ControlFrame {
+            kind,
+            start_types,
+            end_types,
+            height_of_value_stack: self.values.len(),
+            unreachable: false,
+        });
+        self.push_vals(&start_values);
     }
 
     fn pop_ctrl(&mut self) -> Result<ControlFrame, VInstError> {
         let frame = controls_last!(self.controls)?;
-        self.pop_vals(
-            frame
-                .end_types
-                .iter()
-                .map(|v| StackValue::Value(*v))
-                .collect::<Vec<_>>()
-                .as_ref(),
-        )?;
+        self.pop_vals(&stack_values(&frame.end_types))?;
         let frame = self
             .controls
             .pop()
@@ -92,8 +101,23 @@ impl ControlStack for TheStack {
         }
     }
 
+    fn get_ctrl(&self, label: u32) -> Result<&ControlFrame, VInstError> {
+        self.controls
+            .iter()
+            .rev()
+            .nth(label as usize)
+            .ok_or(VInstError::NoLabelAtIndex(label))
+    }
+
+    fn get_outermost_ctrl(&self) -> Result<&ControlFrame, VInstError> {
+        self.controls
+            .first()
+            .ok_or(VInstError::ControlStackUnderflow)
+    }
+
     fn unreachable(&mut self) {
         if let Some(frame) = self.controls.last_mut() {
+            self.values.truncate(frame.height_of_value_stack);
             frame.unreachable = true;
         }
     }
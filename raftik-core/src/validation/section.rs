@@ -1,6 +1,7 @@
 use super::{Context, ItemFilter, error::ValidationError, types};
 use crate::{
     ast::{
+        custom::NameSection,
         section::{
             CodeSection, DataSection, ElementSection, ExportSection, FunctionSection,
             GlobalSection, ImportSection, MemorySection, StartSection, TableSection,
@@ -59,9 +60,11 @@ pub fn validate_export_section(
 }
 
 pub fn validate_code_section<'a>(
-    code_section: &'a CodeSection<'a>,
+    code_section: &CodeSection,
     context: &mut Context<'a>,
+    names: &NameSection,
 ) -> Result<(), ValidationError> {
+    let imported_funcs = context.functions.imported().len();
     let funcs_declared: Vec<_> = context
         .functions
         .internal()
@@ -92,7 +95,11 @@ pub fn validate_code_section<'a>(
             context,
             func_type,
             &funcbody.expression,
-            format!("at code section #{}", i),
+            format!(
+                "at code section #{} ({})",
+                i,
+                names.describe_function((imported_funcs + i) as u32)
+            ),
         )?;
     }
     context.locals.clear();